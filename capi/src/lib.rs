@@ -0,0 +1,2 @@
+// The functions of the ffi module are exported by the cdylib and staticlib of this crate
+pub use qbjs_deserializer::ffi::*;
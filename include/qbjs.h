@@ -0,0 +1,188 @@
+#ifndef QBJS_H
+#define QBJS_H
+
+/* Generated with cbindgen from src/ffi.rs, do not edit */
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+// Error codes set in `qbjs_error`.
+typedef enum {
+  QBJS_OK = 0,
+  // A null pointer or a key that isn't valid UTF-8.
+  QBJS_INVALID_ARGUMENT,
+  // The bytes aren't a valid document.
+  QBJS_DESERIALIZE_ERROR,
+  // A value of the document couldn't be decoded.
+  QBJS_READ_ERROR,
+  // The value doesn't have the type the function expects.
+  QBJS_TYPE_MISMATCH,
+  // The key or index isn't in the container.
+  QBJS_NOT_FOUND,
+  // The string holds a nul character, it can't be returned as a C string.
+  QBJS_INTERIOR_NUL,
+} qbjs_error_code;
+
+// Types of the values, both numbers stored as integers and as doubles are numbers.
+typedef enum {
+  QBJS_NULL,
+  QBJS_BOOL,
+  QBJS_NUMBER,
+  QBJS_STRING,
+  QBJS_ARRAY,
+  QBJS_OBJECT,
+} qbjs_type;
+
+// Analyzed copy of a document, values are decoded when they are queried.
+typedef struct qbjs_document qbjs_document;
+
+// Value of a document, valid as long as its document.
+typedef struct qbjs_value qbjs_value;
+
+// Set by functions failing with a non null `err` argument.
+// The message, when not null, must be freed with `qbjs_free`.
+typedef struct {
+  qbjs_error_code code;
+  char *message;
+} qbjs_error;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Decodes the document to JSON text, written to `out` which must be freed with `qbjs_free`.
+// An empty document decodes to `{}`.
+//
+// # Safety
+//
+// `data` must point to `length` readable bytes, or be null when `length` is 0.
+// `out` must be writable and `err` either null or writable.
+bool qbjs_to_json(const uint8_t *data, size_t length, char **out, qbjs_error *err);
+
+// Frees a string returned by this library, null is ignored.
+//
+// # Safety
+//
+// `string` must have been returned by this library and not freed yet.
+void qbjs_free(char *string);
+
+// Copies and analyzes the document, returns null on failure.
+// The document must be freed with `qbjs_document_free`.
+//
+// # Safety
+//
+// `data` must point to `length` readable bytes, or be null when `length` is 0.
+// `err` must be either null or writable.
+qbjs_document *qbjs_document_new(const uint8_t *data, size_t length, qbjs_error *err);
+
+// Frees the document and invalidates its values, null is ignored.
+//
+// # Safety
+//
+// `document` must have been returned by `qbjs_document_new` and not freed yet.
+void qbjs_document_free(qbjs_document *document);
+
+// Returns the root array or object of the document, null when `document` is null.
+//
+// # Safety
+//
+// `document` must be null or a live document.
+const qbjs_value *qbjs_document_root(const qbjs_document *document);
+
+// Returns the type of the value, `QBJS_NULL` when `value` is null.
+//
+// # Safety
+//
+// `value` must be null or a value of a live document.
+qbjs_type qbjs_value_type(const qbjs_value *value);
+
+// Returns the number of values of an array or entries of an object, 0 for other values.
+//
+// # Safety
+//
+// `value` must be null or a value of a live document.
+size_t qbjs_value_length(const qbjs_value *value);
+
+// Returns the value of the object's entry with the given key, or null on failure.
+// When the object holds the key several times, the last entry is returned.
+//
+// # Safety
+//
+// `document` must be a live document and `value` one of its values.
+// `key` must be a nul terminated string and `err` either null or writable.
+const qbjs_value *qbjs_value_get(const qbjs_document *document,
+                                 const qbjs_value *value,
+                                 const char *key,
+                                 qbjs_error *err);
+
+// Returns the value at the given index of an array, or of the entry at the given index of
+// an object in storage order, or null on failure.
+//
+// # Safety
+//
+// `value` must be a value of a live document and `err` either null or writable.
+const qbjs_value *qbjs_value_index(const qbjs_value *value, size_t index, qbjs_error *err);
+
+// Writes the key of the object's entry at the given index to `out`, which must be freed
+// with `qbjs_free`.
+//
+// # Safety
+//
+// `document` must be a live document and `value` one of its values.
+// `out` must be writable and `err` either null or writable.
+bool qbjs_value_key(const qbjs_document *document,
+                    const qbjs_value *value,
+                    size_t index,
+                    char **out,
+                    qbjs_error *err);
+
+// Writes the boolean to `out`.
+//
+// # Safety
+//
+// `document` must be a live document and `value` one of its values.
+// `out` must be writable and `err` either null or writable.
+bool qbjs_value_bool(const qbjs_document *document,
+                     const qbjs_value *value,
+                     bool *out,
+                     qbjs_error *err);
+
+// Writes the number to `out`, whether it's stored as an integer or a double.
+//
+// # Safety
+//
+// `document` must be a live document and `value` one of its values.
+// `out` must be writable and `err` either null or writable.
+bool qbjs_value_number(const qbjs_document *document,
+                       const qbjs_value *value,
+                       double *out,
+                       qbjs_error *err);
+
+// Writes the UTF-8 string to `out`, which must be freed with `qbjs_free`.
+//
+// # Safety
+//
+// `document` must be a live document and `value` one of its values.
+// `out` must be writable and `err` either null or writable.
+bool qbjs_value_string(const qbjs_document *document,
+                       const qbjs_value *value,
+                       char **out,
+                       qbjs_error *err);
+
+// Decodes the value to JSON text, written to `out` which must be freed with `qbjs_free`.
+//
+// # Safety
+//
+// `document` must be a live document and `value` one of its values.
+// `out` must be writable and `err` either null or writable.
+bool qbjs_value_to_json(const qbjs_document *document,
+                        const qbjs_value *value,
+                        char **out,
+                        qbjs_error *err);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* QBJS_H */
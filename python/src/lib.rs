@@ -0,0 +1,202 @@
+use std::collections::HashSet;
+
+use pyo3::create_exception;
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::pybacked::PyBackedBytes;
+use pyo3::types::{PyBool, PyBytes, PyDict, PyFloat, PyInt, PyList, PyString, PyTuple};
+use pyo3::PyTypeInfo;
+use serde_json::{Map, Number, Value};
+
+use qbjs_deserializer::qbjs::{self as decoder, write};
+
+create_exception!(
+    qbjs,
+    DeserializeError,
+    PyValueError,
+    "Raised by loads, details holds the error of the document when there's one."
+);
+create_exception!(qbjs, AnalysisError, DeserializeError);
+create_exception!(qbjs, InsufficientDataError, DeserializeError);
+create_exception!(qbjs, InvalidRootContainerError, DeserializeError);
+create_exception!(qbjs, InvalidJsonPointerError, DeserializeError);
+create_exception!(qbjs, ReadError, DeserializeError);
+create_exception!(
+    qbjs,
+    EncodeError,
+    PyValueError,
+    "Raised by dumps for objects that can't be stored in a document."
+);
+
+// Exceptions get the readable description of the error as message and the variant of
+// the error they wrap, if any, as details
+fn exception<T: PyTypeInfo>(py: Python<'_>, message: String, details: Option<String>) -> PyErr {
+    let err = PyErr::new::<T, _>(message);
+    // Setting an attribute of a fresh exception instance can't fail
+    let _ = err.value(py).setattr("details", details);
+    err
+}
+
+fn deserialize_error(py: Python<'_>, err: decoder::DeserializeError) -> PyErr {
+    let message = err.to_string();
+    match err {
+        decoder::DeserializeError::AnalysisError(error) => {
+            exception::<AnalysisError>(py, message, Some(format!("{:?}", error)))
+        }
+        decoder::DeserializeError::InsufficientData => {
+            exception::<InsufficientDataError>(py, message, None)
+        }
+        decoder::DeserializeError::InvalidRootContainer => {
+            exception::<InvalidRootContainerError>(py, message, None)
+        }
+        decoder::DeserializeError::InvalidJsonPointer(pointer) => {
+            exception::<InvalidJsonPointerError>(py, message, Some(pointer))
+        }
+        decoder::DeserializeError::ReadError(error) => {
+            exception::<ReadError>(py, message, Some(format!("{:?}", error)))
+        }
+        decoder::DeserializeError::IoError(kind) => {
+            exception::<DeserializeError>(py, message, Some(format!("{:?}", kind)))
+        }
+        decoder::DeserializeError::EncodeError(error) => encode_error(py, error),
+        _ => exception::<DeserializeError>(py, message, None),
+    }
+}
+
+fn encode_error(py: Python<'_>, err: write::EncodeError) -> PyErr {
+    exception::<EncodeError>(py, err.to_string(), Some(format!("{:?}", err)))
+}
+
+fn to_python<'py>(py: Python<'py>, value: &Value) -> PyResult<Bound<'py, PyAny>> {
+    Ok(match value {
+        Value::Null => py.None().into_bound(py),
+        Value::Bool(value) => PyBool::new(py, *value).to_owned().into_any(),
+        Value::Number(number) => match number.as_i64() {
+            Some(number) => number.into_pyobject(py)?.into_any(),
+            None => PyFloat::new(py, number.as_f64().unwrap_or(f64::NAN)).into_any(),
+        },
+        Value::String(string) => PyString::new(py, string).into_any(),
+        Value::Array(values) => {
+            let values = values
+                .iter()
+                .map(|value| to_python(py, value))
+                .collect::<PyResult<Vec<_>>>()?;
+            PyList::new(py, values)?.into_any()
+        }
+        Value::Object(entries) => {
+            let dict = PyDict::new(py);
+            for (key, value) in entries {
+                dict.set_item(key, to_python(py, value)?)?;
+            }
+            dict.into_any()
+        }
+    })
+}
+
+// Containers being converted, to reject circular references the same way json.dumps does
+type Markers = HashSet<usize>;
+
+fn from_python(object: &Bound<'_, PyAny>, markers: &mut Markers) -> PyResult<Value> {
+    if object.is_none() {
+        return Ok(Value::Null);
+    }
+    // Before integers, bool is a subclass of int
+    if let Ok(value) = object.downcast::<PyBool>() {
+        return Ok(Value::Bool(value.is_true()));
+    }
+    if object.is_instance_of::<PyInt>() {
+        // Documents store numbers as doubles, so integers out of the i64 range are rounded
+        if let Ok(value) = object.extract::<i64>() {
+            return Ok(Value::from(value));
+        }
+        return float_to_json(object.extract::<f64>()?);
+    }
+    if let Ok(value) = object.downcast::<PyFloat>() {
+        return float_to_json(value.value());
+    }
+    if let Ok(value) = object.downcast::<PyString>() {
+        return Ok(Value::String(value.to_str()?.to_owned()));
+    }
+
+    let is_container = object.is_instance_of::<PyList>()
+        || object.is_instance_of::<PyTuple>()
+        || object.is_instance_of::<PyDict>();
+    if !is_container {
+        return Err(PyTypeError::new_err(format!(
+            "Object of type {} is not qbjs serializable",
+            object.get_type().name()?
+        )));
+    }
+
+    let marker = object.as_ptr() as usize;
+    if !markers.insert(marker) {
+        return Err(PyValueError::new_err("Circular reference detected"));
+    }
+    let value = if let Ok(dict) = object.downcast::<PyDict>() {
+        let mut entries = Map::new();
+        for (key, value) in dict.iter() {
+            let key = key
+                .downcast::<PyString>()
+                .map_err(|_| PyTypeError::new_err("keys must be str"))?;
+            entries.insert(key.to_str()?.to_owned(), from_python(&value, markers)?);
+        }
+        Value::Object(entries)
+    } else {
+        Value::Array(
+            object
+                .try_iter()?
+                .map(|value| from_python(&value?, markers))
+                .collect::<PyResult<_>>()?,
+        )
+    };
+    markers.remove(&marker);
+
+    Ok(value)
+}
+
+fn float_to_json(value: f64) -> PyResult<Value> {
+    Number::from_f64(value)
+        .map(Value::Number)
+        .ok_or_else(|| PyValueError::new_err("Out of range float values are not JSON compliant"))
+}
+
+/// Decodes a document from bytes or bytearray to a dict or a list.
+#[pyfunction]
+fn loads(py: Python<'_>, data: PyBackedBytes) -> PyResult<PyObject> {
+    let value = decoder::deserialize_to_json(&data).map_err(|err| deserialize_error(py, err))?;
+    Ok(to_python(py, &value)?.unbind())
+}
+
+/// Encodes a dict or a list to a document, the same way Qt5 does.
+#[pyfunction]
+fn dumps<'py>(py: Python<'py>, obj: &Bound<'py, PyAny>) -> PyResult<Bound<'py, PyBytes>> {
+    let value = from_python(obj, &mut Markers::new())?;
+    let qbjs = write::encode_document(&write::Value::from_json(&value))
+        .map_err(|err| encode_error(py, err))?;
+    Ok(PyBytes::new(py, &qbjs))
+}
+
+#[pymodule]
+#[pyo3(name = "qbjs")]
+pub fn qbjs_module(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    let py = module.py();
+    module.add_function(wrap_pyfunction!(loads, module)?)?;
+    module.add_function(wrap_pyfunction!(dumps, module)?)?;
+    module.add("DeserializeError", py.get_type::<DeserializeError>())?;
+    module.add("AnalysisError", py.get_type::<AnalysisError>())?;
+    module.add(
+        "InsufficientDataError",
+        py.get_type::<InsufficientDataError>(),
+    )?;
+    module.add(
+        "InvalidRootContainerError",
+        py.get_type::<InvalidRootContainerError>(),
+    )?;
+    module.add(
+        "InvalidJsonPointerError",
+        py.get_type::<InvalidJsonPointerError>(),
+    )?;
+    module.add("ReadError", py.get_type::<ReadError>())?;
+    module.add("EncodeError", py.get_type::<EncodeError>())?;
+    Ok(())
+}
@@ -0,0 +1,110 @@
+use pyo3::ffi::c_str;
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict, PyModule};
+
+use std::ffi::CStr;
+use std::fs;
+
+fn read_qbjs_test_file(file_name: &str) -> Vec<u8> {
+    let file_path = format!(
+        "{}/../tests/test_data/qbjs_data/{}.qbjs",
+        env!("CARGO_MANIFEST_DIR"),
+        file_name
+    );
+    fs::read(&file_path).unwrap_or_else(|_| panic!("Couldn't read file: {}", file_path))
+}
+
+// Runs the Python code with the module imported as qbjs and the document bytes as example
+fn run_python(code: &CStr) {
+    let example = read_qbjs_test_file("400_example_from_qbjs_source_document");
+    Python::with_gil(|py| {
+        let module = PyModule::new(py, "qbjs").unwrap();
+        qbjs::qbjs_module(&module).unwrap();
+
+        let globals = PyDict::new(py);
+        globals.set_item("qbjs", module).unwrap();
+        globals
+            .set_item("example", PyBytes::new(py, &example))
+            .unwrap();
+        if let Err(err) = py.run(code, Some(&globals), None) {
+            err.display(py);
+            panic!("Python code failed");
+        }
+    });
+}
+
+#[test]
+fn python_loads() {
+    run_python(c_str!(
+        r#"
+document = qbjs.loads(example)
+assert document["firstName"] == "John", document
+assert document["age"] == 25 and isinstance(document["age"], int)
+assert document["phoneNumber"][1] == {"type": "fax", "number": "646 555-4567"}
+assert qbjs.loads(bytearray(example)) == document
+assert qbjs.loads(b"") == {}
+"#
+    ));
+}
+
+#[test]
+fn python_dumps() {
+    run_python(c_str!(
+        r#"
+# Documents are encoded the same way Qt does
+assert qbjs.dumps(qbjs.loads(example)) == example
+
+value = {"text": "héllo 世界", "values": [None, True, 1.5, -3, 2**40], "tuple": (1,)}
+assert qbjs.loads(qbjs.dumps(value)) == dict(value, tuple=[1])
+
+for invalid, error in [
+    ({1: "key"}, TypeError),
+    ({"set": {1}}, TypeError),
+    ([float("nan")], ValueError),
+    (1, qbjs.EncodeError),
+]:
+    try:
+        qbjs.dumps(invalid)
+        assert False, invalid
+    except error:
+        pass
+
+circular = []
+circular.append(circular)
+try:
+    qbjs.dumps(circular)
+    assert False
+except ValueError as err:
+    assert "Circular" in str(err)
+"#
+    ));
+}
+
+#[test]
+fn python_exceptions() {
+    run_python(c_str!(
+        r#"
+try:
+    qbjs.loads(b"qbjs")
+    assert False
+except qbjs.InsufficientDataError as err:
+    assert isinstance(err, qbjs.DeserializeError) and isinstance(err, ValueError)
+    assert str(err) == "invalid qbjs document: the data ends before the document", str(err)
+    assert err.details is None
+
+try:
+    qbjs.loads(b"json" + example[4:])
+    assert False
+except qbjs.AnalysisError as err:
+    assert str(err) == "invalid qbjs document: the header doesn't start with \"qbjs\"", str(err)
+    assert err.details == "HeaderAnalysisError(InvalidTag)", err.details
+
+try:
+    qbjs.dumps(1)
+    assert False
+except qbjs.EncodeError as err:
+    assert str(err) == "the root value isn't an array or an object", str(err)
+    assert err.details == "InvalidRootContainer", err.details
+"#
+    ));
+}
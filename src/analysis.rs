@@ -1,3 +1,6 @@
+use std::error::Error;
+use std::fmt;
+
 use crate::type_conversions::as_u32;
 
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -19,6 +22,45 @@ impl AnalysisError {
     }
 }
 
+impl fmt::Display for AnalysisError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            AnalysisError::HeaderAnalysisError(err) => match err {
+                header::Error::InvalidLength => "the header is shorter than 8 bytes",
+                header::Error::InvalidTag => "the header doesn't start with \"qbjs\"",
+                header::Error::InvalidVersion => "the header version isn't 1",
+            },
+            AnalysisError::MetadataAnalysisError(err) => match err {
+                metadata::Error::InvalidContainerBaseLength => {
+                    "a container base is past the end of the data"
+                }
+                metadata::Error::InvalidValueHeaderSize => {
+                    "a value header is past the end of the data"
+                }
+                metadata::Error::InvalidOffsetTableLength => {
+                    "an offset table is past the end of the data"
+                }
+            },
+            AnalysisError::DataAnalysisError(err) => match err {
+                data::Error::UnknownQtValue => "a value header has an unknown type",
+                data::Error::InvalidArrayContainer => {
+                    "an array value refers to an object container"
+                }
+                data::Error::InvalidObjectContainer => {
+                    "an object value refers to an array container"
+                }
+                data::Error::InvalidValueLength => "a string size is past the end of the data",
+                data::Error::InvalidContainerOffset => {
+                    "a nested container isn't stored inside its parent container"
+                }
+            },
+        };
+        f.write_str(message)
+    }
+}
+
+impl Error for AnalysisError {}
+
 pub mod data {
     use std::ops::Range;
 
@@ -28,6 +70,7 @@ pub mod data {
         InvalidArrayContainer, // Means we tried to deserialize and array but the container base's object flag is set
         InvalidObjectContainer, // Means we tried to deserialize and array but the container base's object flag isn't set
         InvalidValueLength,
+        InvalidContainerOffset, // Means a nested container starts before the end of its parent's base or past its size
     }
 
     #[derive(Debug)]
@@ -37,6 +80,8 @@ pub mod data {
 
     #[derive(Debug)]
     pub struct Array {
+        pub container: ByteField, // From the container base to the end of the array
+        pub table: ByteField,     // Value headers
         pub values: Vec<Value>,
     }
 
@@ -48,12 +93,15 @@ pub mod data {
 
     #[derive(Debug)]
     pub struct Entry {
+        pub position: usize, // Value header, the key follows it
         pub key: Key,
         pub value: Value,
     }
 
     #[derive(Debug)]
     pub struct Object {
+        pub container: ByteField, // From the container base to the end of the object
+        pub table: ByteField,     // Offsets of the entries
         pub entries: Vec<Entry>,
     }
 
@@ -115,6 +163,11 @@ pub mod header {
             Ok(QbjsHeader::new(tag, version))
         }
     }
+
+    // Whether the data starts with a valid header, whatever follows it
+    pub fn has_qbjs_header(data: &[u8]) -> bool {
+        QbjsHeader::from_data(data).is_ok()
+    }
 }
 
 pub mod metadata {
@@ -125,6 +178,7 @@ pub mod metadata {
     pub enum Error {
         InvalidContainerBaseLength,
         InvalidValueHeaderSize,
+        InvalidOffsetTableLength,
     }
 
     pub const CONTAINER_BASE_LENGTH: usize = 12;
@@ -176,6 +230,7 @@ pub mod metadata {
     }
 
     pub const VALUE_HEADER_BYTE_SIZE: usize = 4;
+    pub const OFFSET_TABLE_ENTRY_BYTE_SIZE: usize = 4;
     const QT_VALUE_TYPE_MASK: u8 = 0b111;
     const LATIN_OR_INT_VALUE_FLAG_MASK: u8 = 0b1 << 3;
     const LATIN_KEY_FLAG_MASK: u8 = 0b1 << 4;
@@ -217,16 +272,15 @@ pub fn analyze_document(data: &[u8]) -> Result<data::Value, AnalysisError> {
 
     header::QbjsHeader::from_data(header_data).map_err(AnalysisError::header)?;
 
-    let container_base_range =
-        header::HEADER_LENGTH..(header::HEADER_LENGTH + metadata::CONTAINER_BASE_LENGTH);
-
-    let container_base_data = data
-        .get(container_base_range)
-        .ok_or(metadata::Error::InvalidContainerBaseLength)
-        .map_err(AnalysisError::metadata)?;
+    analyze_container(data, header::HEADER_LENGTH).map(|(value, _)| value)
+}
 
-    let container_base =
-        metadata::ContainerBase::from_data(container_base_data).map_err(AnalysisError::metadata)?;
+// Analyzes the array or object whose base starts at the given position
+pub(crate) fn analyze_container(
+    data: &[u8],
+    base_start: usize,
+) -> Result<(data::Value, usize), AnalysisError> {
+    let container_base = analyze_container_base(data, base_start)?;
 
     let analyze_container = if container_base.is_object {
         analyze_object
@@ -234,20 +288,72 @@ pub fn analyze_document(data: &[u8]) -> Result<data::Value, AnalysisError> {
         analyze_array
     };
 
-    analyze_container(data, header::HEADER_LENGTH).map(|(value, _)| value)
+    analyze_container(data, base_start)
 }
 
-fn analyze_array(data: &[u8], base_start: usize) -> Result<(data::Value, usize), AnalysisError> {
+pub(crate) fn analyze_container_base(
+    data: &[u8],
+    base_start: usize,
+) -> Result<metadata::ContainerBase, AnalysisError> {
     let base_end = base_start + metadata::CONTAINER_BASE_LENGTH;
     let base_range = base_start..base_end;
 
-    let array_info_data = data
+    let base_data = data
         .get(base_range)
         .ok_or(metadata::Error::InvalidContainerBaseLength)
         .map_err(AnalysisError::metadata)?;
 
-    let array_info =
-        metadata::ContainerBase::from_data(array_info_data).map_err(AnalysisError::metadata)?;
+    metadata::ContainerBase::from_data(base_data).map_err(AnalysisError::metadata)
+}
+
+pub(crate) fn analyze_value_header(
+    data: &[u8],
+    header_start: usize,
+) -> Result<metadata::ValueHeader, AnalysisError> {
+    let header_end = header_start + metadata::VALUE_HEADER_BYTE_SIZE;
+
+    let header_data = data
+        .get(header_start..header_end)
+        .ok_or(metadata::Error::InvalidValueHeaderSize)
+        .map_err(AnalysisError::metadata)?;
+
+    metadata::ValueHeader::from_data(header_data, header_start).map_err(AnalysisError::metadata)
+}
+
+// Returns the offset stored in the offset table of an object, relative to the object's base
+pub(crate) fn analyze_offset_table_entry(
+    data: &[u8],
+    table_entry_start: usize,
+) -> Result<usize, AnalysisError> {
+    let table_entry_end = table_entry_start + metadata::OFFSET_TABLE_ENTRY_BYTE_SIZE;
+
+    let table_entry_data = data
+        .get(table_entry_start..table_entry_end)
+        .ok_or(metadata::Error::InvalidOffsetTableLength)
+        .map_err(AnalysisError::metadata)?;
+
+    Ok(as_u32(table_entry_data) as usize)
+}
+
+// Bytes of a container and of its offset table, which holds 4 bytes per value
+fn container_bytefields(
+    base: &metadata::ContainerBase,
+    base_start: usize,
+) -> (data::ByteField, data::ByteField) {
+    let table_start = base_start + base.table_offset as usize;
+    let table_end = table_start + base.length as usize * metadata::OFFSET_TABLE_ENTRY_BYTE_SIZE;
+    (
+        data::ByteField {
+            range: base_start..(base_start + base.size as usize),
+        },
+        data::ByteField {
+            range: table_start..table_end,
+        },
+    )
+}
+
+fn analyze_array(data: &[u8], base_start: usize) -> Result<(data::Value, usize), AnalysisError> {
+    let array_info = analyze_container_base(data, base_start)?;
 
     if array_info.is_object {
         return Err(AnalysisError::data(data::Error::InvalidArrayContainer));
@@ -260,41 +366,30 @@ fn analyze_array(data: &[u8], base_start: usize) -> Result<(data::Value, usize),
 
     let mut offset = base_start + array_info.table_offset as usize;
     for _i in 0..nb_values {
-        let header_start = offset;
-        let header_end = header_start + metadata::VALUE_HEADER_BYTE_SIZE;
-
-        let header_data = data
-            .get(header_start..header_end)
-            .ok_or(metadata::Error::InvalidValueHeaderSize)
-            .map_err(AnalysisError::metadata)?;
-
-        let header = metadata::ValueHeader::from_data(header_data, header_start)
-            .map_err(AnalysisError::metadata)?;
+        let header = analyze_value_header(data, offset)?;
 
         let (value, _) = analyze_value(data, &header, base_start)?;
 
         values.push(value);
 
-        offset = header_end;
+        offset += metadata::VALUE_HEADER_BYTE_SIZE;
     }
 
+    let (container, table) = container_bytefields(&array_info, base_start);
+    let array_end = container.range.end;
+
     Ok((
-        data::Value::Array(data::Array { values }),
-        base_start + array_info.size as usize,
+        data::Value::Array(data::Array {
+            container,
+            table,
+            values,
+        }),
+        array_end,
     ))
 }
 
 fn analyze_object(data: &[u8], base_start: usize) -> Result<(data::Value, usize), AnalysisError> {
-    let base_end = base_start + metadata::CONTAINER_BASE_LENGTH;
-    let base_range = base_start..base_end;
-
-    let object_info_data = data
-        .get(base_range)
-        .ok_or(metadata::Error::InvalidContainerBaseLength)
-        .map_err(AnalysisError::metadata)?;
-
-    let object_info =
-        metadata::ContainerBase::from_data(object_info_data).map_err(AnalysisError::metadata)?;
+    let object_info = analyze_container_base(data, base_start)?;
 
     if !object_info.is_object {
         return Err(AnalysisError::data(data::Error::InvalidObjectContainer));
@@ -305,18 +400,28 @@ fn analyze_object(data: &[u8], base_start: usize) -> Result<(data::Value, usize)
     let mut entries = Vec::<data::Entry>::new();
     entries.reserve_exact(nb_entries);
 
-    let mut offset = base_end;
+    // Entries are read in the order of the offset table, which is the key order Qt maintains.
+    // Their layout in the container may differ after in place edits.
+    let mut offset = base_start + object_info.table_offset as usize;
     for _i in 0..nb_entries {
-        let (entry, entry_end) = analyze_entry(data, offset, base_start)?;
+        let entry_start = base_start + analyze_offset_table_entry(data, offset)?;
+        let entry = analyze_entry(data, entry_start, base_start)?;
 
         entries.push(entry);
 
-        offset = entry_end;
+        offset += metadata::OFFSET_TABLE_ENTRY_BYTE_SIZE;
     }
 
+    let (container, table) = container_bytefields(&object_info, base_start);
+    let object_end = container.range.end;
+
     Ok((
-        data::Value::Object(data::Object { entries }),
-        base_start + object_info.size as usize,
+        data::Value::Object(data::Object {
+            container,
+            table,
+            entries,
+        }),
+        object_end,
     ))
 }
 
@@ -324,36 +429,18 @@ fn analyze_entry(
     data: &[u8],
     entry_start: usize,
     object_start: usize,
-) -> Result<(data::Entry, usize), AnalysisError> {
-    let header_end = entry_start + metadata::VALUE_HEADER_BYTE_SIZE;
-    let header_range = entry_start..header_end;
-
-    let header_data = data
-        .get(header_range)
-        .ok_or(metadata::Error::InvalidValueHeaderSize)
-        .map_err(AnalysisError::metadata)?;
-
-    let header = metadata::ValueHeader::from_data(header_data, entry_start)
-        .map_err(AnalysisError::metadata)?;
+) -> Result<data::Entry, AnalysisError> {
+    let header = analyze_value_header(data, entry_start)?;
 
-    let analyze_key = if header.latin_key_flag {
-        analyze_latin1_key
-    } else {
-        analyze_utf16_key
-    };
+    let (key, _) = analyze_key(data, &header)?;
 
-    let (key, key_end) = analyze_key(data, header_end)?;
+    let (value, _) = analyze_value(data, &header, object_start)?;
 
-    let (value, value_end) = analyze_value(data, &header, object_start)?;
-
-    let entry_end = match value {
-        data::Value::Null(_) | data::Value::Bool(_) | data::Value::SelfContainedNumber(_) => {
-            key_end
-        }
-        _ => value_end,
-    };
-
-    Ok((data::Entry { key, value }, entry_end))
+    Ok(data::Entry {
+        position: entry_start,
+        key,
+        value,
+    })
 }
 
 fn analyze_latin1_string(data: &[u8], string_field_start: usize) -> (data::ByteField, usize) {
@@ -394,6 +481,20 @@ fn analyze_utf16_string(data: &[u8], string_field_start: usize) -> (data::ByteFi
     )
 }
 
+// Keys are stored right after the header of their entry
+pub(crate) fn analyze_key(
+    data: &[u8],
+    header: &metadata::ValueHeader,
+) -> Result<(data::Key, usize), AnalysisError> {
+    let key_start = header.position + metadata::VALUE_HEADER_BYTE_SIZE;
+
+    if header.latin_key_flag {
+        analyze_latin1_key(data, key_start)
+    } else {
+        analyze_utf16_key(data, key_start)
+    }
+}
+
 fn analyze_latin1_key(data: &[u8], key_start: usize) -> Result<(data::Key, usize), AnalysisError> {
     let size_field_range = key_start..(key_start + metadata::LATIN1_SIZE_FIELD_LENGTH);
     let key_data = data
@@ -417,14 +518,14 @@ fn analyze_utf16_key(data: &[u8], key_start: usize) -> Result<(data::Key, usize)
     Ok((data::Key::Utf16String(bytefield), key_end))
 }
 
-const QT_NULL_VALUE: u8 = 0;
-const QT_BOOL_VALUE: u8 = 1;
-const QT_NUMBER_VALUE: u8 = 2;
-const QT_STRING_VALUE: u8 = 3;
-const QT_ARRAY_VALUE: u8 = 4;
-const QT_OBJECT_VALUE: u8 = 5;
+pub(crate) const QT_NULL_VALUE: u8 = 0;
+pub(crate) const QT_BOOL_VALUE: u8 = 1;
+pub(crate) const QT_NUMBER_VALUE: u8 = 2;
+pub(crate) const QT_STRING_VALUE: u8 = 3;
+pub(crate) const QT_ARRAY_VALUE: u8 = 4;
+pub(crate) const QT_OBJECT_VALUE: u8 = 5;
 
-fn analyze_value(
+pub(crate) fn analyze_value(
     data: &[u8],
     header: &metadata::ValueHeader,
     container_start: usize,
@@ -453,17 +554,37 @@ fn analyze_value(
             analyze_string_value(data, value_range_start)
         }
         QT_ARRAY_VALUE => {
-            let value_range_start = container_start + header.value_bit_field as usize;
+            let value_range_start = analyze_nested_container_start(data, header, container_start)?;
             analyze_array(data, value_range_start)
         }
         QT_OBJECT_VALUE => {
-            let value_range_start = container_start + header.value_bit_field as usize;
+            let value_range_start = analyze_nested_container_start(data, header, container_start)?;
             analyze_object(data, value_range_start)
         }
         _ => Err(AnalysisError::data(data::Error::UnknownQtValue)),
     }
 }
 
+// Returns where the base of the nested container of the header starts. It has to be after
+// the base of its parent and inside it, so offsets pointing back to a container being
+// analyzed don't make the analysis loop forever.
+pub(crate) fn analyze_nested_container_start(
+    data: &[u8],
+    header: &metadata::ValueHeader,
+    container_start: usize,
+) -> Result<usize, AnalysisError> {
+    let parent_base = analyze_container_base(data, container_start)?;
+    let offset = header.value_bit_field as usize;
+
+    if offset < metadata::CONTAINER_BASE_LENGTH
+        || offset + metadata::CONTAINER_BASE_LENGTH > parent_base.size as usize
+    {
+        return Err(AnalysisError::data(data::Error::InvalidContainerOffset));
+    }
+
+    Ok(container_start + offset)
+}
+
 const DOUBLE_VALUE_BYTE_SIZE: usize = 8;
 
 fn analyze_double_value(
@@ -0,0 +1,220 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::process;
+
+use qbjs_deserializer::qbjs;
+
+const USAGE: &str = "usage:
+    qbjs query [--compact] [--base64 | --hex] <file> <expression>
+    qbjs explain [--base64 | --hex] <file>
+    qbjs stats [--base64 | --hex] <file>
+    qbjs diff [--storage] [--compact] [--base64 | --hex] <file> <file>
+    qbjs scan [--compact] [--base64 | --hex] <file>";
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+
+    let result = match args.first().map(String::as_str) {
+        Some("query") => query(&args[1..]),
+        Some("explain") => explain(&args[1..]),
+        Some("stats") => stats(&args[1..]),
+        Some("diff") => diff(&args[1..]),
+        Some("scan") => scan(&args[1..]),
+        _ => Err(USAGE.to_owned()),
+    };
+
+    if let Err(message) = result {
+        eprintln!("{}", message);
+        process::exit(1);
+    }
+}
+
+// Splits the arguments between the given flags and the positional arguments
+fn parse_args<'a>(
+    args: &'a [String],
+    flags: &[&str],
+) -> Result<(Vec<&'a str>, Vec<&'a str>), String> {
+    let mut set_flags = Vec::new();
+    let mut positional_args = Vec::new();
+    for arg in args {
+        if flags.contains(&arg.as_str()) {
+            set_flags.push(arg.as_str());
+        } else if arg.starts_with("--") {
+            return Err(format!("unknown option: {}\n{}", arg, USAGE));
+        } else {
+            positional_args.push(arg.as_str());
+        }
+    }
+    Ok((set_flags, positional_args))
+}
+
+fn read_file(path: &str) -> Result<Vec<u8>, String> {
+    fs::read(path).map_err(|err| format!("couldn't read {}: {}", path, err))
+}
+
+// Reads the file, decoding it first when the flags say it holds base64 or hex text
+fn read_input(path: &str, flags: &[&str]) -> Result<Vec<u8>, String> {
+    let decode = match (flags.contains(&"--base64"), flags.contains(&"--hex")) {
+        (false, false) => return read_file(path),
+        (true, false) => qbjs::text::decode_base64,
+        (false, true) => qbjs::text::decode_hex,
+        (true, true) => return Err(USAGE.to_owned()),
+    };
+    decode(&read_file(path)?).map_err(|err| format!("couldn't decode {}: {}", path, err))
+}
+
+fn print_json(value: &serde_json::Value, compact: bool) -> Result<(), String> {
+    let text = if compact {
+        serde_json::to_string(value)
+    } else {
+        serde_json::to_string_pretty(value)
+    };
+    println!("{}", text.map_err(|err| err.to_string())?);
+    Ok(())
+}
+
+fn query(args: &[String]) -> Result<(), String> {
+    let (flags, args) = parse_args(args, &["--compact", "--base64", "--hex"])?;
+    let (path, expression) = match args.as_slice() {
+        [path, expression] => (path, expression),
+        _ => return Err(USAGE.to_owned()),
+    };
+
+    let query = qbjs::Query::parse(expression).map_err(|err| err.to_string())?;
+    let qbjs_content = read_input(path, &flags)?;
+    let results = query
+        .evaluate(&qbjs_content)
+        .map_err(|err| err.to_string())?;
+
+    for result in results {
+        print_json(&result, flags.contains(&"--compact"))?;
+    }
+    Ok(())
+}
+
+fn explain(args: &[String]) -> Result<(), String> {
+    let (flags, args) = parse_args(args, &["--base64", "--hex"])?;
+    let path = match args.as_slice() {
+        [path] => path,
+        _ => return Err(USAGE.to_owned()),
+    };
+
+    let qbjs_content = read_input(path, &flags)?;
+    let explanation = qbjs::explain(&qbjs_content);
+    explanation
+        .write(io::stdout().lock())
+        .map_err(|err| err.to_string())?;
+
+    // Regions explained before the error of invalid documents are printed all the same
+    match explanation.error {
+        Some(err) => Err(err.to_string()),
+        None => Ok(()),
+    }
+}
+
+fn stats(args: &[String]) -> Result<(), String> {
+    let (flags, args) = parse_args(args, &["--base64", "--hex"])?;
+    let path = match args.as_slice() {
+        [path] => path,
+        _ => return Err(USAGE.to_owned()),
+    };
+
+    let qbjs_content = read_input(path, &flags)?;
+    let stats = qbjs::stats(&qbjs_content).map_err(|err| err.to_string())?;
+
+    let values = &stats.values;
+    println!("values:");
+    for (name, count) in [
+        ("nulls", values.nulls),
+        ("bools", values.bools),
+        ("self contained numbers", values.self_contained_numbers),
+        ("doubles", values.doubles),
+        ("latin1 strings", values.latin1_strings),
+        ("utf16 strings", values.utf16_strings),
+        ("arrays", values.arrays),
+        ("objects", values.objects),
+    ] {
+        println!("    {:<24}{}", name, count);
+    }
+
+    println!("keys:");
+    println!("    {:<24}{}", "latin1 keys", stats.latin1_keys);
+    println!("    {:<24}{}", "utf16 keys", stats.utf16_keys);
+
+    println!("max depth: {}", stats.max_depth);
+
+    println!("largest containers:");
+    for container in &stats.largest_containers {
+        let pointer = if container.pointer.is_empty() {
+            "(root)"
+        } else {
+            &container.pointer
+        };
+        let (kind, items) = if container.is_object {
+            ("object", "entries")
+        } else {
+            ("array", "values")
+        };
+        println!(
+            "    {:<24}{} of {} {}, {} bytes",
+            pointer, kind, container.length, items, container.size
+        );
+    }
+
+    let bytes = &stats.bytes;
+    println!("bytes:");
+    for (name, count) in [
+        ("total", bytes.total),
+        ("header", bytes.header),
+        ("container bases", bytes.container_bases),
+        ("tables", bytes.tables),
+        ("entry headers", bytes.entry_headers),
+        ("keys", bytes.keys),
+        ("strings", bytes.strings),
+        ("doubles", bytes.doubles),
+        ("padding", bytes.padding),
+        ("wasted", bytes.wasted),
+    ] {
+        println!("    {:<24}{}", name, count);
+    }
+    Ok(())
+}
+
+fn diff(args: &[String]) -> Result<(), String> {
+    let (flags, args) = parse_args(args, &["--storage", "--compact", "--base64", "--hex"])?;
+    let (a_path, b_path) = match args.as_slice() {
+        [a_path, b_path] => (a_path, b_path),
+        _ => return Err(USAGE.to_owned()),
+    };
+
+    let a = read_input(a_path, &flags)?;
+    let b = read_input(b_path, &flags)?;
+    let options = qbjs::DiffOptions {
+        storage: flags.contains(&"--storage"),
+    };
+    let operations = qbjs::diff(&a, &b, options).map_err(|err| err.to_string())?;
+
+    print_json(
+        &serde_json::Value::Array(operations),
+        flags.contains(&"--compact"),
+    )
+}
+
+fn scan(args: &[String]) -> Result<(), String> {
+    let (flags, args) = parse_args(args, &["--compact", "--base64", "--hex"])?;
+    let path = match args.as_slice() {
+        [path] => path,
+        _ => return Err(USAGE.to_owned()),
+    };
+
+    let data = read_input(path, &flags)?;
+    for (offset, document) in qbjs::scan(&data) {
+        let found = match document {
+            Ok(document) => serde_json::json!({"offset": offset, "document": document}),
+            Err(err) => serde_json::json!({"offset": offset, "error": err.to_string()}),
+        };
+        print_json(&found, flags.contains(&"--compact"))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,116 @@
+use std::borrow::Cow;
+use std::io::Read;
+
+use flate2::read::{GzDecoder, ZlibDecoder};
+use serde_json::Value;
+
+use crate::analysis::{self, data, header, AnalysisError};
+use crate::qbjs::{self, DeserializeError};
+
+// Offsets in a document are stored on 27 bits, so larger documents can't be valid
+pub const DEFAULT_SIZE_LIMIT: usize = 1 << 27;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Compression {
+    None,
+    QCompress, // Big endian u32 uncompressed length followed by a zlib stream
+    Zlib,
+    Gzip,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum CompressionError {
+    UnknownFormat, // Neither a qbjs header nor a known compression
+    InvalidCompressedData(Compression),
+    SizeLimitExceeded(usize), // The limit that was exceeded
+    AnalysisError(AnalysisError),
+    DeserializeError(DeserializeError),
+}
+
+const QCOMPRESS_LENGTH_SIZE: usize = 4;
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+
+// Same checks as zlib's inflate: the deflate method and a header checksum multiple of 31
+fn is_zlib_header(data: &[u8]) -> bool {
+    match data {
+        [cmf, flg, ..] => {
+            *cmf & 0x0f == 8 && *cmf >> 4 <= 7 && (u16::from(*cmf) << 8 | u16::from(*flg)) % 31 == 0
+        }
+        _ => false,
+    }
+}
+
+// Plain zlib is checked first: the length written by qCompress starts with a zero byte
+// for documents under 16 MiB, which isn't a valid zlib header
+pub fn detect_compression(data: &[u8]) -> Option<Compression> {
+    if header::has_qbjs_header(data) {
+        Some(Compression::None)
+    } else if data.starts_with(GZIP_MAGIC) {
+        Some(Compression::Gzip)
+    } else if is_zlib_header(data) {
+        Some(Compression::Zlib)
+    } else if data
+        .get(QCOMPRESS_LENGTH_SIZE..)
+        .map_or(false, is_zlib_header)
+    {
+        Some(Compression::QCompress)
+    } else {
+        None
+    }
+}
+
+// Reads at most one byte over the limit to tell when the decompressed data exceeds it
+fn read_limited<R: Read>(
+    decoder: R,
+    compression: Compression,
+    size_limit: usize,
+) -> Result<Vec<u8>, CompressionError> {
+    let mut decompressed = Vec::new();
+    decoder
+        .take(size_limit as u64 + 1)
+        .read_to_end(&mut decompressed)
+        .map_err(|_| CompressionError::InvalidCompressedData(compression))?;
+
+    if decompressed.len() > size_limit {
+        return Err(CompressionError::SizeLimitExceeded(size_limit));
+    }
+    Ok(decompressed)
+}
+
+// Returns the document wrapped by qCompress, zlib or gzip, or the data itself when it
+// already starts with a qbjs header. Decompression stops once the size limit is exceeded.
+// The length written by qCompress is only a hint for qUncompress, so it isn't checked.
+pub fn decompress(data: &[u8], size_limit: usize) -> Result<Cow<'_, [u8]>, CompressionError> {
+    let compression = detect_compression(data).ok_or(CompressionError::UnknownFormat)?;
+
+    let decompressed = match compression {
+        Compression::None => return Ok(Cow::Borrowed(data)),
+        Compression::QCompress => read_limited(
+            ZlibDecoder::new(&data[QCOMPRESS_LENGTH_SIZE..]),
+            compression,
+            size_limit,
+        )?,
+        Compression::Zlib => read_limited(ZlibDecoder::new(data), compression, size_limit)?,
+        Compression::Gzip => read_limited(GzDecoder::new(data), compression, size_limit)?,
+    };
+    Ok(Cow::Owned(decompressed))
+}
+
+// Decompresses the document then analyzes it, the analysis refers to the returned bytes
+pub fn analyze_compressed_document(
+    data: &[u8],
+    size_limit: usize,
+) -> Result<(Vec<u8>, data::Value), CompressionError> {
+    let document = decompress(data, size_limit)?.into_owned();
+    let analysis =
+        analysis::analyze_document(&document).map_err(CompressionError::AnalysisError)?;
+    Ok((document, analysis))
+}
+
+pub fn deserialize_compressed_to_json(
+    data: &[u8],
+    size_limit: usize,
+) -> Result<Value, CompressionError> {
+    let document = decompress(data, size_limit)?;
+    qbjs::deserialize_to_json(&document).map_err(CompressionError::DeserializeError)
+}
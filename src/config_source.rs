@@ -0,0 +1,118 @@
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use config::{ConfigError, FileStoredFormat, Format, Map, Source, Value, ValueKind};
+
+use crate::qbjs;
+
+// Format for config::File. The config crate reads files as UTF-8 text, replacing invalid
+// sequences, so only documents without bytes above 0x7F survive it. QbjsFile reads any
+// document.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct QbjsFormat;
+
+const REPLACEMENT_CHARACTER: char = '\u{fffd}';
+
+impl Format for QbjsFormat {
+    fn parse(
+        &self,
+        uri: Option<&String>,
+        text: &str,
+    ) -> Result<Map<String, Value>, Box<dyn Error + Send + Sync>> {
+        if text.contains(REPLACEMENT_CHARACTER) {
+            return Err("the document isn't valid UTF-8 text, load it with QbjsFile".into());
+        }
+        parse_document(uri, text.as_bytes())
+    }
+}
+
+impl FileStoredFormat for QbjsFormat {
+    fn file_extensions(&self) -> &'static [&'static str] {
+        &["qbjs"]
+    }
+}
+
+// Source reading a document file as bytes
+#[derive(Debug, Clone)]
+pub struct QbjsFile {
+    path: PathBuf,
+    required: bool,
+}
+
+impl QbjsFile {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        QbjsFile {
+            path: path.into(),
+            required: true,
+        }
+    }
+
+    // Missing files that aren't required provide no value, the same as config::File
+    pub fn required(mut self, required: bool) -> Self {
+        self.required = required;
+        self
+    }
+}
+
+impl Source for QbjsFile {
+    fn clone_into_box(&self) -> Box<dyn Source + Send + Sync> {
+        Box::new(self.clone())
+    }
+
+    fn collect(&self) -> Result<Map<String, Value>, ConfigError> {
+        let uri = self.path.to_string_lossy().into_owned();
+        let qbjs = match fs::read(&self.path) {
+            Ok(qbjs) => qbjs,
+            Err(err) if err.kind() == io::ErrorKind::NotFound && !self.required => {
+                return Ok(Map::new());
+            }
+            Err(err) => return Err(ConfigError::Foreign(Box::new(err))),
+        };
+
+        parse_document(Some(&uri), &qbjs).map_err(|cause| ConfigError::FileParse {
+            uri: Some(uri),
+            cause,
+        })
+    }
+}
+
+// The root of the document must be an object, the same as the tables of other formats
+fn parse_document(
+    uri: Option<&String>,
+    qbjs: &[u8],
+) -> Result<Map<String, Value>, Box<dyn Error + Send + Sync>> {
+    match qbjs::deserialize_to_json(qbjs)? {
+        serde_json::Value::Object(entries) => Ok(entries
+            .iter()
+            .map(|(key, value)| (key.clone(), config_value(uri, value)))
+            .collect()),
+        _ => Err("the root of the document isn't an object".into()),
+    }
+}
+
+fn config_value(uri: Option<&String>, value: &serde_json::Value) -> Value {
+    let kind = match value {
+        serde_json::Value::Null => ValueKind::Nil,
+        serde_json::Value::Bool(value) => ValueKind::Boolean(*value),
+        serde_json::Value::Number(number) => match number.as_i64() {
+            Some(number) => ValueKind::I64(number),
+            None => ValueKind::Float(number.as_f64().unwrap_or(f64::NAN)),
+        },
+        serde_json::Value::String(string) => ValueKind::String(string.clone()),
+        serde_json::Value::Array(values) => ValueKind::Array(
+            values
+                .iter()
+                .map(|value| config_value(uri, value))
+                .collect(),
+        ),
+        serde_json::Value::Object(entries) => ValueKind::Table(
+            entries
+                .iter()
+                .map(|(key, value)| (key.clone(), config_value(uri, value)))
+                .collect(),
+        ),
+    };
+    Value::new(uri, kind)
+}
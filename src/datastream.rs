@@ -0,0 +1,110 @@
+use std::io::{self, Read};
+
+use serde_json::Value;
+
+use crate::analysis::header;
+use crate::qbjs::{self, DeserializeError};
+
+// QByteArray read from a QDataStream
+#[derive(Debug, Clone, PartialEq)]
+pub enum ByteArray {
+    Null, // Written by a default constructed QByteArray
+    Empty,
+    Document(Result<Value, DeserializeError>), // Starts with a qbjs header
+    Data(Vec<u8>),
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum DataStreamError {
+    IoError(io::ErrorKind),
+    TruncatedRecord(usize), // Position of the length of the record
+}
+
+// QDataStream writes a QByteArray as its big endian u32 length followed by its bytes,
+// the length of a null QByteArray is 0xFFFFFFFF
+const NULL_LENGTH: u32 = 0xffff_ffff;
+const LENGTH_SIZE: usize = 4;
+
+// Iterator over consecutive QByteArray records, such as the ones written by
+// `stream << document.toBinaryData()`. It stops after the first error.
+pub struct ByteArrayReader<R> {
+    reader: R,
+    position: usize,
+    done: bool,
+}
+
+pub fn read_byte_arrays<R: Read>(reader: R) -> ByteArrayReader<R> {
+    ByteArrayReader {
+        reader,
+        position: 0,
+        done: false,
+    }
+}
+
+impl<R: Read> ByteArrayReader<R> {
+    // Reads at most the given number of bytes, without allocating them beforehand
+    // since the length of corrupted records can be anything
+    fn read_bytes(&mut self, length: usize) -> Result<Vec<u8>, DataStreamError> {
+        let mut bytes = Vec::new();
+        self.reader
+            .by_ref()
+            .take(length as u64)
+            .read_to_end(&mut bytes)
+            .map_err(|err| DataStreamError::IoError(err.kind()))?;
+        Ok(bytes)
+    }
+
+    // Returns None at the end of the stream
+    fn read_record(&mut self) -> Result<Option<ByteArray>, DataStreamError> {
+        let record_start = self.position;
+
+        let length_bytes = self.read_bytes(LENGTH_SIZE)?;
+        match length_bytes.len() {
+            0 => return Ok(None),
+            LENGTH_SIZE => {}
+            _ => return Err(DataStreamError::TruncatedRecord(record_start)),
+        }
+        let length = u32::from_be_bytes([
+            length_bytes[0],
+            length_bytes[1],
+            length_bytes[2],
+            length_bytes[3],
+        ]);
+        self.position += LENGTH_SIZE;
+
+        if length == NULL_LENGTH {
+            return Ok(Some(ByteArray::Null));
+        }
+        if length == 0 {
+            return Ok(Some(ByteArray::Empty));
+        }
+
+        let bytes = self.read_bytes(length as usize)?;
+        if bytes.len() != length as usize {
+            return Err(DataStreamError::TruncatedRecord(record_start));
+        }
+        self.position += bytes.len();
+
+        if header::has_qbjs_header(&bytes) {
+            return Ok(Some(ByteArray::Document(qbjs::deserialize_to_json(&bytes))));
+        }
+
+        Ok(Some(ByteArray::Data(bytes)))
+    }
+}
+
+impl<R: Read> Iterator for ByteArrayReader<R> {
+    type Item = Result<ByteArray, DataStreamError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let record = self.read_record().transpose();
+        if !matches!(record, Some(Ok(_))) {
+            self.done = true;
+        }
+        record
+    }
+}
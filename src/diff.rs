@@ -0,0 +1,233 @@
+use std::collections::HashMap;
+
+use serde_json::{json, Map, Value};
+
+use crate::qbjs::{self, DeserializeError};
+use crate::read::ReadError;
+use crate::spans::escape_pointer_token;
+use crate::write;
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct DiffOptions {
+    // Also reports values and keys that are equal but stored differently:
+    // latin1 or UTF-16 strings and keys, self contained or double numbers
+    pub storage: bool,
+}
+
+// Compares two documents and returns the JSON Patch (RFC 6902) operations turning the first
+// one into the second one. Storage differences are reported as "replace" operations with the
+// same value and an extra "storage" member, which JSON Patch appliers ignore.
+pub fn diff(a: &[u8], b: &[u8], options: DiffOptions) -> Result<Vec<Value>, DeserializeError> {
+    let a = storage_tree(a)?;
+    let b = storage_tree(b)?;
+
+    let mut differ = Differ {
+        options,
+        operations: Vec::new(),
+    };
+    differ
+        .diff_values("", &a, &b)
+        .map_err(DeserializeError::ReadError)?;
+
+    Ok(differ.operations)
+}
+
+fn storage_tree(qbjs: &[u8]) -> Result<write::Value, DeserializeError> {
+    // Same as deserialize_to_json, an empty document is an empty object
+    if qbjs.is_empty() {
+        return Ok(write::Value::Object(Vec::new()));
+    }
+
+    let document = qbjs::analyze_root_container(qbjs)?;
+    write::value_from_analysis(qbjs, &document).map_err(DeserializeError::ReadError)
+}
+
+fn value_storage(value: &write::Value) -> &'static str {
+    match value {
+        write::Value::Null => "null",
+        write::Value::Bool(_) => "bool",
+        write::Value::SelfContainedNumber(_) => "self-contained int",
+        write::Value::Number(_) => "double",
+        write::Value::Latin1String(_) => "latin1",
+        write::Value::Utf16String(_) => "utf16",
+        write::Value::Array(_) => "array",
+        write::Value::Object(_) => "object",
+    }
+}
+
+fn key_storage(key: &write::Key) -> &'static str {
+    match key {
+        write::Key::Latin1String(_) => "latin1",
+        write::Key::Utf16String(_) => "utf16",
+    }
+}
+
+fn storage_change(from: &'static str, to: &'static str) -> Option<Value> {
+    (from != to).then(|| json!({"from": from, "to": to}))
+}
+
+// Index of the first entry of each key, duplicated keys are matched by their first entry
+fn key_indexes(keys: &[String]) -> HashMap<&str, usize> {
+    let mut indexes = HashMap::with_capacity(keys.len());
+    for (index, key) in keys.iter().enumerate() {
+        indexes.entry(key.as_str()).or_insert(index);
+    }
+    indexes
+}
+
+// Same as jq, numbers are equal when their values are, whatever their storage
+pub(crate) fn scalars_equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64() == b.as_f64(),
+        _ => a == b,
+    }
+}
+
+struct Differ {
+    options: DiffOptions,
+    operations: Vec<Value>,
+}
+
+impl Differ {
+    fn add(&mut self, path: String, value: &write::Value) -> Result<(), ReadError> {
+        let value = value.to_json()?;
+        self.operations
+            .push(json!({"op": "add", "path": path, "value": value}));
+        Ok(())
+    }
+
+    fn remove(&mut self, path: String) {
+        self.operations.push(json!({"op": "remove", "path": path}));
+    }
+
+    fn replace(&mut self, path: &str, value: Value, storage: Option<Map<String, Value>>) {
+        let mut operation = json!({"op": "replace", "path": path, "value": value});
+        if let Some(storage) = storage {
+            operation["storage"] = Value::Object(storage);
+        }
+        self.operations.push(operation);
+    }
+
+    fn diff_values(
+        &mut self,
+        path: &str,
+        a: &write::Value,
+        b: &write::Value,
+    ) -> Result<(), ReadError> {
+        match (a, b) {
+            (write::Value::Object(a), write::Value::Object(b)) => self.diff_objects(path, a, b),
+            (write::Value::Array(a), write::Value::Array(b)) => self.diff_arrays(path, a, b),
+            (write::Value::Array(_) | write::Value::Object(_), _)
+            | (_, write::Value::Array(_) | write::Value::Object(_)) => {
+                self.replace(path, b.to_json()?, None);
+                Ok(())
+            }
+            _ => {
+                let b_json = b.to_json()?;
+                if !scalars_equal(&a.to_json()?, &b_json) {
+                    self.replace(path, b_json, None);
+                } else if self.options.storage {
+                    if let Some(change) = storage_change(value_storage(a), value_storage(b)) {
+                        let storage = Map::from_iter([("value".to_owned(), change)]);
+                        self.replace(path, b_json, Some(storage));
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    // Entries are matched by key, removed entries come first then changed then added ones
+    fn diff_objects(
+        &mut self,
+        path: &str,
+        a: &[write::Entry],
+        b: &[write::Entry],
+    ) -> Result<(), ReadError> {
+        let a_keys = a
+            .iter()
+            .map(|entry| entry.key.decode())
+            .collect::<Result<Vec<_>, _>>()?;
+        let b_keys = b
+            .iter()
+            .map(|entry| entry.key.decode())
+            .collect::<Result<Vec<_>, _>>()?;
+        let a_indexes = key_indexes(&a_keys);
+        let b_indexes = key_indexes(&b_keys);
+
+        // Entries after the first one of a duplicated key are skipped
+        for (index, key) in a_keys.iter().enumerate() {
+            if a_indexes[key.as_str()] == index && !b_indexes.contains_key(key.as_str()) {
+                self.remove(format!("{}/{}", path, escape_pointer_token(key)));
+            }
+        }
+
+        for (index, (a_entry, key)) in a.iter().zip(&a_keys).enumerate() {
+            let b_entry = match b_indexes.get(key.as_str()) {
+                Some(b_index) if a_indexes[key.as_str()] == index => &b[*b_index],
+                _ => continue,
+            };
+            let entry_path = format!("{}/{}", path, escape_pointer_token(key));
+
+            let operations_count = self.operations.len();
+            self.diff_values(&entry_path, &a_entry.value, &b_entry.value)?;
+
+            let key_change = storage_change(key_storage(&a_entry.key), key_storage(&b_entry.key));
+            if let (true, Some(key_change)) = (self.options.storage, key_change) {
+                // The key change joins the replacement of the value, if it was replaced.
+                // Otherwise the value is replaced after the changes inside of it.
+                let value_operation = self.operations[operations_count..]
+                    .iter_mut()
+                    .find(|operation| operation["path"] == entry_path.as_str());
+                match value_operation {
+                    Some(operation) => {
+                        let storage = operation
+                            .as_object_mut()
+                            .expect("operations are objects")
+                            .entry("storage")
+                            .or_insert_with(|| json!({}));
+                        storage["key"] = key_change;
+                    }
+                    None => {
+                        let storage = Map::from_iter([("key".to_owned(), key_change)]);
+                        let value = b_entry.value.to_json()?;
+                        self.replace(&entry_path, value, Some(storage));
+                    }
+                }
+            }
+        }
+
+        for (index, (b_entry, key)) in b.iter().zip(&b_keys).enumerate() {
+            if b_indexes[key.as_str()] == index && !a_indexes.contains_key(key.as_str()) {
+                self.add(
+                    format!("{}/{}", path, escape_pointer_token(key)),
+                    &b_entry.value,
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Values are compared by index, extra values are removed from the end or appended
+    fn diff_arrays(
+        &mut self,
+        path: &str,
+        a: &[write::Value],
+        b: &[write::Value],
+    ) -> Result<(), ReadError> {
+        for (index, (a_value, b_value)) in a.iter().zip(b).enumerate() {
+            self.diff_values(&format!("{}/{}", path, index), a_value, b_value)?;
+        }
+
+        for index in (b.len()..a.len()).rev() {
+            self.remove(format!("{}/{}", path, index));
+        }
+
+        for (index, b_value) in b.iter().enumerate().skip(a.len()) {
+            self.add(format!("{}/{}", path, index), b_value)?;
+        }
+
+        Ok(())
+    }
+}
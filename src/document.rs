@@ -0,0 +1,112 @@
+use serde::ser::{Error, Serialize, SerializeMap, SerializeSeq, Serializer};
+
+use crate::analysis::data;
+use crate::qbjs::{self, DeserializeError};
+use crate::read::{self, ReadError};
+
+// Analyzed document borrowing the input bytes.
+// Serializing it decodes the values from the input bytes on the fly, so it can be handed to any
+// serde serializer without building an intermediate serde_json::Value.
+#[derive(Debug)]
+pub struct QbjsDocument<'a> {
+    data: &'a [u8],
+    root: Option<data::Value>, // None for an empty document
+}
+
+impl<'a> QbjsDocument<'a> {
+    pub fn from_data(data: &'a [u8]) -> Result<Self, DeserializeError> {
+        if data.is_empty() {
+            return Ok(QbjsDocument { data, root: None });
+        }
+
+        let root = qbjs::analyze_root_container(data)?;
+
+        Ok(QbjsDocument {
+            data,
+            root: Some(root),
+        })
+    }
+
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    pub fn root(&self) -> Option<&data::Value> {
+        self.root.as_ref()
+    }
+}
+
+impl Serialize for QbjsDocument<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match &self.root {
+            Some(root) => ValueRef::new(self.data, root).serialize(serializer),
+            None => serializer.serialize_map(Some(0))?.end(), // Same as deserialize_to_json
+        }
+    }
+}
+
+// Serializable view of an analyzed value
+#[derive(Debug, Clone, Copy)]
+pub struct ValueRef<'a> {
+    data: &'a [u8],
+    value: &'a data::Value,
+}
+
+impl<'a> ValueRef<'a> {
+    pub fn new(data: &'a [u8], value: &'a data::Value) -> Self {
+        ValueRef { data, value }
+    }
+}
+
+impl Serialize for ValueRef<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.value {
+            data::Value::Null(_) => serializer.serialize_unit(),
+            data::Value::Bool(position) => {
+                let value = read::decode_bool(self.data, *position).map_err(read_error)?;
+                serializer.serialize_bool(value)
+            }
+            data::Value::SelfContainedNumber(position) => {
+                let value =
+                    read::decode_self_contained_number(self.data, *position).map_err(read_error)?;
+                serializer.serialize_i32(value)
+            }
+            data::Value::Number(bytefield) => {
+                let value = read::decode_number(self.data, bytefield).map_err(read_error)?;
+                if !value.is_finite() {
+                    return Err(read_error(ReadError::FailedToDecodeNumber));
+                }
+                serializer.serialize_f64(value)
+            }
+            data::Value::Latin1String(bytefield) => serializer
+                .serialize_str(&read::latin1_str(self.data, bytefield).map_err(read_error)?),
+            data::Value::Utf16String(bytefield) => serializer
+                .serialize_str(&read::utf16_str(self.data, bytefield).map_err(read_error)?),
+            data::Value::Array(array) => {
+                let mut seq = serializer.serialize_seq(Some(array.values.len()))?;
+                for value in &array.values {
+                    seq.serialize_element(&ValueRef::new(self.data, value))?;
+                }
+                seq.end()
+            }
+            data::Value::Object(object) => {
+                let mut map = serializer.serialize_map(Some(object.entries.len()))?;
+                for entry in &object.entries {
+                    let key = match &entry.key {
+                        data::Key::Latin1String(bytefield) => {
+                            read::latin1_str(self.data, bytefield)
+                        }
+                        data::Key::Utf16String(bytefield) => read::utf16_str(self.data, bytefield),
+                    }
+                    .map_err(read_error)?;
+                    map.serialize_entry(&key, &ValueRef::new(self.data, &entry.value))?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+fn read_error<E: Error>(err: ReadError) -> E {
+    E::custom(format_args!("failed to read qbjs value: {}", err))
+}
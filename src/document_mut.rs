@@ -0,0 +1,342 @@
+use std::ops::Range;
+
+use serde_json::Value;
+
+use crate::analysis::{self, header, metadata, AnalysisError};
+use crate::qbjs::{self, DeserializeError};
+use crate::read::{self, ReadError};
+use crate::spans::{parse_index, parse_pointer};
+use crate::type_conversions::as_u32;
+use crate::write::{self, EncodeError};
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum EditError {
+    DeserializeError(DeserializeError),
+    InvalidPointer(String), // Not a JSON Pointer or an invalid array index
+    PathNotFound(String),
+}
+
+impl EditError {
+    fn analysis(err: AnalysisError) -> Self {
+        EditError::DeserializeError(DeserializeError::AnalysisError(err))
+    }
+
+    fn read(err: ReadError) -> Self {
+        EditError::DeserializeError(DeserializeError::ReadError(err))
+    }
+
+    fn encode(err: EncodeError) -> Self {
+        EditError::DeserializeError(DeserializeError::EncodeError(err))
+    }
+}
+
+// Document whose values are replaced in place.
+// Values stored in their header are rewritten there and data that fits in the bytes of the
+// replaced value overwrites them. Larger data is written at the end of its container, before
+// its table, growing the containers holding it the same way Qt does: the replaced data is left
+// unreferenced until the document is compacted.
+// Only existing values can be replaced, use patch to add or remove values.
+#[derive(Debug, Clone)]
+pub struct QbjsDocumentMut {
+    data: Vec<u8>,
+}
+
+// Header of a value along with the bases of the containers holding it, from the root
+struct Location {
+    header: metadata::ValueHeader,
+    container_starts: Vec<usize>,
+}
+
+fn has_data(header: &metadata::ValueHeader) -> bool {
+    match header.qt_value_type {
+        analysis::QT_NUMBER_VALUE => !header.latin_or_int_value_flag,
+        analysis::QT_STRING_VALUE | analysis::QT_ARRAY_VALUE | analysis::QT_OBJECT_VALUE => true,
+        _ => false,
+    }
+}
+
+impl QbjsDocumentMut {
+    // Same as deserialize_to_json, an empty document is an empty object
+    pub fn from_data(data: Vec<u8>) -> Result<Self, DeserializeError> {
+        if data.is_empty() {
+            let data = write::encode_document(&write::Value::Object(Vec::new()))
+                .map_err(DeserializeError::EncodeError)?;
+            return Ok(QbjsDocumentMut { data });
+        }
+
+        qbjs::analyze_root_container(&data)?;
+
+        Ok(QbjsDocumentMut { data })
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+
+    pub fn set_null(&mut self, pointer: &str) -> Result<(), EditError> {
+        self.set(pointer, &write::Value::Null)
+    }
+
+    pub fn set_bool(&mut self, pointer: &str, value: bool) -> Result<(), EditError> {
+        self.set(pointer, &write::Value::Bool(value))
+    }
+
+    pub fn set_number(&mut self, pointer: &str, number: f64) -> Result<(), EditError> {
+        self.set(pointer, &write::Value::number(number))
+    }
+
+    pub fn set_string(&mut self, pointer: &str, string: &str) -> Result<(), EditError> {
+        self.set(pointer, &write::Value::string(string))
+    }
+
+    pub fn set_value(&mut self, pointer: &str, value: &Value) -> Result<(), EditError> {
+        self.set(pointer, &write::Value::from_json(value))
+    }
+
+    // Rewrites the document tightly packed, which drops the data of the replaced values
+    pub fn compact(&mut self) -> Result<(), DeserializeError> {
+        self.data = qbjs::compact(&self.data)?;
+        Ok(())
+    }
+
+    fn set(&mut self, pointer: &str, value: &write::Value) -> Result<(), EditError> {
+        let tokens =
+            parse_pointer(pointer).ok_or_else(|| EditError::InvalidPointer(pointer.to_owned()))?;
+
+        // The root has no header, the whole document is replaced
+        if tokens.is_empty() {
+            self.data = write::encode_document(value).map_err(EditError::encode)?;
+            return Ok(());
+        }
+
+        let location = self.locate(&tokens, pointer)?;
+        let container_start = *location
+            .container_starts
+            .last()
+            .expect("values are held by a container");
+
+        let (header, value_data) =
+            write::encode_detached_value(value, location.header.latin_key_flag)
+                .map_err(EditError::encode)?;
+
+        if value_data.is_empty() {
+            write::write_u32(&mut self.data, location.header.position, header);
+            return Ok(());
+        }
+
+        if let Some(old_data) = self.value_data_range(&location.header, container_start)? {
+            if value_data.len() <= old_data.len() {
+                self.data[old_data.clone()].fill(0);
+                let data_end = old_data.start + value_data.len();
+                self.data[old_data.start..data_end].copy_from_slice(&value_data);
+                self.write_header(
+                    location.header.position,
+                    header,
+                    old_data.start - container_start,
+                );
+                return Ok(());
+            }
+        }
+
+        let base = analysis::analyze_container_base(&self.data, container_start)
+            .map_err(EditError::analysis)?;
+        let data_start = container_start + base.table_offset as usize;
+        self.insert_data(&location.container_starts, data_start, &value_data)?;
+
+        // The headers of arrays are in their table, which follows the inserted data
+        let header_position = if location.header.position >= data_start {
+            location.header.position + value_data.len()
+        } else {
+            location.header.position
+        };
+        self.write_header(header_position, header, data_start - container_start);
+        Ok(())
+    }
+
+    fn write_header(&mut self, position: usize, header: u32, data_offset: usize) {
+        let header = (header & 0b11111) | ((data_offset as u32) << 5);
+        write::write_u32(&mut self.data, position, header);
+    }
+
+    fn locate(&self, tokens: &[String], pointer: &str) -> Result<Location, EditError> {
+        let mut container_start = header::HEADER_LENGTH;
+        let mut container_starts = Vec::with_capacity(tokens.len());
+        let mut value_header: Option<metadata::ValueHeader> = None;
+
+        for token in tokens {
+            if let Some(header) = &value_header {
+                match header.qt_value_type {
+                    analysis::QT_ARRAY_VALUE | analysis::QT_OBJECT_VALUE => {
+                        container_start += header.value_bit_field as usize;
+                    }
+                    _ => return Err(EditError::PathNotFound(pointer.to_owned())),
+                }
+            }
+            container_starts.push(container_start);
+
+            let base = analysis::analyze_container_base(&self.data, container_start)
+                .map_err(EditError::analysis)?;
+            let table_start = container_start + base.table_offset as usize;
+
+            let header = if base.is_object {
+                self.find_entry(container_start, table_start, base.length as usize, token)?
+            } else {
+                let index = parse_index(token)
+                    .ok_or_else(|| EditError::InvalidPointer(pointer.to_owned()))?;
+                if index >= base.length as usize {
+                    return Err(EditError::PathNotFound(pointer.to_owned()));
+                }
+                let header_start = table_start + index * metadata::VALUE_HEADER_BYTE_SIZE;
+                Some(
+                    analysis::analyze_value_header(&self.data, header_start)
+                        .map_err(EditError::analysis)?,
+                )
+            };
+            value_header = Some(header.ok_or_else(|| EditError::PathNotFound(pointer.to_owned()))?);
+        }
+
+        Ok(Location {
+            header: value_header.expect("pointers have reference tokens"),
+            container_starts,
+        })
+    }
+
+    fn find_entry(
+        &self,
+        object_start: usize,
+        table_start: usize,
+        length: usize,
+        key: &str,
+    ) -> Result<Option<metadata::ValueHeader>, EditError> {
+        for index in 0..length {
+            let table_entry_start = table_start + index * metadata::OFFSET_TABLE_ENTRY_BYTE_SIZE;
+            let entry_start = object_start
+                + analysis::analyze_offset_table_entry(&self.data, table_entry_start)
+                    .map_err(EditError::analysis)?;
+            let header = analysis::analyze_value_header(&self.data, entry_start)
+                .map_err(EditError::analysis)?;
+            let (entry_key, _) =
+                analysis::analyze_key(&self.data, &header).map_err(EditError::analysis)?;
+            let entry_key = match &entry_key {
+                analysis::data::Key::Latin1String(bytefield) => {
+                    read::latin1_str(&self.data, bytefield)
+                }
+                analysis::data::Key::Utf16String(bytefield) => {
+                    read::utf16_str(&self.data, bytefield)
+                }
+            }
+            .map_err(EditError::read)?;
+
+            if entry_key == key {
+                return Ok(Some(header));
+            }
+        }
+        Ok(None)
+    }
+
+    // Bytes of the value that aren't stored in its header, padding included
+    fn value_data_range(
+        &self,
+        header: &metadata::ValueHeader,
+        container_start: usize,
+    ) -> Result<Option<Range<usize>>, EditError> {
+        if !has_data(header) {
+            return Ok(None);
+        }
+
+        let data_start = container_start + header.value_bit_field as usize;
+        let data_length = match header.qt_value_type {
+            analysis::QT_NUMBER_VALUE => 8,
+            analysis::QT_STRING_VALUE => {
+                let (size_field_length, char_length) = if header.latin_or_int_value_flag {
+                    (
+                        metadata::LATIN1_SIZE_FIELD_LENGTH,
+                        metadata::LATIN1_CHAR_LENGTH,
+                    )
+                } else {
+                    (
+                        metadata::UTF16_SIZE_FIELD_LENGTH,
+                        metadata::UTF16_CHAR_LENGTH,
+                    )
+                };
+                let size_field = match self.data.get(data_start..(data_start + size_field_length)) {
+                    Some(size_field) => size_field,
+                    None => return Ok(None),
+                };
+                let string_length = size_field_length + as_u32(size_field) as usize * char_length;
+                (string_length + 3) / 4 * 4
+            }
+            _ => {
+                analysis::analyze_container_base(&self.data, data_start)
+                    .map_err(EditError::analysis)?
+                    .size as usize
+            }
+        };
+
+        let range = data_start..(data_start + data_length);
+        Ok((range.end <= self.data.len()).then_some(range))
+    }
+
+    // Inserts the data at the given position, which is inside of every given container,
+    // then updates the sizes and offsets of these containers. Containers nested in them
+    // move along with their content, their offsets are relative to their base.
+    fn insert_data(
+        &mut self,
+        container_starts: &[usize],
+        position: usize,
+        data: &[u8],
+    ) -> Result<(), EditError> {
+        let root_size = self.data.len() - header::HEADER_LENGTH;
+        if root_size + data.len() > write::MAX_OFFSET {
+            return Err(EditError::encode(EncodeError::ContainerTooLarge));
+        }
+
+        self.data.splice(position..position, data.iter().copied());
+
+        for &container_start in container_starts.iter().rev() {
+            let base = analysis::analyze_container_base(&self.data, container_start)
+                .map_err(EditError::analysis)?;
+            let shift = |offset: usize| {
+                if container_start + offset >= position {
+                    offset + data.len()
+                } else {
+                    offset
+                }
+            };
+
+            let table_offset = shift(base.table_offset as usize);
+            let size = base.size as usize + data.len();
+            write::write_u32(&mut self.data, container_start, size as u32);
+            write::write_u32(&mut self.data, container_start + 8, table_offset as u32);
+
+            let table_start = container_start + table_offset;
+            for index in 0..base.length as usize {
+                let table_entry_start = table_start + index * metadata::VALUE_HEADER_BYTE_SIZE;
+                let header_start = if base.is_object {
+                    let entry_offset = shift(
+                        analysis::analyze_offset_table_entry(&self.data, table_entry_start)
+                            .map_err(EditError::analysis)?,
+                    );
+                    write::write_u32(&mut self.data, table_entry_start, entry_offset as u32);
+                    container_start + entry_offset
+                } else {
+                    table_entry_start
+                };
+
+                let header = analysis::analyze_value_header(&self.data, header_start)
+                    .map_err(EditError::analysis)?;
+                if has_data(&header) {
+                    let raw_header = as_u32(&self.data[header_start..]);
+                    let data_offset = shift(header.value_bit_field as usize);
+                    self.write_header(header_start, raw_header, data_offset);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,238 @@
+use std::borrow::Cow;
+
+use crate::analysis::{self, data, header, metadata, AnalysisError};
+use crate::qbjs::DeserializeError;
+use crate::read;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event<'a> {
+    StartObject(usize), // Number of entries
+    Key(Cow<'a, str>),
+    StartArray(usize), // Number of values
+    Null,
+    Bool(bool),
+    Int(i32), // Self contained number
+    Double(f64),
+    Str(Cow<'a, str>),
+    End, // Closes the last started object or array
+}
+
+// Event and the position of the bytes it was read from:
+// - the container base for StartObject and StartArray,
+// - the size field for Key and Str,
+// - the value header for Null, Bool and Int,
+// - the 8 bytes of the double for Double,
+// - the end of the container for End.
+pub type PositionedEvent<'a> = (usize, Event<'a>);
+
+struct Frame {
+    base_start: usize,
+    base: metadata::ContainerBase,
+    next_index: usize,
+}
+
+// Pull parser walking the document without building the data::Value tree.
+// Memory use only depends on the depth of the document.
+pub struct QbjsEvents<'a> {
+    data: &'a [u8],
+    stack: Vec<Frame>,
+    pending_value: Option<(metadata::ValueHeader, usize)>, // Value of the last read key and its container
+    started: bool,
+    done: bool,
+}
+
+impl<'a> QbjsEvents<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        QbjsEvents {
+            data,
+            stack: Vec::new(),
+            pending_value: None,
+            started: false,
+            done: false,
+        }
+    }
+
+    fn start(&mut self) -> Result<PositionedEvent<'a>, DeserializeError> {
+        self.started = true;
+
+        // Same as deserialize_to_json, an empty document is an empty object
+        if self.data.is_empty() {
+            return Ok((0, Event::StartObject(0)));
+        }
+
+        if self.data.len() < header::HEADER_LENGTH {
+            return Err(DeserializeError::InsufficientData);
+        }
+
+        let header_data = &self.data[0..header::HEADER_LENGTH];
+        header::QbjsHeader::from_data(header_data)
+            .map_err(AnalysisError::header)
+            .map_err(DeserializeError::AnalysisError)?;
+
+        let base = analysis::analyze_container_base(self.data, header::HEADER_LENGTH)
+            .map_err(DeserializeError::AnalysisError)?;
+
+        Ok(self.start_container(header::HEADER_LENGTH, base))
+    }
+
+    fn start_container(
+        &mut self,
+        base_start: usize,
+        base: metadata::ContainerBase,
+    ) -> PositionedEvent<'a> {
+        let length = base.length as usize;
+        let event = if base.is_object {
+            Event::StartObject(length)
+        } else {
+            Event::StartArray(length)
+        };
+
+        self.stack.push(Frame {
+            base_start,
+            base,
+            next_index: 0,
+        });
+
+        (base_start, event)
+    }
+
+    fn next_event(&mut self) -> Result<PositionedEvent<'a>, DeserializeError> {
+        if !self.started {
+            return self.start();
+        }
+
+        if let Some((header, container_start)) = self.pending_value.take() {
+            return self.value_event(&header, container_start);
+        }
+
+        let frame = match self.stack.last_mut() {
+            Some(frame) => frame,
+            // Only happens for empty documents, which don't push any frame
+            None => {
+                self.done = true;
+                return Ok((0, Event::End));
+            }
+        };
+
+        if frame.next_index == frame.base.length as usize {
+            let container_end = frame.base_start + frame.base.size as usize;
+            self.stack.pop();
+            self.done = self.stack.is_empty();
+            return Ok((container_end, Event::End));
+        }
+
+        let index = frame.next_index;
+        frame.next_index += 1;
+
+        let base_start = frame.base_start;
+        let table_start = base_start + frame.base.table_offset as usize;
+        let table_entry_start = table_start + index * metadata::OFFSET_TABLE_ENTRY_BYTE_SIZE;
+
+        if frame.base.is_object {
+            let entry_start = base_start
+                + analysis::analyze_offset_table_entry(self.data, table_entry_start)
+                    .map_err(DeserializeError::AnalysisError)?;
+            let header = analysis::analyze_value_header(self.data, entry_start)
+                .map_err(DeserializeError::AnalysisError)?;
+            let (key, _) = analysis::analyze_key(self.data, &header)
+                .map_err(DeserializeError::AnalysisError)?;
+            let key_position = entry_start + metadata::VALUE_HEADER_BYTE_SIZE;
+
+            let key = match &key {
+                data::Key::Latin1String(bytefield) => read::latin1_str(self.data, bytefield),
+                data::Key::Utf16String(bytefield) => read::utf16_str(self.data, bytefield),
+            }
+            .map_err(DeserializeError::ReadError)?;
+
+            self.pending_value = Some((header, base_start));
+            Ok((key_position, Event::Key(key)))
+        } else {
+            // The offset table of an array directly contains the value headers
+            let header = analysis::analyze_value_header(self.data, table_entry_start)
+                .map_err(DeserializeError::AnalysisError)?;
+            self.value_event(&header, base_start)
+        }
+    }
+
+    fn value_event(
+        &mut self,
+        header: &metadata::ValueHeader,
+        container_start: usize,
+    ) -> Result<PositionedEvent<'a>, DeserializeError> {
+        match header.qt_value_type {
+            analysis::QT_ARRAY_VALUE | analysis::QT_OBJECT_VALUE => {
+                // Checked before pushing the container so cyclic offsets don't grow the stack
+                let base_start =
+                    analysis::analyze_nested_container_start(self.data, header, container_start)
+                        .map_err(DeserializeError::AnalysisError)?;
+                let base = analysis::analyze_container_base(self.data, base_start)
+                    .map_err(DeserializeError::AnalysisError)?;
+
+                let expects_object = header.qt_value_type == analysis::QT_OBJECT_VALUE;
+                if base.is_object != expects_object {
+                    let error = if expects_object {
+                        data::Error::InvalidObjectContainer
+                    } else {
+                        data::Error::InvalidArrayContainer
+                    };
+                    return Err(DeserializeError::AnalysisError(AnalysisError::data(error)));
+                }
+
+                Ok(self.start_container(base_start, base))
+            }
+            _ => {
+                // Other values are leaves, analyzing them doesn't recurse
+                let (value, _) = analysis::analyze_value(self.data, header, container_start)
+                    .map_err(DeserializeError::AnalysisError)?;
+                self.leaf_event(&value).map_err(DeserializeError::ReadError)
+            }
+        }
+    }
+
+    fn leaf_event(&self, value: &data::Value) -> Result<PositionedEvent<'a>, read::ReadError> {
+        match value {
+            data::Value::Null(position) => Ok((*position, Event::Null)),
+            data::Value::Bool(position) => {
+                let value = read::decode_bool(self.data, *position)?;
+                Ok((*position, Event::Bool(value)))
+            }
+            data::Value::SelfContainedNumber(position) => {
+                let value = read::decode_self_contained_number(self.data, *position)?;
+                Ok((*position, Event::Int(value)))
+            }
+            data::Value::Number(bytefield) => {
+                let value = read::decode_number(self.data, bytefield)?;
+                Ok((bytefield.range.start, Event::Double(value)))
+            }
+            data::Value::Latin1String(bytefield) => {
+                let value = read::latin1_str(self.data, bytefield)?;
+                let size_field_start = bytefield.range.start - metadata::LATIN1_SIZE_FIELD_LENGTH;
+                Ok((size_field_start, Event::Str(value)))
+            }
+            data::Value::Utf16String(bytefield) => {
+                let value = read::utf16_str(self.data, bytefield)?;
+                let size_field_start = bytefield.range.start - metadata::UTF16_SIZE_FIELD_LENGTH;
+                Ok((size_field_start, Event::Str(value)))
+            }
+            data::Value::Array(_) | data::Value::Object(_) => {
+                unreachable!("containers are not leaves")
+            }
+        }
+    }
+}
+
+impl<'a> Iterator for QbjsEvents<'a> {
+    type Item = Result<PositionedEvent<'a>, DeserializeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let event = self.next_event();
+        if event.is_err() {
+            self.done = true;
+        }
+        Some(event)
+    }
+}
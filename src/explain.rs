@@ -0,0 +1,359 @@
+use std::io;
+use std::ops::Range;
+
+use crate::analysis::{self, data, header, metadata, AnalysisError};
+use crate::qbjs::DeserializeError;
+use crate::read;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Region {
+    pub range: Range<usize>,
+    pub depth: usize, // Nesting level of the container the region belongs to
+    pub description: String,
+}
+
+// Layout of a document: every region the reader refers to, in file order.
+// Bytes no region refers to are reported as unreferenced regions.
+// The explanation stops at the first error, regions explained before it are kept.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Explanation<'a> {
+    pub data: &'a [u8],
+    pub regions: Vec<Region>,
+    pub error: Option<DeserializeError>,
+}
+
+const HEX_BYTES_PER_LINE: usize = 16;
+
+impl Explanation<'_> {
+    // Writes each region as its offset, raw hex and description.
+    // Regions longer than a line continue on the next lines. The error isn't written.
+    pub fn write<W: io::Write>(&self, mut writer: W) -> io::Result<()> {
+        let hex_width = HEX_BYTES_PER_LINE * 3 - 1;
+
+        for region in &self.regions {
+            let bytes = self.data.get(region.range.clone()).unwrap_or(&[]);
+            let mut lines = bytes.chunks(HEX_BYTES_PER_LINE);
+            let first_line = lines.next().unwrap_or(&[]);
+
+            writeln!(
+                writer,
+                "{:08x}  {:<hex_width$}  {}{}",
+                region.range.start,
+                hex(first_line),
+                "  ".repeat(region.depth),
+                region.description
+            )?;
+            for (index, line) in lines.enumerate() {
+                let offset = region.range.start + (index + 1) * HEX_BYTES_PER_LINE;
+                writeln!(writer, "{:08x}  {}", offset, hex(line))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub fn explain(qbjs: &[u8]) -> Explanation<'_> {
+    let mut explainer = Explainer {
+        data: qbjs,
+        regions: Vec::new(),
+    };
+    let result = explainer.explain_document();
+
+    let mut regions = explainer.regions;
+    regions.sort_by_key(|region| (region.range.start, region.depth));
+    let regions = with_unreferenced_regions(regions, qbjs.len());
+
+    Explanation {
+        data: qbjs,
+        regions,
+        error: result.err(),
+    }
+}
+
+// Inserts regions for the bytes the other regions don't cover
+fn with_unreferenced_regions(regions: Vec<Region>, data_length: usize) -> Vec<Region> {
+    let mut all_regions = Vec::with_capacity(regions.len());
+    let mut covered_end = 0;
+
+    let unreferenced = |range| Region {
+        range,
+        depth: 0,
+        description: "unreferenced bytes".to_owned(),
+    };
+
+    for region in regions {
+        if region.range.start > covered_end {
+            all_regions.push(unreferenced(covered_end..region.range.start));
+        }
+        covered_end = covered_end.max(region.range.end);
+        all_regions.push(region);
+    }
+    if data_length > covered_end {
+        all_regions.push(unreferenced(covered_end..data_length));
+    }
+
+    all_regions
+}
+
+struct Explainer<'a> {
+    data: &'a [u8],
+    regions: Vec<Region>,
+}
+
+impl Explainer<'_> {
+    fn push(&mut self, range: Range<usize>, depth: usize, description: String) {
+        self.regions.push(Region {
+            range,
+            depth,
+            description,
+        });
+    }
+
+    fn explain_document(&mut self) -> Result<(), DeserializeError> {
+        // Same as deserialize_to_json, an empty document is valid
+        if self.data.is_empty() {
+            return Ok(());
+        }
+
+        if self.data.len() < header::HEADER_LENGTH {
+            return Err(DeserializeError::InsufficientData);
+        }
+
+        let tag = &self.data[0..4];
+        let version = &self.data[4..8];
+        self.push(0..4, 0, format!("tag {:?}", String::from_utf8_lossy(tag)));
+        self.push(
+            4..8,
+            0,
+            format!(
+                "version {}",
+                u32::from_le_bytes(version.try_into().unwrap())
+            ),
+        );
+
+        header::QbjsHeader::from_data(&self.data[0..header::HEADER_LENGTH])
+            .map_err(AnalysisError::header)
+            .map_err(DeserializeError::AnalysisError)?;
+
+        self.explain_container(header::HEADER_LENGTH, 0)
+    }
+
+    fn explain_container(
+        &mut self,
+        base_start: usize,
+        depth: usize,
+    ) -> Result<(), DeserializeError> {
+        let base = analysis::analyze_container_base(self.data, base_start)
+            .map_err(DeserializeError::AnalysisError)?;
+        self.push(
+            base_start..(base_start + metadata::CONTAINER_BASE_LENGTH),
+            depth,
+            format!(
+                "{} base: size {}, is_object {}, length {}, table_offset {}",
+                if base.is_object { "object" } else { "array" },
+                base.size,
+                u8::from(base.is_object),
+                base.length,
+                base.table_offset
+            ),
+        );
+
+        let table_start = base_start + base.table_offset as usize;
+        for index in 0..base.length as usize {
+            let table_entry_start = table_start + index * metadata::OFFSET_TABLE_ENTRY_BYTE_SIZE;
+            let table_entry_range =
+                table_entry_start..(table_entry_start + metadata::OFFSET_TABLE_ENTRY_BYTE_SIZE);
+
+            if base.is_object {
+                let entry_offset =
+                    analysis::analyze_offset_table_entry(self.data, table_entry_start)
+                        .map_err(DeserializeError::AnalysisError)?;
+                self.push(
+                    table_entry_range,
+                    depth + 1,
+                    format!("offset table [{}]: entry at {}", index, entry_offset),
+                );
+
+                let header =
+                    self.explain_header(base_start + entry_offset, depth + 1, "entry header")?;
+                self.explain_key(&header, depth + 1)?;
+                self.explain_value(&header, base_start, depth + 1)?;
+            } else {
+                let label = format!("value header [{}]", index);
+                let header = self.explain_header(table_entry_start, depth + 1, &label)?;
+                self.explain_value(&header, base_start, depth + 1)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn explain_header(
+        &mut self,
+        header_start: usize,
+        depth: usize,
+        label: &str,
+    ) -> Result<metadata::ValueHeader, DeserializeError> {
+        let header = analysis::analyze_value_header(self.data, header_start)
+            .map_err(DeserializeError::AnalysisError)?;
+
+        let value_type = match header.qt_value_type {
+            analysis::QT_NULL_VALUE => "null",
+            analysis::QT_BOOL_VALUE => "bool",
+            analysis::QT_NUMBER_VALUE => "number",
+            analysis::QT_STRING_VALUE => "string",
+            analysis::QT_ARRAY_VALUE => "array",
+            analysis::QT_OBJECT_VALUE => "object",
+            _ => "unknown",
+        };
+        let mut description = format!(
+            "{}: type {} ({}), latin_or_int {}, latin_key {}, value {}",
+            label,
+            header.qt_value_type,
+            value_type,
+            u8::from(header.latin_or_int_value_flag),
+            u8::from(header.latin_key_flag),
+            header.value_bit_field
+        );
+
+        // Values stored in the header itself
+        match header.qt_value_type {
+            analysis::QT_BOOL_VALUE => {
+                let value = read::decode_bool(self.data, header_start)
+                    .map_err(DeserializeError::ReadError)?;
+                description += &format!(" = {}", value);
+            }
+            analysis::QT_NUMBER_VALUE if header.latin_or_int_value_flag => {
+                let value = read::decode_self_contained_number(self.data, header_start)
+                    .map_err(DeserializeError::ReadError)?;
+                description += &format!(" = {}", value);
+            }
+            _ => {}
+        }
+
+        self.push(
+            header_start..(header_start + metadata::VALUE_HEADER_BYTE_SIZE),
+            depth,
+            description,
+        );
+        Ok(header)
+    }
+
+    fn explain_key(
+        &mut self,
+        header: &metadata::ValueHeader,
+        depth: usize,
+    ) -> Result<(), DeserializeError> {
+        let (key, key_end) =
+            analysis::analyze_key(self.data, header).map_err(DeserializeError::AnalysisError)?;
+
+        let (bytefield, size_field_length, encoding, key) = match &key {
+            data::Key::Latin1String(bytefield) => (
+                bytefield,
+                metadata::LATIN1_SIZE_FIELD_LENGTH,
+                "latin1",
+                read::latin1_str(self.data, bytefield),
+            ),
+            data::Key::Utf16String(bytefield) => (
+                bytefield,
+                metadata::UTF16_SIZE_FIELD_LENGTH,
+                "utf16",
+                read::utf16_str(self.data, bytefield),
+            ),
+        };
+        let key = key.map_err(DeserializeError::ReadError)?;
+
+        self.push_string(
+            bytefield,
+            size_field_length,
+            key_end,
+            depth,
+            format!("{} key: {:?}", encoding, key),
+        );
+        Ok(())
+    }
+
+    fn explain_value(
+        &mut self,
+        header: &metadata::ValueHeader,
+        container_start: usize,
+        depth: usize,
+    ) -> Result<(), DeserializeError> {
+        match header.qt_value_type {
+            analysis::QT_ARRAY_VALUE | analysis::QT_OBJECT_VALUE => {
+                let base_start =
+                    analysis::analyze_nested_container_start(self.data, header, container_start)
+                        .map_err(DeserializeError::AnalysisError)?;
+                return self.explain_container(base_start, depth);
+            }
+            analysis::QT_NULL_VALUE | analysis::QT_BOOL_VALUE => return Ok(()),
+            analysis::QT_NUMBER_VALUE if header.latin_or_int_value_flag => return Ok(()),
+            _ => {}
+        }
+
+        let (value, value_end) = analysis::analyze_value(self.data, header, container_start)
+            .map_err(DeserializeError::AnalysisError)?;
+
+        match &value {
+            data::Value::Number(bytefield) => {
+                let number = read::decode_number(self.data, bytefield)
+                    .map_err(DeserializeError::ReadError)?;
+                self.push(bytefield.range.clone(), depth, format!("double {}", number));
+            }
+            data::Value::Latin1String(bytefield) => {
+                let string =
+                    read::latin1_str(self.data, bytefield).map_err(DeserializeError::ReadError)?;
+                let description = format!("latin1 string: {:?}", string);
+                self.push_string(
+                    bytefield,
+                    metadata::LATIN1_SIZE_FIELD_LENGTH,
+                    value_end,
+                    depth,
+                    description,
+                );
+            }
+            data::Value::Utf16String(bytefield) => {
+                let string =
+                    read::utf16_str(self.data, bytefield).map_err(DeserializeError::ReadError)?;
+                let description = format!("utf16 string: {:?}", string);
+                self.push_string(
+                    bytefield,
+                    metadata::UTF16_SIZE_FIELD_LENGTH,
+                    value_end,
+                    depth,
+                    description,
+                );
+            }
+            _ => unreachable!("other values are handled from their header"),
+        }
+        Ok(())
+    }
+
+    // Size field and characters of a string, followed by its alignment padding
+    fn push_string(
+        &mut self,
+        bytefield: &data::ByteField,
+        size_field_length: usize,
+        aligned_end: usize,
+        depth: usize,
+        description: String,
+    ) {
+        let string_start = bytefield.range.start - size_field_length;
+        self.push(string_start..bytefield.range.end, depth, description);
+        if aligned_end > bytefield.range.end {
+            self.push(
+                bytefield.range.end..aligned_end,
+                depth,
+                "padding".to_owned(),
+            );
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use std::collections::BTreeMap;
+
+use serde_json::Value;
+
+use crate::analysis::{self, data, header, metadata, AnalysisError};
+use crate::qbjs::DeserializeError;
+use crate::read;
+use crate::spans::{escape_pointer_token, parse_index, unescape_pointer_token};
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Token {
+    Key(String), // Object key or array index
+    Wildcard,    // Any key or index
+}
+
+impl Token {
+    fn matches_key(&self, key: &str) -> bool {
+        match self {
+            Token::Key(token) => token == key,
+            Token::Wildcard => true,
+        }
+    }
+
+    fn matches_index(&self, index: usize) -> bool {
+        match self {
+            Token::Key(token) => parse_index(token) == Some(index),
+            Token::Wildcard => true,
+        }
+    }
+}
+
+// Parses a JSON Pointer (RFC 6901) where a "*" reference token matches any key or index
+fn parse_pattern(pattern: &str) -> Result<Vec<Token>, DeserializeError> {
+    if pattern.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let tokens = pattern
+        .strip_prefix('/')
+        .ok_or_else(|| DeserializeError::InvalidJsonPointer(pattern.to_owned()))?;
+
+    tokens
+        .split('/')
+        .map(|token| match token {
+            "*" => Ok(Token::Wildcard),
+            token => unescape_pointer_token(token)
+                .map(Token::Key)
+                .ok_or_else(|| DeserializeError::InvalidJsonPointer(pattern.to_owned())),
+        })
+        .collect()
+}
+
+// Decodes the values matching the patterns, keyed by their JSON Pointer.
+// Only the containers leading to the matching values are analyzed, the other values are skipped.
+pub fn extract(
+    qbjs: &[u8],
+    patterns: &[&str],
+) -> Result<BTreeMap<String, Value>, DeserializeError> {
+    let patterns = patterns
+        .iter()
+        .map(|pattern| parse_pattern(pattern))
+        .collect::<Result<Vec<_>, _>>()?;
+    let patterns = patterns.iter().map(Vec::as_slice).collect::<Vec<_>>();
+
+    let mut extracted = BTreeMap::new();
+
+    // Same as deserialize_to_json, an empty document is an empty object
+    if qbjs.is_empty() {
+        if patterns.iter().any(|tokens| tokens.is_empty()) {
+            extracted.insert(String::new(), serde_json::json!({}));
+        }
+        return Ok(extracted);
+    }
+
+    if qbjs.len() < header::HEADER_LENGTH {
+        return Err(DeserializeError::InsufficientData);
+    }
+
+    header::QbjsHeader::from_data(&qbjs[0..header::HEADER_LENGTH])
+        .map_err(AnalysisError::header)
+        .map_err(DeserializeError::AnalysisError)?;
+
+    let extractor = Extractor { data: qbjs };
+
+    if patterns.iter().any(|tokens| tokens.is_empty()) {
+        let document = crate::qbjs::analyze_root_container(qbjs)?;
+        let value = read::read_value(qbjs, &document).map_err(DeserializeError::ReadError)?;
+        extracted.insert(String::new(), value);
+    }
+
+    let remaining_patterns = patterns
+        .iter()
+        .filter(|tokens| !tokens.is_empty())
+        .copied()
+        .collect::<Vec<_>>();
+    extractor.extract_from_container(
+        header::HEADER_LENGTH,
+        None,
+        &remaining_patterns,
+        "",
+        &mut extracted,
+    )?;
+
+    Ok(extracted)
+}
+
+struct Extractor<'a> {
+    data: &'a [u8],
+}
+
+impl Extractor<'_> {
+    // Every pattern has at least one token left. The container must be an object or an array
+    // when its value header says so, the root can be either.
+    fn extract_from_container(
+        &self,
+        base_start: usize,
+        expected_is_object: Option<bool>,
+        patterns: &[&[Token]],
+        pointer: &str,
+        extracted: &mut BTreeMap<String, Value>,
+    ) -> Result<(), DeserializeError> {
+        if patterns.is_empty() {
+            return Ok(());
+        }
+
+        let base = analysis::analyze_container_base(self.data, base_start)
+            .map_err(DeserializeError::AnalysisError)?;
+        match (expected_is_object, base.is_object) {
+            (Some(false), true) => Err(data::Error::InvalidArrayContainer),
+            (Some(true), false) => Err(data::Error::InvalidObjectContainer),
+            _ => Ok(()),
+        }
+        .map_err(AnalysisError::data)
+        .map_err(DeserializeError::AnalysisError)?;
+        let table_start = base_start + base.table_offset as usize;
+
+        for index in 0..base.length as usize {
+            let table_entry_start = table_start + index * metadata::OFFSET_TABLE_ENTRY_BYTE_SIZE;
+
+            let (header, reference_token, matching_patterns) = if base.is_object {
+                let entry_start = base_start
+                    + analysis::analyze_offset_table_entry(self.data, table_entry_start)
+                        .map_err(DeserializeError::AnalysisError)?;
+                let header = analysis::analyze_value_header(self.data, entry_start)
+                    .map_err(DeserializeError::AnalysisError)?;
+                let (key, _) = analysis::analyze_key(self.data, &header)
+                    .map_err(DeserializeError::AnalysisError)?;
+                let key = match &key {
+                    data::Key::Latin1String(bytefield) => read::latin1_str(self.data, bytefield),
+                    data::Key::Utf16String(bytefield) => read::utf16_str(self.data, bytefield),
+                }
+                .map_err(DeserializeError::ReadError)?;
+
+                let matching_patterns = patterns
+                    .iter()
+                    .filter(|tokens| tokens[0].matches_key(&key))
+                    .map(|tokens| &tokens[1..])
+                    .collect::<Vec<_>>();
+                (header, escape_pointer_token(&key), matching_patterns)
+            } else {
+                let matching_patterns = patterns
+                    .iter()
+                    .filter(|tokens| tokens[0].matches_index(index))
+                    .map(|tokens| &tokens[1..])
+                    .collect::<Vec<_>>();
+                if matching_patterns.is_empty() {
+                    continue; // Skipped without reading its header
+                }
+                let header = analysis::analyze_value_header(self.data, table_entry_start)
+                    .map_err(DeserializeError::AnalysisError)?;
+                (header, index.to_string(), matching_patterns)
+            };
+
+            if matching_patterns.is_empty() {
+                continue;
+            }
+
+            let value_pointer = format!("{}/{}", pointer, reference_token);
+            self.extract_from_value(
+                &header,
+                base_start,
+                &matching_patterns,
+                &value_pointer,
+                extracted,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn extract_from_value(
+        &self,
+        header: &metadata::ValueHeader,
+        container_start: usize,
+        patterns: &[&[Token]],
+        pointer: &str,
+        extracted: &mut BTreeMap<String, Value>,
+    ) -> Result<(), DeserializeError> {
+        if patterns.iter().any(|tokens| tokens.is_empty()) {
+            let (value, _) = analysis::analyze_value(self.data, header, container_start)
+                .map_err(DeserializeError::AnalysisError)?;
+            let value = read::read_value(self.data, &value).map_err(DeserializeError::ReadError)?;
+            extracted.insert(pointer.to_owned(), value);
+        }
+
+        let is_container = matches!(
+            header.qt_value_type,
+            analysis::QT_ARRAY_VALUE | analysis::QT_OBJECT_VALUE
+        );
+        if !is_container {
+            return Ok(()); // Remaining tokens can't match inside a scalar
+        }
+
+        let remaining_patterns = patterns
+            .iter()
+            .filter(|tokens| !tokens.is_empty())
+            .copied()
+            .collect::<Vec<_>>();
+        let base_start = container_start + header.value_bit_field as usize;
+        let is_object = header.qt_value_type == analysis::QT_OBJECT_VALUE;
+        self.extract_from_container(
+            base_start,
+            Some(is_object),
+            &remaining_patterns,
+            pointer,
+            extracted,
+        )
+    }
+}
@@ -0,0 +1,589 @@
+// C API, see include/qbjs.h which cbindgen generates from this file.
+// Doc comments are copied to the header, so they are written for C callers.
+
+use std::borrow::Cow;
+use std::ffi::{c_char, CStr, CString};
+use std::ptr;
+use std::slice;
+
+use crate::analysis::data;
+use crate::qbjs;
+use crate::read::{self, ReadError};
+
+/// Error codes set in `qbjs_error`.
+#[repr(C)]
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum QbjsErrorCode {
+    QBJS_OK = 0,
+    /// A null pointer or a key that isn't valid UTF-8.
+    QBJS_INVALID_ARGUMENT,
+    /// The bytes aren't a valid document.
+    QBJS_DESERIALIZE_ERROR,
+    /// A value of the document couldn't be decoded.
+    QBJS_READ_ERROR,
+    /// The value doesn't have the type the function expects.
+    QBJS_TYPE_MISMATCH,
+    /// The key or index isn't in the container.
+    QBJS_NOT_FOUND,
+    /// The string holds a nul character, it can't be returned as a C string.
+    QBJS_INTERIOR_NUL,
+}
+
+/// Set by functions failing with a non null `err` argument.
+/// The message, when not null, must be freed with `qbjs_free`.
+#[repr(C)]
+#[derive(Debug)]
+pub struct QbjsError {
+    pub code: QbjsErrorCode,
+    pub message: *mut c_char,
+}
+
+/// Types of the values, both numbers stored as integers and as doubles are numbers.
+#[repr(C)]
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum QbjsType {
+    QBJS_NULL,
+    QBJS_BOOL,
+    QBJS_NUMBER,
+    QBJS_STRING,
+    QBJS_ARRAY,
+    QBJS_OBJECT,
+}
+
+/// Analyzed copy of a document, values are decoded when they are queried.
+pub struct QbjsDocumentHandle {
+    data: Vec<u8>,
+    root: data::Value,
+}
+
+/// Value of a document, valid as long as its document.
+pub struct QbjsValue {
+    _private: [u8; 0],
+}
+
+// The callers guarantee that err, when not null, is writable
+unsafe fn set_error(err: *mut QbjsError, code: QbjsErrorCode, message: &str) {
+    if err.is_null() {
+        return;
+    }
+    let message = CString::new(message.replace('\0', "\\0")).expect("nul was escaped");
+    *err = QbjsError {
+        code,
+        message: message.into_raw(),
+    };
+}
+
+unsafe fn set_ok(err: *mut QbjsError) {
+    if err.is_null() {
+        return;
+    }
+    *err = QbjsError {
+        code: QbjsErrorCode::QBJS_OK,
+        message: ptr::null_mut(),
+    };
+}
+
+// Writes the string to out, which the callers guarantee to be writable when not null,
+// or sets the error
+unsafe fn return_string(string: &str, out: *mut *mut c_char, err: *mut QbjsError) -> bool {
+    if out.is_null() {
+        set_error(err, QbjsErrorCode::QBJS_INVALID_ARGUMENT, "out is null");
+        return false;
+    }
+    match CString::new(string) {
+        Ok(string) => {
+            *out = string.into_raw();
+            set_ok(err);
+            true
+        }
+        Err(error) => {
+            set_error(err, QbjsErrorCode::QBJS_INTERIOR_NUL, &error.to_string());
+            false
+        }
+    }
+}
+
+// The bytes given by the caller, null is accepted for empty documents
+unsafe fn input_bytes<'a>(data: *const u8, length: usize) -> Option<&'a [u8]> {
+    if length == 0 {
+        Some(&[])
+    } else if data.is_null() {
+        None
+    } else {
+        Some(slice::from_raw_parts(data, length))
+    }
+}
+
+// Values are only created from the data::Value of a document, the callers guarantee
+// that it's still alive
+unsafe fn value_ref<'a>(value: *const QbjsValue) -> Option<&'a data::Value> {
+    value.cast::<data::Value>().as_ref()
+}
+
+fn value_ptr(value: &data::Value) -> *const QbjsValue {
+    (value as *const data::Value).cast()
+}
+
+fn read_key<'a>(data: &'a [u8], key: &data::Key) -> Result<Cow<'a, str>, ReadError> {
+    match key {
+        data::Key::Latin1String(bytefield) => read::latin1_str(data, bytefield),
+        data::Key::Utf16String(bytefield) => read::utf16_str(data, bytefield),
+    }
+}
+
+/// Decodes the document to JSON text, written to `out` which must be freed with `qbjs_free`.
+/// An empty document decodes to `{}`.
+///
+/// # Safety
+///
+/// `data` must point to `length` readable bytes, or be null when `length` is 0.
+/// `out` must be writable and `err` either null or writable.
+#[no_mangle]
+pub unsafe extern "C" fn qbjs_to_json(
+    data: *const u8,
+    length: usize,
+    out: *mut *mut c_char,
+    err: *mut QbjsError,
+) -> bool {
+    let qbjs = match input_bytes(data, length) {
+        Some(qbjs) => qbjs,
+        None => {
+            set_error(err, QbjsErrorCode::QBJS_INVALID_ARGUMENT, "data is null");
+            return false;
+        }
+    };
+
+    match qbjs::deserialize_to_json(qbjs) {
+        Ok(value) => return_string(&value.to_string(), out, err),
+        Err(error) => {
+            set_error(
+                err,
+                QbjsErrorCode::QBJS_DESERIALIZE_ERROR,
+                &error.to_string(),
+            );
+            false
+        }
+    }
+}
+
+/// Frees a string returned by this library, null is ignored.
+///
+/// # Safety
+///
+/// `string` must have been returned by this library and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn qbjs_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+/// Copies and analyzes the document, returns null on failure.
+/// The document must be freed with `qbjs_document_free`.
+///
+/// # Safety
+///
+/// `data` must point to `length` readable bytes, or be null when `length` is 0.
+/// `err` must be either null or writable.
+#[no_mangle]
+pub unsafe extern "C" fn qbjs_document_new(
+    data: *const u8,
+    length: usize,
+    err: *mut QbjsError,
+) -> *mut QbjsDocumentHandle {
+    let qbjs = match input_bytes(data, length) {
+        Some(qbjs) => qbjs,
+        None => {
+            set_error(err, QbjsErrorCode::QBJS_INVALID_ARGUMENT, "data is null");
+            return ptr::null_mut();
+        }
+    };
+
+    // An empty document is an empty object, the same as for qbjs_to_json
+    let root = if qbjs.is_empty() {
+        Ok(data::Value::Object(data::Object {
+            container: data::ByteField { range: 0..0 },
+            table: data::ByteField { range: 0..0 },
+            entries: Vec::new(),
+        }))
+    } else {
+        qbjs::analyze_root_container(qbjs)
+    };
+
+    match root {
+        Ok(root) => {
+            set_ok(err);
+            Box::into_raw(Box::new(QbjsDocumentHandle {
+                data: qbjs.to_vec(),
+                root,
+            }))
+        }
+        Err(error) => {
+            set_error(
+                err,
+                QbjsErrorCode::QBJS_DESERIALIZE_ERROR,
+                &error.to_string(),
+            );
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Frees the document and invalidates its values, null is ignored.
+///
+/// # Safety
+///
+/// `document` must have been returned by `qbjs_document_new` and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn qbjs_document_free(document: *mut QbjsDocumentHandle) {
+    if !document.is_null() {
+        drop(Box::from_raw(document));
+    }
+}
+
+/// Returns the root array or object of the document, null when `document` is null.
+///
+/// # Safety
+///
+/// `document` must be null or a live document.
+#[no_mangle]
+pub unsafe extern "C" fn qbjs_document_root(
+    document: *const QbjsDocumentHandle,
+) -> *const QbjsValue {
+    match document.as_ref() {
+        Some(document) => value_ptr(&document.root),
+        None => ptr::null(),
+    }
+}
+
+/// Returns the type of the value, `QBJS_NULL` when `value` is null.
+///
+/// # Safety
+///
+/// `value` must be null or a value of a live document.
+#[no_mangle]
+pub unsafe extern "C" fn qbjs_value_type(value: *const QbjsValue) -> QbjsType {
+    match value_ref(value) {
+        None | Some(data::Value::Null(_)) => QbjsType::QBJS_NULL,
+        Some(data::Value::Bool(_)) => QbjsType::QBJS_BOOL,
+        Some(data::Value::SelfContainedNumber(_) | data::Value::Number(_)) => QbjsType::QBJS_NUMBER,
+        Some(data::Value::Latin1String(_) | data::Value::Utf16String(_)) => QbjsType::QBJS_STRING,
+        Some(data::Value::Array(_)) => QbjsType::QBJS_ARRAY,
+        Some(data::Value::Object(_)) => QbjsType::QBJS_OBJECT,
+    }
+}
+
+/// Returns the number of values of an array or entries of an object, 0 for other values.
+///
+/// # Safety
+///
+/// `value` must be null or a value of a live document.
+#[no_mangle]
+pub unsafe extern "C" fn qbjs_value_length(value: *const QbjsValue) -> usize {
+    match value_ref(value) {
+        Some(data::Value::Array(array)) => array.values.len(),
+        Some(data::Value::Object(object)) => object.entries.len(),
+        _ => 0,
+    }
+}
+
+/// Returns the value of the object's entry with the given key, or null on failure.
+/// When the object holds the key several times, the last entry is returned.
+///
+/// # Safety
+///
+/// `document` must be a live document and `value` one of its values.
+/// `key` must be a nul terminated string and `err` either null or writable.
+#[no_mangle]
+pub unsafe extern "C" fn qbjs_value_get(
+    document: *const QbjsDocumentHandle,
+    value: *const QbjsValue,
+    key: *const c_char,
+    err: *mut QbjsError,
+) -> *const QbjsValue {
+    let (document, value) = match (document.as_ref(), value_ref(value)) {
+        (Some(document), Some(value)) if !key.is_null() => (document, value),
+        _ => {
+            set_error(err, QbjsErrorCode::QBJS_INVALID_ARGUMENT, "null argument");
+            return ptr::null();
+        }
+    };
+    let key = match CStr::from_ptr(key).to_str() {
+        Ok(key) => key,
+        Err(error) => {
+            set_error(
+                err,
+                QbjsErrorCode::QBJS_INVALID_ARGUMENT,
+                &error.to_string(),
+            );
+            return ptr::null();
+        }
+    };
+    let object = match value {
+        data::Value::Object(object) => object,
+        _ => {
+            set_error(err, QbjsErrorCode::QBJS_TYPE_MISMATCH, "not an object");
+            return ptr::null();
+        }
+    };
+
+    let mut found = None;
+    for entry in &object.entries {
+        match read_key(&document.data, &entry.key) {
+            Ok(entry_key) if entry_key == key => found = Some(&entry.value),
+            Ok(_) => {}
+            Err(error) => {
+                set_error(err, QbjsErrorCode::QBJS_READ_ERROR, &error.to_string());
+                return ptr::null();
+            }
+        }
+    }
+
+    match found {
+        Some(found) => {
+            set_ok(err);
+            value_ptr(found)
+        }
+        None => {
+            set_error(err, QbjsErrorCode::QBJS_NOT_FOUND, &format!("{:?}", key));
+            ptr::null()
+        }
+    }
+}
+
+/// Returns the value at the given index of an array, or of the entry at the given index of
+/// an object in storage order, or null on failure.
+///
+/// # Safety
+///
+/// `value` must be a value of a live document and `err` either null or writable.
+#[no_mangle]
+pub unsafe extern "C" fn qbjs_value_index(
+    value: *const QbjsValue,
+    index: usize,
+    err: *mut QbjsError,
+) -> *const QbjsValue {
+    let found = match value_ref(value) {
+        Some(data::Value::Array(array)) => array.values.get(index),
+        Some(data::Value::Object(object)) => object.entries.get(index).map(|entry| &entry.value),
+        Some(_) => {
+            set_error(err, QbjsErrorCode::QBJS_TYPE_MISMATCH, "not a container");
+            return ptr::null();
+        }
+        None => {
+            set_error(err, QbjsErrorCode::QBJS_INVALID_ARGUMENT, "value is null");
+            return ptr::null();
+        }
+    };
+
+    match found {
+        Some(found) => {
+            set_ok(err);
+            value_ptr(found)
+        }
+        None => {
+            set_error(err, QbjsErrorCode::QBJS_NOT_FOUND, &format!("{:?}", index));
+            ptr::null()
+        }
+    }
+}
+
+/// Writes the key of the object's entry at the given index to `out`, which must be freed
+/// with `qbjs_free`.
+///
+/// # Safety
+///
+/// `document` must be a live document and `value` one of its values.
+/// `out` must be writable and `err` either null or writable.
+#[no_mangle]
+pub unsafe extern "C" fn qbjs_value_key(
+    document: *const QbjsDocumentHandle,
+    value: *const QbjsValue,
+    index: usize,
+    out: *mut *mut c_char,
+    err: *mut QbjsError,
+) -> bool {
+    let (document, value) = match (document.as_ref(), value_ref(value)) {
+        (Some(document), Some(value)) => (document, value),
+        _ => {
+            set_error(err, QbjsErrorCode::QBJS_INVALID_ARGUMENT, "null argument");
+            return false;
+        }
+    };
+    let entry = match value {
+        data::Value::Object(object) => match object.entries.get(index) {
+            Some(entry) => entry,
+            None => {
+                set_error(err, QbjsErrorCode::QBJS_NOT_FOUND, &format!("{:?}", index));
+                return false;
+            }
+        },
+        _ => {
+            set_error(err, QbjsErrorCode::QBJS_TYPE_MISMATCH, "not an object");
+            return false;
+        }
+    };
+
+    match read_key(&document.data, &entry.key) {
+        Ok(key) => return_string(&key, out, err),
+        Err(error) => {
+            set_error(err, QbjsErrorCode::QBJS_READ_ERROR, &error.to_string());
+            false
+        }
+    }
+}
+
+/// Writes the boolean to `out`.
+///
+/// # Safety
+///
+/// `document` must be a live document and `value` one of its values.
+/// `out` must be writable and `err` either null or writable.
+#[no_mangle]
+pub unsafe extern "C" fn qbjs_value_bool(
+    document: *const QbjsDocumentHandle,
+    value: *const QbjsValue,
+    out: *mut bool,
+    err: *mut QbjsError,
+) -> bool {
+    let (document, value) = match (document.as_ref(), value_ref(value)) {
+        (Some(document), Some(value)) if !out.is_null() => (document, value),
+        _ => {
+            set_error(err, QbjsErrorCode::QBJS_INVALID_ARGUMENT, "null argument");
+            return false;
+        }
+    };
+
+    let decoded = match value {
+        data::Value::Bool(position) => read::decode_bool(&document.data, *position),
+        _ => {
+            set_error(err, QbjsErrorCode::QBJS_TYPE_MISMATCH, "not a bool");
+            return false;
+        }
+    };
+    match decoded {
+        Ok(decoded) => {
+            *out = decoded;
+            set_ok(err);
+            true
+        }
+        Err(error) => {
+            set_error(err, QbjsErrorCode::QBJS_READ_ERROR, &error.to_string());
+            false
+        }
+    }
+}
+
+/// Writes the number to `out`, whether it's stored as an integer or a double.
+///
+/// # Safety
+///
+/// `document` must be a live document and `value` one of its values.
+/// `out` must be writable and `err` either null or writable.
+#[no_mangle]
+pub unsafe extern "C" fn qbjs_value_number(
+    document: *const QbjsDocumentHandle,
+    value: *const QbjsValue,
+    out: *mut f64,
+    err: *mut QbjsError,
+) -> bool {
+    let (document, value) = match (document.as_ref(), value_ref(value)) {
+        (Some(document), Some(value)) if !out.is_null() => (document, value),
+        _ => {
+            set_error(err, QbjsErrorCode::QBJS_INVALID_ARGUMENT, "null argument");
+            return false;
+        }
+    };
+
+    let decoded = match value {
+        data::Value::SelfContainedNumber(position) => {
+            read::decode_self_contained_number(&document.data, *position).map(f64::from)
+        }
+        data::Value::Number(bytefield) => read::decode_number(&document.data, bytefield),
+        _ => {
+            set_error(err, QbjsErrorCode::QBJS_TYPE_MISMATCH, "not a number");
+            return false;
+        }
+    };
+    match decoded {
+        Ok(decoded) => {
+            *out = decoded;
+            set_ok(err);
+            true
+        }
+        Err(error) => {
+            set_error(err, QbjsErrorCode::QBJS_READ_ERROR, &error.to_string());
+            false
+        }
+    }
+}
+
+/// Writes the UTF-8 string to `out`, which must be freed with `qbjs_free`.
+///
+/// # Safety
+///
+/// `document` must be a live document and `value` one of its values.
+/// `out` must be writable and `err` either null or writable.
+#[no_mangle]
+pub unsafe extern "C" fn qbjs_value_string(
+    document: *const QbjsDocumentHandle,
+    value: *const QbjsValue,
+    out: *mut *mut c_char,
+    err: *mut QbjsError,
+) -> bool {
+    let (document, value) = match (document.as_ref(), value_ref(value)) {
+        (Some(document), Some(value)) => (document, value),
+        _ => {
+            set_error(err, QbjsErrorCode::QBJS_INVALID_ARGUMENT, "null argument");
+            return false;
+        }
+    };
+
+    let decoded = match value {
+        data::Value::Latin1String(bytefield) => read::latin1_str(&document.data, bytefield),
+        data::Value::Utf16String(bytefield) => read::utf16_str(&document.data, bytefield),
+        _ => {
+            set_error(err, QbjsErrorCode::QBJS_TYPE_MISMATCH, "not a string");
+            return false;
+        }
+    };
+    match decoded {
+        Ok(decoded) => return_string(&decoded, out, err),
+        Err(error) => {
+            set_error(err, QbjsErrorCode::QBJS_READ_ERROR, &error.to_string());
+            false
+        }
+    }
+}
+
+/// Decodes the value to JSON text, written to `out` which must be freed with `qbjs_free`.
+///
+/// # Safety
+///
+/// `document` must be a live document and `value` one of its values.
+/// `out` must be writable and `err` either null or writable.
+#[no_mangle]
+pub unsafe extern "C" fn qbjs_value_to_json(
+    document: *const QbjsDocumentHandle,
+    value: *const QbjsValue,
+    out: *mut *mut c_char,
+    err: *mut QbjsError,
+) -> bool {
+    let (document, value) = match (document.as_ref(), value_ref(value)) {
+        (Some(document), Some(value)) => (document, value),
+        _ => {
+            set_error(err, QbjsErrorCode::QBJS_INVALID_ARGUMENT, "null argument");
+            return false;
+        }
+    };
+
+    match read::read_value(&document.data, value) {
+        Ok(decoded) => return_string(&decoded.to_string(), out, err),
+        Err(error) => {
+            set_error(err, QbjsErrorCode::QBJS_READ_ERROR, &error.to_string());
+            false
+        }
+    }
+}
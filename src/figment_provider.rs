@@ -0,0 +1,78 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use figment::value::{Dict, Map, Value};
+use figment::{Error, Metadata, Profile, Provider};
+
+use crate::qbjs;
+
+#[derive(Debug, Clone)]
+enum QbjsSource {
+    File(PathBuf),
+    Bytes(Vec<u8>),
+}
+
+// Provider of the entries of a document, whose root must be an object. Like figment's
+// file formats, the entries go to the default profile unless another profile is selected
+// or the provider is nested.
+#[derive(Debug, Clone)]
+pub struct Qbjs {
+    source: QbjsSource,
+    profile: Option<Profile>,
+}
+
+impl Qbjs {
+    // A missing file provides no value, the same as figment's file formats
+    pub fn file<P: Into<PathBuf>>(path: P) -> Self {
+        Qbjs {
+            source: QbjsSource::File(path.into()),
+            profile: Some(Profile::Default),
+        }
+    }
+
+    pub fn bytes<B: Into<Vec<u8>>>(qbjs: B) -> Self {
+        Qbjs {
+            source: QbjsSource::Bytes(qbjs.into()),
+            profile: Some(Profile::Default),
+        }
+    }
+
+    // The top level keys of the document are profiles
+    pub fn nested(mut self) -> Self {
+        self.profile = None;
+        self
+    }
+
+    pub fn profile<P: Into<Profile>>(mut self, profile: P) -> Self {
+        self.profile = Some(profile.into());
+        self
+    }
+}
+
+impl Provider for Qbjs {
+    fn metadata(&self) -> Metadata {
+        match &self.source {
+            QbjsSource::File(path) => Metadata::from("qbjs file", path.as_path()),
+            QbjsSource::Bytes(_) => Metadata::named("qbjs document"),
+        }
+    }
+
+    fn data(&self) -> Result<Map<Profile, Dict>, Error> {
+        let qbjs = match &self.source {
+            QbjsSource::File(path) => match fs::read(path) {
+                Ok(qbjs) => qbjs,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Map::new()),
+                Err(err) => return Err(Error::from(err.to_string())),
+            },
+            QbjsSource::Bytes(qbjs) => qbjs.clone(),
+        };
+
+        let json = qbjs::deserialize_to_json(&qbjs).map_err(|err| Error::from(err.to_string()))?;
+        let value = Value::serialize(json)?;
+        match &self.profile {
+            Some(profile) => Ok(profile.collect(value.deserialize::<Dict>()?)),
+            None => value.deserialize::<Map<Profile, Dict>>(),
+        }
+    }
+}
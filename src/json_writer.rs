@@ -0,0 +1,511 @@
+use std::io;
+use std::ops;
+
+use serde_json::ser::{CharEscape, CompactFormatter, Formatter, PrettyFormatter};
+
+use crate::analysis::data;
+use crate::read::{self, ReadError};
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum WriteError {
+    ReadError(ReadError),
+    IoError(io::ErrorKind),
+}
+
+impl WriteError {
+    pub fn read(err: ReadError) -> Self {
+        WriteError::ReadError(err)
+    }
+    pub fn io(err: io::Error) -> Self {
+        WriteError::IoError(err.kind())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct WriteOptions {
+    pub pretty: bool, // Same layout as serde_json::to_writer_pretty, or QJsonDocument::Indented in Qt mode
+    pub qt_compatible: bool, // Reproduces the text of Qt5's QJsonDocument::toJson
+}
+
+impl WriteOptions {
+    // Same output as QJsonDocument::toJson(QJsonDocument::Indented)
+    pub fn qt_indented() -> Self {
+        WriteOptions {
+            pretty: true,
+            qt_compatible: true,
+        }
+    }
+
+    // Same output as QJsonDocument::toJson(QJsonDocument::Compact)
+    pub fn qt_compact() -> Self {
+        WriteOptions {
+            pretty: false,
+            qt_compatible: true,
+        }
+    }
+}
+
+// Writes the JSON text of an analyzed value straight to the writer, decoding the strings and
+// numbers from the data slice on the fly.
+// Nothing is rolled back on error: the writer may have received a partial document.
+pub fn write_value<W: io::Write>(
+    data: &[u8],
+    value: &data::Value,
+    writer: W,
+    options: WriteOptions,
+) -> Result<(), WriteError> {
+    if options.qt_compatible {
+        let mut json_writer = JsonWriter::new(data, writer, QtFormatter::new(!options.pretty));
+        json_writer.write_value(value)?;
+        // Qt terminates indented documents with a line feed
+        if options.pretty {
+            json_writer
+                .writer
+                .write_all(b"\n")
+                .map_err(WriteError::io)?;
+        }
+        Ok(())
+    } else if options.pretty {
+        JsonWriter::new(data, writer, PrettyFormatter::new()).write_value(value)
+    } else {
+        JsonWriter::new(data, writer, CompactFormatter).write_value(value)
+    }
+}
+
+// Writes the JSON text of an empty document, consistent with `qbjs::deserialize_to_json`.
+// Qt doesn't produce any text for a null document.
+pub fn write_empty_document<W: io::Write>(
+    writer: W,
+    options: WriteOptions,
+) -> Result<(), WriteError> {
+    if options.qt_compatible {
+        Ok(())
+    } else if options.pretty {
+        JsonWriter::new(&[], writer, PrettyFormatter::new()).write_empty_object()
+    } else {
+        JsonWriter::new(&[], writer, CompactFormatter).write_empty_object()
+    }
+}
+
+struct JsonWriter<'a, W, F> {
+    data: &'a [u8],
+    writer: W,
+    formatter: F,
+}
+
+// How values that have no strict JSON representation are written
+trait DecodingPolicy {
+    // Qt writes null for infinite and NaN doubles whereas serde_json has no representation for them
+    fn write_non_finite<W: io::Write>(&mut self, writer: &mut W) -> Result<(), WriteError> {
+        let _ = writer;
+        Err(WriteError::read(ReadError::FailedToDecodeNumber))
+    }
+
+    // Qt escapes unpaired UTF-16 surrogates as \uXXXX where strict decoding fails
+    fn escape_unpaired_surrogates(&self) -> bool {
+        false
+    }
+}
+
+impl DecodingPolicy for CompactFormatter {}
+impl DecodingPolicy for PrettyFormatter<'_> {}
+
+impl<'a, W: io::Write, F: Formatter + DecodingPolicy> JsonWriter<'a, W, F> {
+    fn new(data: &'a [u8], writer: W, formatter: F) -> Self {
+        JsonWriter {
+            data,
+            writer,
+            formatter,
+        }
+    }
+
+    fn write_empty_object(&mut self) -> Result<(), WriteError> {
+        self.formatter
+            .begin_object(&mut self.writer)
+            .map_err(WriteError::io)?;
+        self.formatter
+            .end_object(&mut self.writer)
+            .map_err(WriteError::io)
+    }
+
+    fn write_value(&mut self, value: &data::Value) -> Result<(), WriteError> {
+        match value {
+            data::Value::Null(_) => self
+                .formatter
+                .write_null(&mut self.writer)
+                .map_err(WriteError::io),
+            data::Value::Bool(position) => {
+                let value = read::decode_bool(self.data, *position).map_err(WriteError::read)?;
+                self.formatter
+                    .write_bool(&mut self.writer, value)
+                    .map_err(WriteError::io)
+            }
+            data::Value::SelfContainedNumber(position) => {
+                let value = read::decode_self_contained_number(self.data, *position)
+                    .map_err(WriteError::read)?;
+                self.formatter
+                    .write_i32(&mut self.writer, value)
+                    .map_err(WriteError::io)
+            }
+            data::Value::Number(bytefield) => {
+                let value = read::decode_number(self.data, bytefield).map_err(WriteError::read)?;
+                if !value.is_finite() {
+                    return self.formatter.write_non_finite(&mut self.writer);
+                }
+                self.formatter
+                    .write_f64(&mut self.writer, value)
+                    .map_err(WriteError::io)
+            }
+            data::Value::Latin1String(bytefield) => self.write_latin1_string(bytefield),
+            data::Value::Utf16String(bytefield) => self.write_utf16_string(bytefield),
+            data::Value::Array(array) => self.write_array(array),
+            data::Value::Object(object) => self.write_object(object),
+        }
+    }
+
+    fn write_array(&mut self, array: &data::Array) -> Result<(), WriteError> {
+        self.formatter
+            .begin_array(&mut self.writer)
+            .map_err(WriteError::io)?;
+
+        for (i, value) in array.values.iter().enumerate() {
+            self.formatter
+                .begin_array_value(&mut self.writer, i == 0)
+                .map_err(WriteError::io)?;
+            self.write_value(value)?;
+            self.formatter
+                .end_array_value(&mut self.writer)
+                .map_err(WriteError::io)?;
+        }
+
+        self.formatter
+            .end_array(&mut self.writer)
+            .map_err(WriteError::io)
+    }
+
+    fn write_object(&mut self, object: &data::Object) -> Result<(), WriteError> {
+        self.formatter
+            .begin_object(&mut self.writer)
+            .map_err(WriteError::io)?;
+
+        for (i, entry) in object.entries.iter().enumerate() {
+            self.formatter
+                .begin_object_key(&mut self.writer, i == 0)
+                .map_err(WriteError::io)?;
+            match &entry.key {
+                data::Key::Latin1String(bytefield) => self.write_latin1_string(bytefield)?,
+                data::Key::Utf16String(bytefield) => self.write_utf16_string(bytefield)?,
+            }
+            self.formatter
+                .end_object_key(&mut self.writer)
+                .map_err(WriteError::io)?;
+
+            self.formatter
+                .begin_object_value(&mut self.writer)
+                .map_err(WriteError::io)?;
+            self.write_value(&entry.value)?;
+            self.formatter
+                .end_object_value(&mut self.writer)
+                .map_err(WriteError::io)?;
+        }
+
+        self.formatter
+            .end_object(&mut self.writer)
+            .map_err(WriteError::io)
+    }
+
+    fn write_latin1_string(&mut self, bytefield: &data::ByteField) -> Result<(), WriteError> {
+        let string_data =
+            read::latin1_string_data(self.data, bytefield).map_err(WriteError::read)?;
+
+        self.formatter
+            .begin_string(&mut self.writer)
+            .map_err(WriteError::io)?;
+
+        // Runs of printable ASCII are valid UTF-8 and can be written without any conversion
+        let mut run_start = 0;
+        for (i, byte) in string_data.iter().enumerate() {
+            if byte.is_ascii() && !needs_escape(*byte as char) {
+                continue;
+            }
+            self.write_ascii_run(&string_data[run_start..i])?;
+            self.write_char(*byte as char)?;
+            run_start = i + 1;
+        }
+        self.write_ascii_run(&string_data[run_start..])?;
+
+        self.formatter
+            .end_string(&mut self.writer)
+            .map_err(WriteError::io)
+    }
+
+    fn write_utf16_string(&mut self, bytefield: &data::ByteField) -> Result<(), WriteError> {
+        let string_data =
+            read::utf16_string_data(self.data, bytefield).map_err(WriteError::read)?;
+
+        self.formatter
+            .begin_string(&mut self.writer)
+            .map_err(WriteError::io)?;
+
+        let code_units = string_data
+            .chunks_exact(2)
+            .map(|unit| u16::from_le_bytes([unit[0], unit[1]]));
+        for c in char::decode_utf16(code_units) {
+            match c {
+                Ok(c) => self.write_char(c)?,
+                Err(err) if self.formatter.escape_unpaired_surrogates() => {
+                    let escape = format!("\\u{:04x}", err.unpaired_surrogate());
+                    self.formatter
+                        .write_string_fragment(&mut self.writer, &escape)
+                        .map_err(WriteError::io)?;
+                }
+                Err(_) => return Err(WriteError::read(ReadError::FailedToDecodeUtf16String)),
+            }
+        }
+
+        self.formatter
+            .end_string(&mut self.writer)
+            .map_err(WriteError::io)
+    }
+
+    fn write_ascii_run(&mut self, run: &[u8]) -> Result<(), WriteError> {
+        if run.is_empty() {
+            return Ok(());
+        }
+
+        // Only ASCII bytes are gathered in runs
+        let fragment = std::str::from_utf8(run).expect("ASCII run must be valid UTF-8");
+        self.formatter
+            .write_string_fragment(&mut self.writer, fragment)
+            .map_err(WriteError::io)
+    }
+
+    fn write_char(&mut self, c: char) -> Result<(), WriteError> {
+        let result = match char_escape(c) {
+            Some(escape) => self.formatter.write_char_escape(&mut self.writer, escape),
+            None => {
+                let mut buffer = [0; 4];
+                self.formatter
+                    .write_string_fragment(&mut self.writer, c.encode_utf8(&mut buffer))
+            }
+        };
+        result.map_err(WriteError::io)
+    }
+}
+
+// Mimics qjsonwriter.cpp from Qt5: 4 spaces indentation, a space after the colons of object
+// keys, and the closing bracket of empty containers on its own line.
+struct QtFormatter {
+    compact: bool,
+    indent: usize,
+    has_value: bool,
+}
+
+impl QtFormatter {
+    fn new(compact: bool) -> Self {
+        QtFormatter {
+            compact,
+            indent: 0,
+            has_value: false,
+        }
+    }
+
+    fn begin_container<W: ?Sized + io::Write>(
+        &mut self,
+        writer: &mut W,
+        bracket: &[u8],
+    ) -> io::Result<()> {
+        self.has_value = false;
+        writer.write_all(bracket)?;
+        if !self.compact {
+            self.indent += 1;
+            writer.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+
+    fn end_container<W: ?Sized + io::Write>(
+        &mut self,
+        writer: &mut W,
+        bracket: &[u8],
+    ) -> io::Result<()> {
+        if !self.compact {
+            self.indent -= 1;
+            if self.has_value {
+                writer.write_all(b"\n")?;
+            }
+            self.write_indent(writer)?;
+        }
+        writer.write_all(bracket)
+    }
+
+    fn begin_container_value<W: ?Sized + io::Write>(
+        &mut self,
+        writer: &mut W,
+        first: bool,
+    ) -> io::Result<()> {
+        if !first {
+            writer.write_all(if self.compact { b"," } else { b",\n" })?;
+        }
+        if !self.compact {
+            self.write_indent(writer)?;
+        }
+        Ok(())
+    }
+
+    fn write_indent<W: ?Sized + io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        for _ in 0..self.indent {
+            writer.write_all(b"    ")?;
+        }
+        Ok(())
+    }
+}
+
+impl Formatter for QtFormatter {
+    fn write_i32<W: ?Sized + io::Write>(&mut self, writer: &mut W, value: i32) -> io::Result<()> {
+        // Qt stores all numbers as doubles, self contained ones included
+        self.write_f64(writer, value as f64)
+    }
+
+    fn write_f64<W: ?Sized + io::Write>(&mut self, writer: &mut W, value: f64) -> io::Result<()> {
+        // Integral values that fit in a qint64 are written as integers, 1000000 and not 1e+06
+        if value.fract() == 0.0 && QINT64_RANGE.contains(&value) {
+            write!(writer, "{}", value as i64)
+        } else {
+            writer.write_all(qt_shortest_double(value).as_bytes())
+        }
+    }
+
+    fn begin_array<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.begin_container(writer, b"[")
+    }
+
+    fn end_array<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.end_container(writer, b"]")
+    }
+
+    fn begin_array_value<W: ?Sized + io::Write>(
+        &mut self,
+        writer: &mut W,
+        first: bool,
+    ) -> io::Result<()> {
+        self.begin_container_value(writer, first)
+    }
+
+    fn end_array_value<W: ?Sized + io::Write>(&mut self, _writer: &mut W) -> io::Result<()> {
+        self.has_value = true;
+        Ok(())
+    }
+
+    fn begin_object<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.begin_container(writer, b"{")
+    }
+
+    fn end_object<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.end_container(writer, b"}")
+    }
+
+    fn begin_object_key<W: ?Sized + io::Write>(
+        &mut self,
+        writer: &mut W,
+        first: bool,
+    ) -> io::Result<()> {
+        self.begin_container_value(writer, first)
+    }
+
+    fn begin_object_value<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(if self.compact { b":" } else { b": " })
+    }
+
+    fn end_object_value<W: ?Sized + io::Write>(&mut self, _writer: &mut W) -> io::Result<()> {
+        self.has_value = true;
+        Ok(())
+    }
+}
+
+impl DecodingPolicy for QtFormatter {
+    fn write_non_finite<W: io::Write>(&mut self, writer: &mut W) -> Result<(), WriteError> {
+        self.write_null(writer).map_err(WriteError::io)
+    }
+
+    fn escape_unpaired_surrogates(&self) -> bool {
+        true
+    }
+}
+
+const QINT64_RANGE: ops::Range<f64> = i64::MIN as f64..-(i64::MIN as f64);
+
+// Same text as QByteArray::number(value, 'g', QLocale::FloatingPointShortest): the shortest digits
+// that round trip, in decimal or exponent form, whichever is the shortest.
+fn qt_shortest_double(value: f64) -> String {
+    // Rust's exponent formatting also produces the shortest round trip digits, e.g. "1.2345e3"
+    let exponent_form = format!("{:e}", value.abs());
+    let (mantissa, exponent) = exponent_form
+        .split_once('e')
+        .expect("exponent formatting always contains an exponent");
+    let digits = mantissa.replace('.', "");
+    let exponent = exponent
+        .parse::<i32>()
+        .expect("exponent formatting always produces an integer exponent");
+    let decimal_point = exponent + 1;
+    let nb_digits = digits.len() as i32;
+
+    // Qt picks the exponent form as soon as it is shorter than the decimal one.
+    // Same computation as QLocaleData::doubleToString, exponents being padded to 2 digits.
+    let cutoff = if decimal_point > 0 {
+        let exponent_digits = if decimal_point > 100 { 2 } else { 1 };
+        let decimal_separator = if nb_digits > decimal_point { 1 } else { 0 };
+        nb_digits + 4 + exponent_digits + decimal_separator
+    } else {
+        6
+    };
+
+    let mut text = String::new();
+    // Qt doesn't write the sign of negative zero
+    if value < 0.0 {
+        text.push('-');
+    }
+
+    if exponent < -4 || exponent >= cutoff {
+        text.push_str(&digits[..1]);
+        if digits.len() > 1 {
+            text.push('.');
+            text.push_str(&digits[1..]);
+        }
+        text.push('e');
+        text.push(if exponent < 0 { '-' } else { '+' });
+        text.push_str(&format!("{:02}", exponent.abs()));
+    } else if decimal_point <= 0 {
+        text.push_str("0.");
+        text.push_str(&"0".repeat((-decimal_point) as usize));
+        text.push_str(&digits);
+    } else if decimal_point < nb_digits {
+        text.push_str(&digits[..decimal_point as usize]);
+        text.push('.');
+        text.push_str(&digits[decimal_point as usize..]);
+    } else {
+        text.push_str(&digits);
+        text.push_str(&"0".repeat((decimal_point - nb_digits) as usize));
+    }
+
+    text
+}
+
+fn needs_escape(c: char) -> bool {
+    char_escape(c).is_some()
+}
+
+// Same escaping rules as serde_json and Qt
+fn char_escape(c: char) -> Option<CharEscape> {
+    match c {
+        '"' => Some(CharEscape::Quote),
+        '\\' => Some(CharEscape::ReverseSolidus),
+        '\x08' => Some(CharEscape::Backspace),
+        '\x0c' => Some(CharEscape::FormFeed),
+        '\n' => Some(CharEscape::LineFeed),
+        '\r' => Some(CharEscape::CarriageReturn),
+        '\t' => Some(CharEscape::Tab),
+        c if (c as u32) < 0x20 => Some(CharEscape::AsciiControl(c as u8)),
+        _ => None,
+    }
+}
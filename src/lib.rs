@@ -1,4 +1,31 @@
 pub mod analysis;
+#[cfg(feature = "compression")]
+pub mod compression;
+#[cfg(feature = "config")]
+pub mod config_source;
+pub mod datastream;
+pub mod diff;
+pub mod document;
+pub mod document_mut;
+pub mod events;
+pub mod explain;
+pub mod extract;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "figment")]
+pub mod figment_provider;
+pub mod json_writer;
+pub mod patch;
+pub mod plugin;
 pub mod qbjs;
+pub mod query;
 pub mod read;
+pub mod scan;
+pub mod settings;
+pub mod slack;
+pub mod spans;
+pub mod stats;
+pub mod text;
 mod type_conversions;
+pub mod visit;
+pub mod write;
@@ -0,0 +1,287 @@
+use serde_json::{Map, Value};
+
+use crate::diff::scalars_equal;
+use crate::qbjs::{self, DeserializeError};
+use crate::spans::{self, parse_index};
+use crate::write;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum PatchError {
+    DeserializeError(DeserializeError),
+    InvalidPatch,            // The JSON Patch isn't an array
+    InvalidOperation(usize), // Index of the operation in the JSON Patch
+    InvalidPointer(String),  // Not a JSON Pointer or an invalid array index
+    PathNotFound(String),
+    TestFailed(String),
+}
+
+impl PatchError {
+    fn read(err: crate::read::ReadError) -> Self {
+        PatchError::DeserializeError(DeserializeError::ReadError(err))
+    }
+}
+
+// Applies the JSON Patch (RFC 6902) operations to the document and encodes the result.
+// Values the operations don't replace keep their storage, new values are stored the same
+// way Qt does. Either every operation applies or an error is returned.
+pub fn patch(qbjs: &[u8], json_patch: &Value) -> Result<Vec<u8>, PatchError> {
+    let operations = json_patch.as_array().ok_or(PatchError::InvalidPatch)?;
+
+    let mut root = storage_tree(qbjs)?;
+    for (index, operation) in operations.iter().enumerate() {
+        apply_operation(&mut root, operation, index)?;
+    }
+
+    encode(&root)
+}
+
+// Applies the JSON Merge Patch (RFC 7396) to the document and encodes the result.
+// Values the merge patch doesn't replace keep their storage.
+pub fn merge_patch(qbjs: &[u8], json_merge_patch: &Value) -> Result<Vec<u8>, PatchError> {
+    let mut root = storage_tree(qbjs)?;
+    merge(&mut root, json_merge_patch);
+
+    encode(&root)
+}
+
+fn storage_tree(qbjs: &[u8]) -> Result<write::Value, PatchError> {
+    // Same as deserialize_to_json, an empty document is an empty object
+    if qbjs.is_empty() {
+        return Ok(write::Value::Object(Vec::new()));
+    }
+
+    let document = qbjs::analyze_root_container(qbjs).map_err(PatchError::DeserializeError)?;
+    write::value_from_analysis(qbjs, &document).map_err(PatchError::read)
+}
+
+fn encode(root: &write::Value) -> Result<Vec<u8>, PatchError> {
+    write::encode_document(root)
+        .map_err(|err| PatchError::DeserializeError(DeserializeError::EncodeError(err)))
+}
+
+fn parse_pointer(pointer: &str) -> Result<Vec<String>, PatchError> {
+    spans::parse_pointer(pointer).ok_or_else(|| PatchError::InvalidPointer(pointer.to_owned()))
+}
+
+// Keys are compared by their code units, which doesn't require them to be valid UTF-16
+fn find_entry(entries: &[write::Entry], key: &str) -> Option<usize> {
+    let key = key.encode_utf16().collect::<Vec<_>>();
+    entries
+        .iter()
+        .position(|entry| entry.key.code_units() == key)
+}
+
+// New keys are inserted where Qt expects them, the entries being sorted by key
+fn insert_entry(entries: &mut Vec<write::Entry>, key: &str, value: write::Value) {
+    let key = write::Key::new(key);
+    let code_units = key.code_units();
+    let index = entries.partition_point(|entry| entry.key.code_units() < code_units);
+    entries.insert(index, write::Entry { key, value });
+}
+
+fn get_mut<'a>(
+    root: &'a mut write::Value,
+    tokens: &[String],
+    pointer: &str,
+) -> Result<&'a mut write::Value, PatchError> {
+    let mut value = root;
+    for token in tokens {
+        value = match value {
+            write::Value::Object(entries) => match find_entry(entries, token) {
+                Some(index) => &mut entries[index].value,
+                None => return Err(PatchError::PathNotFound(pointer.to_owned())),
+            },
+            write::Value::Array(values) => {
+                let index = parse_index(token)
+                    .ok_or_else(|| PatchError::InvalidPointer(pointer.to_owned()))?;
+                values
+                    .get_mut(index)
+                    .ok_or_else(|| PatchError::PathNotFound(pointer.to_owned()))?
+            }
+            _ => return Err(PatchError::PathNotFound(pointer.to_owned())),
+        };
+    }
+    Ok(value)
+}
+
+fn add(root: &mut write::Value, pointer: &str, value: write::Value) -> Result<(), PatchError> {
+    let tokens = parse_pointer(pointer)?;
+    let (last, parent_tokens) = match tokens.split_last() {
+        Some(split) => split,
+        None => {
+            *root = value;
+            return Ok(());
+        }
+    };
+
+    match get_mut(root, parent_tokens, pointer)? {
+        // An existing entry keeps its key and gets the new value
+        write::Value::Object(entries) => match find_entry(entries, last) {
+            Some(index) => entries[index].value = value,
+            None => insert_entry(entries, last, value),
+        },
+        write::Value::Array(values) => {
+            let index = match last.as_str() {
+                "-" => values.len(),
+                token => parse_index(token)
+                    .ok_or_else(|| PatchError::InvalidPointer(pointer.to_owned()))?,
+            };
+            if index > values.len() {
+                return Err(PatchError::PathNotFound(pointer.to_owned()));
+            }
+            values.insert(index, value);
+        }
+        _ => return Err(PatchError::PathNotFound(pointer.to_owned())),
+    }
+    Ok(())
+}
+
+fn remove(root: &mut write::Value, pointer: &str) -> Result<write::Value, PatchError> {
+    let tokens = parse_pointer(pointer)?;
+    let (last, parent_tokens) = match tokens.split_last() {
+        Some(split) => split,
+        None => return Err(PatchError::PathNotFound(pointer.to_owned())),
+    };
+
+    match get_mut(root, parent_tokens, pointer)? {
+        write::Value::Object(entries) => match find_entry(entries, last) {
+            Some(index) => Ok(entries.remove(index).value),
+            None => Err(PatchError::PathNotFound(pointer.to_owned())),
+        },
+        write::Value::Array(values) => {
+            let index =
+                parse_index(last).ok_or_else(|| PatchError::InvalidPointer(pointer.to_owned()))?;
+            if index >= values.len() {
+                return Err(PatchError::PathNotFound(pointer.to_owned()));
+            }
+            Ok(values.remove(index))
+        }
+        _ => Err(PatchError::PathNotFound(pointer.to_owned())),
+    }
+}
+
+// Same as the "test" operation of RFC 6902, numbers are equal when their values are
+fn json_equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| json_equal(a, b))
+        }
+        (Value::Object(a), Value::Object(b)) => {
+            a.len() == b.len()
+                && a.iter()
+                    .all(|(key, a)| b.get(key).map_or(false, |b| json_equal(a, b)))
+        }
+        _ => scalars_equal(a, b),
+    }
+}
+
+fn member<'a>(
+    operation: &'a Map<String, Value>,
+    name: &str,
+    index: usize,
+) -> Result<&'a Value, PatchError> {
+    operation
+        .get(name)
+        .ok_or(PatchError::InvalidOperation(index))
+}
+
+fn pointer_member<'a>(
+    operation: &'a Map<String, Value>,
+    name: &str,
+    index: usize,
+) -> Result<&'a str, PatchError> {
+    member(operation, name, index)?
+        .as_str()
+        .ok_or(PatchError::InvalidOperation(index))
+}
+
+fn apply_operation(
+    root: &mut write::Value,
+    operation: &Value,
+    index: usize,
+) -> Result<(), PatchError> {
+    let operation = operation
+        .as_object()
+        .ok_or(PatchError::InvalidOperation(index))?;
+    let path = pointer_member(operation, "path", index)?;
+
+    match member(operation, "op", index)?.as_str() {
+        Some("add") => {
+            let value = write::Value::from_json(member(operation, "value", index)?);
+            add(root, path, value)
+        }
+        Some("remove") => remove(root, path).map(|_| ()),
+        Some("replace") => {
+            let value = write::Value::from_json(member(operation, "value", index)?);
+            let tokens = parse_pointer(path)?;
+            *get_mut(root, &tokens, path)? = value;
+            Ok(())
+        }
+        // Moved values keep their storage
+        Some("move") => {
+            let from = pointer_member(operation, "from", index)?;
+            if from == path {
+                return Ok(());
+            }
+            // A value can't be moved into one of its children
+            if path.starts_with(from) && path[from.len()..].starts_with('/') {
+                return Err(PatchError::InvalidOperation(index));
+            }
+            let value = remove(root, from)?;
+            add(root, path, value)
+        }
+        Some("copy") => {
+            let from = pointer_member(operation, "from", index)?;
+            let tokens = parse_pointer(from)?;
+            let value = get_mut(root, &tokens, from)?.clone();
+            add(root, path, value)
+        }
+        Some("test") => {
+            let expected = member(operation, "value", index)?;
+            let tokens = parse_pointer(path)?;
+            let value = get_mut(root, &tokens, path)?
+                .to_json()
+                .map_err(PatchError::read)?;
+            if !json_equal(&value, expected) {
+                return Err(PatchError::TestFailed(path.to_owned()));
+            }
+            Ok(())
+        }
+        _ => Err(PatchError::InvalidOperation(index)),
+    }
+}
+
+// Members set to null are removed, objects are merged recursively and other values replace
+// the target
+fn merge(target: &mut write::Value, json_merge_patch: &Value) {
+    let members = match json_merge_patch {
+        Value::Object(members) => members,
+        value => {
+            *target = write::Value::from_json(value);
+            return;
+        }
+    };
+
+    if !matches!(target, write::Value::Object(_)) {
+        *target = write::Value::Object(Vec::new());
+    }
+    let entries = match target {
+        write::Value::Object(entries) => entries,
+        _ => unreachable!("the target was made an object"),
+    };
+
+    for (key, value) in members {
+        match (find_entry(entries, key), value) {
+            (Some(index), Value::Null) => {
+                entries.remove(index);
+            }
+            (None, Value::Null) => {}
+            (Some(index), value) => merge(&mut entries[index].value, value),
+            (None, value) => {
+                let mut new_value = write::Value::Null;
+                merge(&mut new_value, value);
+                insert_entry(entries, key, new_value);
+            }
+        }
+    }
+}
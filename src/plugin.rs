@@ -0,0 +1,202 @@
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::analysis::{header, metadata};
+use crate::qbjs::{self, DeserializeError};
+use crate::type_conversions::as_u32;
+
+// Qt5's moc writes the plugin metadata document right after this marker
+pub const PLUGIN_METADATA_MARKER: &[u8] = b"QTMETADATA  ";
+const PLUGIN_METADATA_SECTION: &[u8] = b".qtmetadata";
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum PluginMetadataError {
+    IoError(io::ErrorKind),
+    InvalidElfFile,
+    MetadataNotFound, // No marker followed by a qbjs header
+    DeserializeError(DeserializeError),
+}
+
+// Reads the Q_PLUGIN_METADATA document of a Qt5 plugin without loading it
+pub fn plugin_metadata_from_elf<P: AsRef<Path>>(path: P) -> Result<Value, PluginMetadataError> {
+    let data = fs::read(path).map_err(|err| PluginMetadataError::IoError(err.kind()))?;
+    plugin_metadata(&data)
+}
+
+// The marker is looked for in the .qtmetadata section, which recent Qt5 versions use,
+// then in the whole file since older versions write the metadata in .rodata
+pub fn plugin_metadata(elf: &[u8]) -> Result<Value, PluginMetadataError> {
+    if elf.get(0..ELF_MAGIC.len()) != Some(ELF_MAGIC) {
+        return Err(PluginMetadataError::InvalidElfFile);
+    }
+
+    // Files whose section headers can't be read, such as truncated files, are searched whole
+    let sections = elf_sections(elf)?.unwrap_or_default();
+
+    let metadata_section = sections
+        .iter()
+        .find(|section| section.name == PLUGIN_METADATA_SECTION);
+    let document_start = metadata_section
+        .and_then(|section| {
+            let start = find_document(elf.get(section.range.clone())?)?;
+            Some(section.range.start + start)
+        })
+        .or_else(|| find_document(elf))
+        .ok_or(PluginMetadataError::MetadataNotFound)?;
+
+    // The root container base holds the size of the document
+    let base_start = document_start + header::HEADER_LENGTH;
+    let size_field = elf
+        .get(base_start..(base_start + metadata::CONTAINER_BASE_LENGTH))
+        .ok_or(PluginMetadataError::DeserializeError(
+            DeserializeError::InsufficientData,
+        ))?;
+    let document = base_start
+        .checked_add(as_u32(size_field) as usize)
+        .and_then(|document_end| elf.get(document_start..document_end))
+        .ok_or(PluginMetadataError::DeserializeError(
+            DeserializeError::InsufficientData,
+        ))?;
+
+    qbjs::deserialize_to_json(document).map_err(PluginMetadataError::DeserializeError)
+}
+
+// Returns the start of the first document following a marker. The marker alone is also
+// found in QtCore, which looks for it in plugins.
+fn find_document(data: &[u8]) -> Option<usize> {
+    data.windows(PLUGIN_METADATA_MARKER.len())
+        .enumerate()
+        .filter(|(_, window)| *window == PLUGIN_METADATA_MARKER)
+        .map(|(position, _)| position + PLUGIN_METADATA_MARKER.len())
+        .find(|start| data.get(*start..).map_or(false, header::has_qbjs_header))
+}
+
+struct Section<'a> {
+    name: &'a [u8],
+    range: Range<usize>,
+}
+
+const ELF_MAGIC: &[u8] = b"\x7fELF";
+const ELF_CLASS_32: u8 = 1;
+const ELF_CLASS_64: u8 = 2;
+const ELF_DATA_LITTLE_ENDIAN: u8 = 1;
+const ELF_DATA_BIG_ENDIAN: u8 = 2;
+const SECTION_TYPE_NOBITS: u64 = 8; // Sections without data in the file, such as .bss
+
+// Reads unsigned fields of the ELF header and section headers
+struct ElfReader<'a> {
+    data: &'a [u8],
+    is_64_bits: bool,
+    is_little_endian: bool,
+}
+
+impl ElfReader<'_> {
+    fn read(&self, position: usize, length: usize) -> Option<u64> {
+        let bytes = self.data.get(position..position.checked_add(length)?)?;
+        let fold = |value: u64, byte: &u8| (value << 8) | u64::from(*byte);
+        Some(if self.is_little_endian {
+            bytes.iter().rev().fold(0, fold)
+        } else {
+            bytes.iter().fold(0, fold)
+        })
+    }
+
+    // Fields whose size depends on the class: addresses, offsets and sizes
+    fn read_word(&self, position: usize) -> Option<usize> {
+        self.read_usize(position, if self.is_64_bits { 8 } else { 4 })
+    }
+
+    fn read_usize(&self, position: usize, length: usize) -> Option<usize> {
+        usize::try_from(self.read(position, length)?).ok()
+    }
+}
+
+// Returns None when the section headers can't be read, and an error when the ELF header
+// describes a section header table that can't exist
+fn elf_sections(elf: &[u8]) -> Result<Option<Vec<Section<'_>>>, PluginMetadataError> {
+    let is_64_bits = match elf.get(4) {
+        Some(&ELF_CLASS_32) => false,
+        Some(&ELF_CLASS_64) => true,
+        _ => return Ok(None),
+    };
+    let is_little_endian = match elf.get(5) {
+        Some(&ELF_DATA_LITTLE_ENDIAN) => true,
+        Some(&ELF_DATA_BIG_ENDIAN) => false,
+        _ => return Ok(None),
+    };
+    let reader = ElfReader {
+        data: elf,
+        is_64_bits,
+        is_little_endian,
+    };
+
+    // Positions of e_shoff, e_shentsize, e_shnum and e_shstrndx in the ELF header
+    let (table_offset, entry_size, entry_count, names_index) = if is_64_bits {
+        (0x28, 0x3a, 0x3c, 0x3e)
+    } else {
+        (0x20, 0x2e, 0x30, 0x32)
+    };
+    let header_fields = || -> Option<(usize, usize, usize, usize)> {
+        Some((
+            reader.read_word(table_offset)?,
+            reader.read_usize(entry_size, 2)?,
+            reader.read_usize(entry_count, 2)?,
+            reader.read_usize(names_index, 2)?,
+        ))
+    };
+    let (table_start, entry_size, entry_count, names_index) = match header_fields() {
+        Some(fields) => fields,
+        None => return Ok(None),
+    };
+
+    // Files without section headers are only searched for the marker
+    if entry_count == 0 {
+        return Ok(Some(Vec::new()));
+    }
+
+    // Section headers are then read without overflowing
+    entry_count
+        .checked_mul(entry_size)
+        .and_then(|table_size| table_start.checked_add(table_size))
+        .filter(|_| names_index < entry_count)
+        .ok_or(PluginMetadataError::InvalidElfFile)?;
+
+    // Positions of sh_type, sh_offset and sh_size in a section header
+    let (type_field, offset_field, size_field) = if is_64_bits {
+        (0x04, 0x18, 0x20)
+    } else {
+        (0x04, 0x10, 0x14)
+    };
+    let section_header = |index: usize| -> Option<(usize, u64, Range<usize>)> {
+        let start = table_start + index * entry_size;
+        let name = reader.read_usize(start, 4)?;
+        let section_type = reader.read(start.checked_add(type_field)?, 4)?;
+        let offset = reader.read_word(start.checked_add(offset_field)?)?;
+        let size = reader.read_word(start.checked_add(size_field)?)?;
+        Some((name, section_type, offset..offset.saturating_add(size)))
+    };
+
+    let sections = || -> Option<Vec<Section<'_>>> {
+        let (_, _, names_range) = section_header(names_index)?;
+        let names = elf.get(names_range)?;
+
+        let mut sections = Vec::with_capacity(entry_count);
+        for index in 0..entry_count {
+            let (name_start, section_type, range) = section_header(index)?;
+            if section_type == SECTION_TYPE_NOBITS {
+                continue;
+            }
+            let name = names
+                .get(name_start..)
+                .and_then(|name| name.split(|byte| *byte == 0).next())?;
+            sections.push(Section { name, range });
+        }
+        Some(sections)
+    };
+
+    Ok(sections())
+}
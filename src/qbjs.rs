@@ -1,21 +1,96 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::io;
+
 use serde_json::Value;
 
 pub use crate::analysis::{self, analyze_document, data, header};
+#[cfg(feature = "compression")]
+pub use crate::compression::{
+    self, analyze_compressed_document, decompress, deserialize_compressed_to_json, Compression,
+    CompressionError,
+};
+#[cfg(feature = "config")]
+pub use crate::config_source::{self, QbjsFile, QbjsFormat};
+pub use crate::datastream::{self, read_byte_arrays, ByteArray, ByteArrayReader, DataStreamError};
+pub use crate::diff::{self, diff, DiffOptions};
+pub use crate::document::{QbjsDocument, ValueRef};
+pub use crate::document_mut::{EditError, QbjsDocumentMut};
+pub use crate::events::{Event, QbjsEvents};
+pub use crate::explain::{self, explain, Explanation, Region};
+pub use crate::extract::{self, extract};
+#[cfg(feature = "figment")]
+pub use crate::figment_provider::{self, Qbjs};
+pub use crate::json_writer::{self, WriteOptions};
+pub use crate::patch::{self, merge_patch, patch, PatchError};
+pub use crate::plugin::{self, plugin_metadata, plugin_metadata_from_elf, PluginMetadataError};
+pub use crate::query::{self, Query};
 pub use crate::read;
+pub use crate::scan::{self, scan, Scan, ScannedDocument};
+pub use crate::settings::{self, settings_file_to_json, settings_to_json, SettingsError};
+pub use crate::slack::{self, analyze_slack, Recovered, RecoveredValue, SlackReport};
+pub use crate::spans::{self, Span};
+pub use crate::stats::{self, stats, Stats};
+pub use crate::text::{self, deserialize_base64, deserialize_hex, TextDecodeError};
+pub use crate::visit::{self, Visitor};
+pub use crate::write;
 
+// New ways to fail come with new features, matches need a wildcard arm
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[non_exhaustive]
 pub enum DeserializeError {
     AnalysisError(analysis::AnalysisError),
     InsufficientData,
     InvalidRootContainer,
+    InvalidJsonPointer(String),
     ReadError(read::ReadError),
+    IoError(io::ErrorKind),
+    EncodeError(write::EncodeError),
 }
 
-pub fn deserialize_to_json(qbjs: &[u8]) -> Result<Value, DeserializeError> {
-    if qbjs.is_empty() {
-        return Ok(serde_json::json!({}));
+// Lets the error be boxed by frameworks expecting std errors, such as config or figment
+impl fmt::Display for DeserializeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeserializeError::AnalysisError(err) => write!(f, "invalid qbjs document: {}", err),
+            DeserializeError::InsufficientData => {
+                write!(
+                    f,
+                    "invalid qbjs document: the data ends before the document"
+                )
+            }
+            DeserializeError::InvalidRootContainer => {
+                write!(
+                    f,
+                    "invalid qbjs document: the root isn't an array or an object"
+                )
+            }
+            DeserializeError::InvalidJsonPointer(pointer) => {
+                write!(f, "invalid JSON pointer: {:?}", pointer)
+            }
+            DeserializeError::ReadError(err) => write!(f, "invalid qbjs document: {}", err),
+            DeserializeError::IoError(kind) => write!(f, "couldn't write the JSON text: {}", kind),
+            DeserializeError::EncodeError(err) => {
+                write!(f, "couldn't encode the document: {}", err)
+            }
+        }
+    }
+}
+
+impl Error for DeserializeError {}
+
+impl DeserializeError {
+    fn from_write_error(err: json_writer::WriteError) -> Self {
+        match err {
+            json_writer::WriteError::ReadError(e) => DeserializeError::ReadError(e),
+            json_writer::WriteError::IoError(kind) => DeserializeError::IoError(kind),
+        }
     }
+}
 
+// Analyzes a non empty document and checks its root is a container
+pub(crate) fn analyze_root_container(qbjs: &[u8]) -> Result<data::Value, DeserializeError> {
     if qbjs.len() < header::HEADER_LENGTH {
         return Err(DeserializeError::InsufficientData);
     }
@@ -23,9 +98,65 @@ pub fn deserialize_to_json(qbjs: &[u8]) -> Result<Value, DeserializeError> {
     let document = analyze_document(qbjs).map_err(DeserializeError::AnalysisError)?;
 
     match document {
-        data::Value::Array(_) | data::Value::Object(_) => {
-            read::read_value(qbjs, &document).map_err(DeserializeError::ReadError)
-        }
+        data::Value::Array(_) | data::Value::Object(_) => Ok(document),
         _ => Err(DeserializeError::InvalidRootContainer),
     }
 }
+
+pub fn deserialize_to_json(qbjs: &[u8]) -> Result<Value, DeserializeError> {
+    if qbjs.is_empty() {
+        return Ok(serde_json::json!({}));
+    }
+
+    let document = analyze_root_container(qbjs)?;
+
+    read::read_value(qbjs, &document).map_err(DeserializeError::ReadError)
+}
+
+// Writes the document as JSON text without building an intermediate serde_json::Value.
+// Object keys are written in the order they are stored in the document.
+pub fn write_json<W: io::Write>(
+    qbjs: &[u8],
+    writer: W,
+    options: WriteOptions,
+) -> Result<(), DeserializeError> {
+    if qbjs.is_empty() {
+        return json_writer::write_empty_document(writer, options)
+            .map_err(DeserializeError::from_write_error);
+    }
+
+    let document = analyze_root_container(qbjs)?;
+
+    json_writer::write_value(qbjs, &document, writer, options)
+        .map_err(DeserializeError::from_write_error)
+}
+
+// Deserializes the document along with the span of every value, keyed by JSON Pointer.
+// An empty document has no span.
+pub fn deserialize_with_spans(
+    qbjs: &[u8],
+) -> Result<(Value, BTreeMap<String, Span>), DeserializeError> {
+    if qbjs.is_empty() {
+        return Ok((serde_json::json!({}), BTreeMap::new()));
+    }
+
+    let document = analyze_root_container(qbjs)?;
+
+    let value = read::read_value(qbjs, &document).map_err(DeserializeError::ReadError)?;
+    let spans = spans::analyze_spans(qbjs, &document).map_err(DeserializeError::ReadError)?;
+
+    Ok((value, spans))
+}
+
+// Rewrites the document with every container tightly packed, which drops the bytes left
+// by in place edits. Keys, strings and numbers keep their storage.
+pub fn compact(qbjs: &[u8]) -> Result<Vec<u8>, DeserializeError> {
+    if qbjs.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let document = analyze_root_container(qbjs)?;
+
+    let value = write::value_from_analysis(qbjs, &document).map_err(DeserializeError::ReadError)?;
+    write::encode_document(&value).map_err(DeserializeError::EncodeError)
+}
@@ -0,0 +1,637 @@
+use std::borrow::Cow;
+use std::error::Error;
+use std::fmt;
+
+use serde_json::Value;
+
+use crate::analysis::data;
+use crate::qbjs::{self, DeserializeError};
+use crate::read;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum QueryError {
+    InvalidExpression(usize), // Byte position in the expression
+    DeserializeError(DeserializeError),
+    CannotIndex(&'static str), // Type of the indexed value
+    CannotIterate(&'static str),
+    CannotSlice(&'static str),
+    NoKeys(&'static str),
+    NoLength(&'static str),
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QueryError::InvalidExpression(position) => {
+                write!(f, "invalid expression at byte {}", position)
+            }
+            QueryError::DeserializeError(err) => err.fmt(f),
+            QueryError::CannotIndex(value_type) => write!(f, "cannot index {}", value_type),
+            QueryError::CannotIterate(value_type) => {
+                write!(f, "cannot iterate over {}", value_type)
+            }
+            QueryError::CannotSlice(value_type) => write!(f, "cannot slice {}", value_type),
+            QueryError::NoKeys(value_type) => write!(f, "{} has no keys", value_type),
+            QueryError::NoLength(value_type) => write!(f, "{} has no length", value_type),
+        }
+    }
+}
+
+impl Error for QueryError {}
+
+impl QueryError {
+    fn read(err: read::ReadError) -> Self {
+        QueryError::DeserializeError(DeserializeError::ReadError(err))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Step {
+    Key(String),
+    Index(i64),
+    Iterate,
+    Slice(Option<i64>, Option<i64>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Filter {
+    Path(Vec<Step>), // No step is the identity
+    Keys,
+    Length,
+    Select(Vec<Filter>, bool, Value), // Compared pipeline, equality or inequality, literal
+}
+
+// Subset of the jq language:
+// - paths: `.`, `.key`, `."key"`, `.["key"]`, `.[0]`, `.[-1]`,
+// - wildcards: `.[]`,
+// - array and string slices: `.[1:3]`, `.[:2]`, `.[-2:]`,
+// - `select(<pipeline> == <literal>)` and `select(<pipeline> != <literal>)`,
+// - `keys` and `length`,
+// - pipes: `<filter> | <filter>`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    pipeline: Vec<Filter>,
+}
+
+impl Query {
+    pub fn parse(expression: &str) -> Result<Query, QueryError> {
+        let mut parser = Parser {
+            expression,
+            position: 0,
+        };
+        let pipeline = parser.parse_pipeline()?;
+        parser.skip_whitespaces();
+        if parser.position != expression.len() {
+            return Err(QueryError::InvalidExpression(parser.position));
+        }
+
+        Ok(Query { pipeline })
+    }
+
+    // Evaluates the query over the analyzed document: only the values in the results,
+    // the compared values and the traversed keys are decoded.
+    pub fn evaluate(&self, qbjs: &[u8]) -> Result<Vec<Value>, QueryError> {
+        // Same as deserialize_to_json, an empty document is an empty object
+        if qbjs.is_empty() {
+            return self.evaluate_value(&serde_json::json!({}));
+        }
+
+        let document = qbjs::analyze_root_container(qbjs).map_err(QueryError::DeserializeError)?;
+        let evaluator = Evaluator { data: qbjs };
+
+        evaluator
+            .evaluate_pipeline(&self.pipeline, Item::Node(&document))?
+            .into_iter()
+            .map(|item| evaluator.decode(item))
+            .collect()
+    }
+
+    pub fn evaluate_value(&self, value: &Value) -> Result<Vec<Value>, QueryError> {
+        let evaluator = Evaluator { data: &[] };
+
+        evaluator
+            .evaluate_pipeline(&self.pipeline, Item::Decoded(Cow::Borrowed(value)))?
+            .into_iter()
+            .map(|item| evaluator.decode(item))
+            .collect()
+    }
+}
+
+struct Parser<'e> {
+    expression: &'e str,
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn rest(&self) -> &str {
+        &self.expression[self.position..]
+    }
+
+    fn error(&self) -> QueryError {
+        QueryError::InvalidExpression(self.position)
+    }
+
+    fn skip_whitespaces(&mut self) {
+        let rest = self.rest();
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    fn consume(&mut self, token: &str) -> bool {
+        if self.rest().starts_with(token) {
+            self.position += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), QueryError> {
+        if self.consume(token) {
+            Ok(())
+        } else {
+            Err(self.error())
+        }
+    }
+
+    fn parse_pipeline(&mut self) -> Result<Vec<Filter>, QueryError> {
+        let mut pipeline = vec![self.parse_filter()?];
+        loop {
+            self.skip_whitespaces();
+            if !self.consume("|") {
+                return Ok(pipeline);
+            }
+            pipeline.push(self.parse_filter()?);
+        }
+    }
+
+    fn parse_filter(&mut self) -> Result<Filter, QueryError> {
+        self.skip_whitespaces();
+
+        if self.rest().starts_with('.') {
+            return self.parse_path().map(Filter::Path);
+        }
+
+        match self.parse_identifier().as_deref() {
+            Some("keys") => Ok(Filter::Keys),
+            Some("length") => Ok(Filter::Length),
+            Some("select") => {
+                self.skip_whitespaces();
+                self.expect("(")?;
+                let pipeline = self.parse_pipeline()?;
+                self.skip_whitespaces();
+                let equality = if self.consume("==") {
+                    true
+                } else if self.consume("!=") {
+                    false
+                } else {
+                    return Err(self.error());
+                };
+                self.skip_whitespaces();
+                let literal = self.parse_literal()?;
+                self.skip_whitespaces();
+                self.expect(")")?;
+                Ok(Filter::Select(pipeline, equality, literal))
+            }
+            _ => Err(self.error()),
+        }
+    }
+
+    fn parse_path(&mut self) -> Result<Vec<Step>, QueryError> {
+        let mut steps = Vec::new();
+
+        self.expect(".")?;
+        if let Some(key) = self.parse_key()? {
+            steps.push(Step::Key(key));
+        }
+
+        loop {
+            if self.consume("[") {
+                steps.push(self.parse_bracket()?);
+            } else if self.consume(".") {
+                match self.parse_key()? {
+                    Some(key) => steps.push(Step::Key(key)),
+                    None if self.rest().starts_with('[') => {}
+                    None => return Err(self.error()),
+                }
+            } else {
+                return Ok(steps);
+            }
+        }
+    }
+
+    // Key following a dot, either an identifier or a string literal
+    fn parse_key(&mut self) -> Result<Option<String>, QueryError> {
+        if self.rest().starts_with('"') {
+            return self.parse_string().map(Some);
+        }
+        Ok(self.parse_identifier())
+    }
+
+    fn parse_identifier(&mut self) -> Option<String> {
+        let rest = self.rest();
+        let starts_identifier = rest
+            .chars()
+            .next()
+            .map_or(false, |c| c.is_ascii_alphabetic() || c == '_');
+        if !starts_identifier {
+            return None;
+        }
+
+        let length = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        let identifier = rest[..length].to_owned();
+        self.position += length;
+        Some(identifier)
+    }
+
+    // Step between brackets, the opening bracket is already consumed
+    fn parse_bracket(&mut self) -> Result<Step, QueryError> {
+        self.skip_whitespaces();
+        if self.consume("]") {
+            return Ok(Step::Iterate);
+        }
+
+        if self.rest().starts_with('"') {
+            let key = self.parse_string()?;
+            self.skip_whitespaces();
+            self.expect("]")?;
+            return Ok(Step::Key(key));
+        }
+
+        let start = self.parse_integer()?;
+        self.skip_whitespaces();
+        if self.consume("]") {
+            return start.map(Step::Index).ok_or_else(|| self.error());
+        }
+
+        self.expect(":")?;
+        let end = self.parse_integer()?;
+        self.skip_whitespaces();
+        self.expect("]")?;
+        Ok(Step::Slice(start, end))
+    }
+
+    fn parse_integer(&mut self) -> Result<Option<i64>, QueryError> {
+        self.skip_whitespaces();
+        let rest = self.rest();
+        let sign_length = usize::from(rest.starts_with('-'));
+        let length = rest[sign_length..]
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len() - sign_length)
+            + sign_length;
+        if length == 0 {
+            return Ok(None);
+        }
+
+        let integer = rest[..length].parse().map_err(|_| self.error())?;
+        self.position += length;
+        Ok(Some(integer))
+    }
+
+    fn parse_string(&mut self) -> Result<String, QueryError> {
+        match self.parse_literal()? {
+            Value::String(string) => Ok(string),
+            _ => Err(self.error()),
+        }
+    }
+
+    // JSON literal: serde_json tells where a string ends, other literals end before a delimiter
+    fn parse_literal(&mut self) -> Result<Value, QueryError> {
+        let rest = self.rest();
+        let (value, length) = if rest.starts_with('"') {
+            let mut stream = serde_json::Deserializer::from_str(rest).into_iter::<Value>();
+            match stream.next() {
+                Some(Ok(value)) => (value, stream.byte_offset()),
+                _ => return Err(self.error()),
+            }
+        } else {
+            let length = rest
+                .find(|c: char| c.is_whitespace() || c == ')' || c == '|' || c == ']')
+                .unwrap_or(rest.len());
+            match serde_json::from_str::<Value>(&rest[..length]) {
+                Ok(value) if !value.is_array() && !value.is_object() => (value, length),
+                _ => return Err(self.error()),
+            }
+        };
+
+        self.position += length;
+        Ok(value)
+    }
+}
+
+enum Item<'t> {
+    Node(&'t data::Value), // Not decoded yet
+    Decoded(Cow<'t, Value>),
+}
+
+struct Evaluator<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Evaluator<'a> {
+    fn decode(&self, item: Item) -> Result<Value, QueryError> {
+        match item {
+            Item::Node(value) => read::read_value(self.data, value).map_err(QueryError::read),
+            Item::Decoded(value) => Ok(value.into_owned()),
+        }
+    }
+
+    fn key(&self, key: &data::Key) -> Result<Cow<'a, str>, QueryError> {
+        match key {
+            data::Key::Latin1String(bytefield) => read::latin1_str(self.data, bytefield),
+            data::Key::Utf16String(bytefield) => read::utf16_str(self.data, bytefield),
+        }
+        .map_err(QueryError::read)
+    }
+
+    fn evaluate_pipeline<'t>(
+        &self,
+        pipeline: &[Filter],
+        input: Item<'t>,
+    ) -> Result<Vec<Item<'t>>, QueryError> {
+        let mut items = vec![input];
+        for filter in pipeline {
+            let mut outputs = Vec::new();
+            for item in items {
+                self.evaluate_filter(filter, item, &mut outputs)?;
+            }
+            items = outputs;
+        }
+        Ok(items)
+    }
+
+    fn evaluate_filter<'t>(
+        &self,
+        filter: &Filter,
+        input: Item<'t>,
+        outputs: &mut Vec<Item<'t>>,
+    ) -> Result<(), QueryError> {
+        match filter {
+            Filter::Path(steps) => {
+                let mut items = vec![input];
+                for step in steps {
+                    let mut step_outputs = Vec::new();
+                    for item in items {
+                        self.evaluate_step(step, item, &mut step_outputs)?;
+                    }
+                    items = step_outputs;
+                }
+                outputs.extend(items);
+            }
+            Filter::Keys => outputs.push(Item::Decoded(Cow::Owned(self.keys(input)?))),
+            Filter::Length => outputs.push(Item::Decoded(Cow::Owned(self.length(input)?))),
+            Filter::Select(pipeline, equality, literal) => {
+                let compared_items = match &input {
+                    Item::Node(value) => self.evaluate_pipeline(pipeline, Item::Node(value))?,
+                    Item::Decoded(value) => {
+                        self.evaluate_pipeline(pipeline, Item::Decoded(Cow::Borrowed(value)))?
+                    }
+                };
+                let mut selections = 0;
+                for compared_item in compared_items {
+                    let compared_value = self.decode(compared_item)?;
+                    if json_equal(&compared_value, literal) == *equality {
+                        selections += 1;
+                    }
+                }
+                // Same as jq, the input is output once for each true comparison
+                match input {
+                    Item::Node(value) => {
+                        outputs.extend((0..selections).map(|_| Item::Node(value)));
+                    }
+                    Item::Decoded(value) => {
+                        for _ in 1..selections {
+                            outputs.push(Item::Decoded(value.clone()));
+                        }
+                        if selections > 0 {
+                            outputs.push(Item::Decoded(value));
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn evaluate_step<'t>(
+        &self,
+        step: &Step,
+        input: Item<'t>,
+        outputs: &mut Vec<Item<'t>>,
+    ) -> Result<(), QueryError> {
+        let node = match input {
+            Item::Node(node @ (data::Value::Array(_) | data::Value::Object(_))) => node,
+            // Leaves are decoded, they can't be indexed anyway
+            Item::Node(leaf) => {
+                let value = self.decode(Item::Node(leaf))?;
+                return self.evaluate_decoded_step(step, Cow::Owned(value), outputs);
+            }
+            Item::Decoded(value) => return self.evaluate_decoded_step(step, value, outputs),
+        };
+
+        match (step, node) {
+            (Step::Key(key), data::Value::Object(object)) => {
+                let mut found = None;
+                for entry in &object.entries {
+                    if self.key(&entry.key)? == key.as_str() {
+                        found = Some(&entry.value);
+                    }
+                }
+                outputs.push(match found {
+                    Some(value) => Item::Node(value),
+                    None => Item::Decoded(Cow::Owned(Value::Null)),
+                });
+            }
+            (Step::Index(index), data::Value::Array(array)) => {
+                outputs.push(match resolve_index(*index, array.values.len()) {
+                    Some(index) => Item::Node(&array.values[index]),
+                    None => Item::Decoded(Cow::Owned(Value::Null)),
+                });
+            }
+            (Step::Iterate, data::Value::Array(array)) => {
+                outputs.extend(array.values.iter().map(Item::Node));
+            }
+            (Step::Iterate, data::Value::Object(object)) => {
+                outputs.extend(object.entries.iter().map(|entry| Item::Node(&entry.value)));
+            }
+            (Step::Slice(start, end), data::Value::Array(array)) => {
+                let range = resolve_slice(*start, *end, array.values.len());
+                let values = array.values[range]
+                    .iter()
+                    .map(|value| read::read_value(self.data, value))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(QueryError::read)?;
+                outputs.push(Item::Decoded(Cow::Owned(Value::Array(values))));
+            }
+            (Step::Key(_), _) | (Step::Index(_), _) => {
+                return Err(QueryError::CannotIndex(node_type(node)))
+            }
+            (Step::Slice(_, _), _) => return Err(QueryError::CannotSlice(node_type(node))),
+            (Step::Iterate, _) => unreachable!("containers can be iterated"),
+        }
+        Ok(())
+    }
+
+    fn evaluate_decoded_step<'t>(
+        &self,
+        step: &Step,
+        input: Cow<'t, Value>,
+        outputs: &mut Vec<Item<'t>>,
+    ) -> Result<(), QueryError> {
+        // Borrows from the input when it is borrowed, otherwise clones the outputs
+        fn output<'t>(
+            input: &Cow<'t, Value>,
+            select: impl for<'v> Fn(&'v Value) -> Option<&'v Value>,
+        ) -> Item<'t> {
+            let selected = match input {
+                Cow::Borrowed(value) => select(value).map(Cow::Borrowed),
+                Cow::Owned(value) => select(value).map(|value| Cow::Owned(value.clone())),
+            };
+            Item::Decoded(selected.unwrap_or(Cow::Owned(Value::Null)))
+        }
+
+        match (step, input.as_ref()) {
+            (Step::Key(_), Value::Null)
+            | (Step::Index(_), Value::Null)
+            | (Step::Slice(_, _), Value::Null) => {
+                outputs.push(Item::Decoded(Cow::Owned(Value::Null)))
+            }
+            (Step::Key(key), Value::Object(_)) => {
+                outputs.push(output(&input, |value| value.get(key)));
+            }
+            (Step::Index(index), Value::Array(array)) => {
+                let index = resolve_index(*index, array.len());
+                outputs.push(output(&input, |value| {
+                    index.and_then(|index| value.get(index))
+                }));
+            }
+            (Step::Iterate, Value::Array(array)) => {
+                for index in 0..array.len() {
+                    outputs.push(output(&input, |value| value.get(index)));
+                }
+            }
+            (Step::Iterate, Value::Object(object)) => {
+                for key in object.keys() {
+                    outputs.push(output(&input, |value| value.get(key)));
+                }
+            }
+            (Step::Slice(start, end), Value::Array(array)) => {
+                let range = resolve_slice(*start, *end, array.len());
+                let slice = Value::Array(array[range].to_vec());
+                outputs.push(Item::Decoded(Cow::Owned(slice)));
+            }
+            (Step::Slice(start, end), Value::String(string)) => {
+                let chars = string.chars().collect::<Vec<_>>();
+                let range = resolve_slice(*start, *end, chars.len());
+                let slice = Value::String(chars[range].iter().collect());
+                outputs.push(Item::Decoded(Cow::Owned(slice)));
+            }
+            (Step::Key(_), value) | (Step::Index(_), value) => {
+                return Err(QueryError::CannotIndex(value_type(value)))
+            }
+            (Step::Iterate, value) => return Err(QueryError::CannotIterate(value_type(value))),
+            (Step::Slice(_, _), value) => return Err(QueryError::CannotSlice(value_type(value))),
+        }
+        Ok(())
+    }
+
+    fn keys(&self, input: Item) -> Result<Value, QueryError> {
+        let mut keys = match input {
+            Item::Node(data::Value::Object(object)) => object
+                .entries
+                .iter()
+                .map(|entry| self.key(&entry.key).map(Cow::into_owned))
+                .collect::<Result<Vec<_>, _>>()?,
+            Item::Node(data::Value::Array(array)) => {
+                return Ok((0..array.values.len()).collect());
+            }
+            Item::Decoded(value) => match value.as_ref() {
+                Value::Object(object) => object.keys().cloned().collect(),
+                Value::Array(array) => return Ok((0..array.len()).collect()),
+                value => return Err(QueryError::NoKeys(value_type(value))),
+            },
+            Item::Node(node) => return Err(QueryError::NoKeys(node_type(node))),
+        };
+        // Same as jq, keys are sorted by code point
+        keys.sort();
+        Ok(keys.into_iter().collect())
+    }
+
+    fn length(&self, input: Item) -> Result<Value, QueryError> {
+        match input {
+            Item::Node(data::Value::Object(object)) => Ok(object.entries.len().into()),
+            Item::Node(data::Value::Array(array)) => Ok(array.values.len().into()),
+            Item::Node(leaf) => {
+                self.length(Item::Decoded(Cow::Owned(self.decode(Item::Node(leaf))?)))
+            }
+            Item::Decoded(value) => match value.as_ref() {
+                Value::Null => Ok(0.into()),
+                Value::Number(number) => match number.as_i64() {
+                    Some(integer) => Ok(integer.unsigned_abs().into()),
+                    None => Ok(number.as_f64().map(f64::abs).into()),
+                },
+                Value::String(string) => Ok(string.chars().count().into()),
+                Value::Array(array) => Ok(array.len().into()),
+                Value::Object(object) => Ok(object.len().into()),
+                Value::Bool(_) => Err(QueryError::NoLength("boolean")),
+            },
+        }
+    }
+}
+
+// Negative indexes count from the end
+fn resolve_index(index: i64, length: usize) -> Option<usize> {
+    let index = if index < 0 {
+        length as i64 + index
+    } else {
+        index
+    };
+    (0..length as i64)
+        .contains(&index)
+        .then_some(index as usize)
+}
+
+fn resolve_slice(start: Option<i64>, end: Option<i64>, length: usize) -> std::ops::Range<usize> {
+    let clamp = |bound: i64| {
+        let bound = if bound < 0 {
+            length as i64 + bound
+        } else {
+            bound
+        };
+        bound.clamp(0, length as i64) as usize
+    };
+    let start = start.map_or(0, clamp);
+    let end = end.map_or(length, clamp);
+    start..end.max(start)
+}
+
+// Same as jq, numbers are equal when their values are, whatever their representation
+fn json_equal(left: &Value, right: &Value) -> bool {
+    match (left, right) {
+        (Value::Number(left), Value::Number(right)) => left.as_f64() == right.as_f64(),
+        _ => left == right,
+    }
+}
+
+fn node_type(node: &data::Value) -> &'static str {
+    match node {
+        data::Value::Null(_) => "null",
+        data::Value::Bool(_) => "boolean",
+        data::Value::SelfContainedNumber(_) | data::Value::Number(_) => "number",
+        data::Value::Latin1String(_) | data::Value::Utf16String(_) => "string",
+        data::Value::Array(_) => "array",
+        data::Value::Object(_) => "object",
+    }
+}
+
+fn value_type(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
@@ -1,10 +1,15 @@
+use std::borrow::Cow;
+use std::error::Error;
+use std::fmt;
+
 use encoding::all::{ISO_8859_1, UTF_16LE};
 use encoding::{DecoderTrap, Encoding};
 
-use serde_json::Value;
+use serde_json::{Map, Value};
 
 use crate::analysis::{data, metadata};
 use crate::type_conversions::{as_i27, as_u32, as_u64};
+use crate::visit;
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum ReadError {
@@ -18,110 +23,280 @@ pub enum ReadError {
     FailedToDecodeNumber,
 }
 
-fn read_latin1_string(data: &[u8], bytefield: &data::ByteField) -> Result<String, ReadError> {
-    let string_data = data
-        .get(bytefield.range.start..bytefield.range.end)
-        .ok_or(ReadError::InvalidLatin1StringDataRange)?;
-
-    ISO_8859_1
-        .decode(string_data, DecoderTrap::Strict)
-        .map_err(|_| ReadError::FailedToDecodeLatin1String)
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ReadError::InvalidBoolDataPosition => "a bool is past the end of the data",
+            ReadError::InvalidSelfContainedNumberDataPosition => {
+                "a self contained number is past the end of the data"
+            }
+            ReadError::InvalidNumberDataRange => "a double is past the end of the data",
+            ReadError::InvalidLatin1StringDataRange => {
+                "a latin1 string is past the end of the data"
+            }
+            ReadError::InvalidUtf16StringDataRange => "a UTF-16 string is past the end of the data",
+            ReadError::FailedToDecodeLatin1String => "a latin1 string can't be decoded",
+            ReadError::FailedToDecodeUtf16String => "a UTF-16 string has unpaired surrogates",
+            ReadError::FailedToDecodeNumber => "a double is infinite or NaN, which JSON can't hold",
+        })
+    }
 }
 
-fn read_utf16_string(data: &[u8], bytefield: &data::ByteField) -> Result<String, ReadError> {
-    let string_data = data
-        .get(bytefield.range.start..bytefield.range.end)
-        .ok_or(ReadError::InvalidUtf16StringDataRange)?;
+impl Error for ReadError {}
 
-    UTF_16LE
-        .decode(string_data, DecoderTrap::Strict)
-        .map_err(|_| ReadError::FailedToDecodeUtf16String)
+pub(crate) fn latin1_string_data<'a>(
+    data: &'a [u8],
+    bytefield: &data::ByteField,
+) -> Result<&'a [u8], ReadError> {
+    data.get(bytefield.range.start..bytefield.range.end)
+        .ok_or(ReadError::InvalidLatin1StringDataRange)
 }
 
-fn read_key(data: &[u8], key: &data::Key) -> Result<String, ReadError> {
-    match key {
-        data::Key::Latin1String(bytefield) => read_latin1_string(data, bytefield),
-        data::Key::Utf16String(bytefield) => read_utf16_string(data, bytefield),
-    }
+pub(crate) fn utf16_string_data<'a>(
+    data: &'a [u8],
+    bytefield: &data::ByteField,
+) -> Result<&'a [u8], ReadError> {
+    data.get(bytefield.range.start..bytefield.range.end)
+        .ok_or(ReadError::InvalidUtf16StringDataRange)
 }
 
-pub fn read_value(data: &[u8], value: &data::Value) -> Result<Value, ReadError> {
-    match value {
-        data::Value::Null(_) => Ok(Value::Null),
-        data::Value::Bool(position) => read_bool(data, *position),
-        data::Value::SelfContainedNumber(position) => read_self_contained_number(data, *position),
-        data::Value::Number(bytefield) => read_number(data, bytefield),
-        data::Value::Latin1String(bytefield) => read_latin1_string_value(data, bytefield),
-        data::Value::Utf16String(bytefield) => read_utf16_string_value(data, bytefield),
-        data::Value::Array(array) => read_array(data, array),
-        data::Value::Object(object) => read_object(data, object),
+// ASCII strings, the most common ones, are borrowed from the input bytes
+pub(crate) fn latin1_str<'a>(
+    data: &'a [u8],
+    bytefield: &data::ByteField,
+) -> Result<Cow<'a, str>, ReadError> {
+    let string_data = latin1_string_data(data, bytefield)?;
+
+    if string_data.is_ascii() {
+        let string = std::str::from_utf8(string_data).expect("ASCII must be valid UTF-8");
+        Ok(Cow::Borrowed(string))
+    } else {
+        ISO_8859_1
+            .decode(string_data, DecoderTrap::Strict)
+            .map(Cow::Owned)
+            .map_err(|_| ReadError::FailedToDecodeLatin1String)
     }
 }
 
-fn read_bool(data: &[u8], position: usize) -> Result<Value, ReadError> {
+pub(crate) fn utf16_str<'a>(
+    data: &'a [u8],
+    bytefield: &data::ByteField,
+) -> Result<Cow<'a, str>, ReadError> {
+    let string_data = utf16_string_data(data, bytefield)?;
+
+    UTF_16LE
+        .decode(string_data, DecoderTrap::Strict)
+        .map(Cow::Owned)
+        .map_err(|_| ReadError::FailedToDecodeUtf16String)
+}
+
+pub(crate) fn decode_bool(data: &[u8], position: usize) -> Result<bool, ReadError> {
     let bool_data = data
         .get(position)
         .ok_or(ReadError::InvalidBoolDataPosition)?;
 
-    Ok(Value::Bool((bool_data & 0b100000) != 0))
+    Ok((bool_data & 0b100000) != 0)
 }
 
-fn read_self_contained_number(data: &[u8], position: usize) -> Result<Value, ReadError> {
+pub(crate) fn decode_self_contained_number(data: &[u8], position: usize) -> Result<i32, ReadError> {
     let number_data = data
         .get(position..(position + metadata::VALUE_HEADER_BYTE_SIZE))
         .ok_or(ReadError::InvalidSelfContainedNumberDataPosition)?;
 
-    let number = as_i27(as_u32(number_data));
-
-    Ok(Value::Number(serde_json::Number::from(number)))
+    Ok(as_i27(as_u32(number_data)))
 }
 
-fn read_number(data: &[u8], bytefield: &data::ByteField) -> Result<Value, ReadError> {
+// Non finite doubles are returned as is, it's up to the caller to decide how to represent them
+pub(crate) fn decode_number(data: &[u8], bytefield: &data::ByteField) -> Result<f64, ReadError> {
     let number_data = data
         .get(bytefield.range.start..bytefield.range.end)
         .ok_or(ReadError::InvalidNumberDataRange)?;
 
-    let number = f64::from_bits(as_u64(number_data));
-    let value = serde_json::Number::from_f64(number).ok_or(ReadError::FailedToDecodeNumber)?;
-    Ok(Value::Number(value))
+    Ok(f64::from_bits(as_u64(number_data)))
+}
+
+// Receives the decoded values of a document in document order.
+// Containers are opened, filled then closed; object values are preceded by their key.
+pub trait ValueBuilder {
+    type Error: From<ReadError>;
+
+    fn null(&mut self) -> Result<(), Self::Error>;
+    fn bool(&mut self, value: bool) -> Result<(), Self::Error>;
+    fn int(&mut self, value: i32) -> Result<(), Self::Error>; // Self contained number
+    fn double(&mut self, value: f64) -> Result<(), Self::Error>; // May be infinite or NaN
+    fn string(&mut self, value: &str) -> Result<(), Self::Error>;
+    fn begin_array(&mut self, length: usize) -> Result<(), Self::Error>;
+    fn end_array(&mut self) -> Result<(), Self::Error>;
+    fn begin_object(&mut self, length: usize) -> Result<(), Self::Error>;
+    fn key(&mut self, key: &str) -> Result<(), Self::Error>;
+    fn end_object(&mut self) -> Result<(), Self::Error>;
 }
 
-fn read_latin1_string_value(data: &[u8], bytefield: &data::ByteField) -> Result<Value, ReadError> {
-    read_latin1_string(data, bytefield).map(Value::String)
+// Decodes an analyzed value into the builder in a single pass
+pub fn build_value<B: ValueBuilder>(
+    data: &[u8],
+    value: &data::Value,
+    builder: &mut B,
+) -> Result<(), B::Error> {
+    visit::walk(value, &mut BuildingVisitor { data, builder })
 }
 
-fn read_utf16_string_value(data: &[u8], bytefield: &data::ByteField) -> Result<Value, ReadError> {
-    read_utf16_string(data, bytefield).map(Value::String)
+pub fn read_value(data: &[u8], value: &data::Value) -> Result<Value, ReadError> {
+    let mut builder = JsonValueBuilder::new();
+    build_value(data, value, &mut builder)?;
+    Ok(builder
+        .finish()
+        .expect("a value is built for every analyzed value"))
+}
+
+struct BuildingVisitor<'a, 'b, B> {
+    data: &'a [u8],
+    builder: &'b mut B,
 }
 
-fn read_array(data: &[u8], array: &data::Array) -> Result<Value, ReadError> {
-    match array
-        .values
-        .iter()
-        .map(|value| read_value(data, value))
-        .collect()
-    {
-        Ok(values) => Ok(Value::Array(values)),
-        Err(e) => Err(e),
+impl<B: ValueBuilder> visit::Visitor for BuildingVisitor<'_, '_, B> {
+    type Error = B::Error;
+
+    fn visit_null(&mut self, _position: usize) -> Result<(), B::Error> {
+        self.builder.null()
+    }
+
+    fn visit_bool(&mut self, position: usize) -> Result<(), B::Error> {
+        self.builder.bool(decode_bool(self.data, position)?)
+    }
+
+    fn visit_self_contained_number(&mut self, position: usize) -> Result<(), B::Error> {
+        self.builder
+            .int(decode_self_contained_number(self.data, position)?)
+    }
+
+    fn visit_number(&mut self, bytefield: &data::ByteField) -> Result<(), B::Error> {
+        self.builder.double(decode_number(self.data, bytefield)?)
+    }
+
+    fn visit_latin1_string(&mut self, bytefield: &data::ByteField) -> Result<(), B::Error> {
+        self.builder.string(&latin1_str(self.data, bytefield)?)
+    }
+
+    fn visit_utf16_string(&mut self, bytefield: &data::ByteField) -> Result<(), B::Error> {
+        self.builder.string(&utf16_str(self.data, bytefield)?)
+    }
+
+    fn enter_array(&mut self, array: &data::Array) -> Result<(), B::Error> {
+        self.builder.begin_array(array.values.len())
+    }
+
+    fn leave_array(&mut self, _array: &data::Array) -> Result<(), B::Error> {
+        self.builder.end_array()
+    }
+
+    fn enter_object(&mut self, object: &data::Object) -> Result<(), B::Error> {
+        self.builder.begin_object(object.entries.len())
     }
+
+    fn enter_entry(&mut self, _index: usize, entry: &data::Entry) -> Result<(), B::Error> {
+        let key = match &entry.key {
+            data::Key::Latin1String(bytefield) => latin1_str(self.data, bytefield),
+            data::Key::Utf16String(bytefield) => utf16_str(self.data, bytefield),
+        }?;
+        self.builder.key(&key)
+    }
+
+    fn leave_object(&mut self, _object: &data::Object) -> Result<(), B::Error> {
+        self.builder.end_object()
+    }
+}
+
+enum PartialContainer {
+    Array(Vec<Value>),
+    Object(Map<String, Value>, Option<String>), // Entries and the key of the next value
+}
+
+// Builds serde_json values for read_value. Only driven by build_value, whose calls are always
+// balanced, which is why calls out of order panic.
+#[derive(Default)]
+pub(crate) struct JsonValueBuilder {
+    stack: Vec<PartialContainer>,
+    root: Option<Value>,
 }
 
-fn read_object(data: &[u8], object: &data::Object) -> Result<Value, ReadError> {
-    match object
-        .entries
-        .iter()
-        .map(|entry| {
-            let key = read_key(data, &entry.key);
-            let value = read_value(data, &entry.value);
-            match (key, value) {
-                (Ok(key), Ok(value)) => Ok((key, value)),
-                (Err(key_error), _) => Err(key_error),
-                (_, Err(value_error)) => Err(value_error),
+impl JsonValueBuilder {
+    pub fn new() -> Self {
+        JsonValueBuilder::default()
+    }
+
+    // Returns the built value, if any was completed
+    pub fn finish(self) -> Option<Value> {
+        self.root
+    }
+
+    fn push_value(&mut self, value: Value) -> Result<(), ReadError> {
+        match self.stack.last_mut() {
+            None => self.root = Some(value),
+            Some(PartialContainer::Array(values)) => values.push(value),
+            Some(PartialContainer::Object(entries, key)) => {
+                let key = key.take().expect("object values are preceded by a key");
+                entries.insert(key, value);
             }
-        })
-        .collect()
-    {
-        Ok(entries) => Ok(Value::Object(entries)),
-        Err(e) => Err(e),
+        }
+        Ok(()) // Returns a result to be the tail of every builder method
+    }
+}
+
+impl ValueBuilder for JsonValueBuilder {
+    type Error = ReadError;
+
+    fn null(&mut self) -> Result<(), ReadError> {
+        self.push_value(Value::Null)
+    }
+
+    fn bool(&mut self, value: bool) -> Result<(), ReadError> {
+        self.push_value(Value::Bool(value))
+    }
+
+    fn int(&mut self, value: i32) -> Result<(), ReadError> {
+        self.push_value(Value::Number(serde_json::Number::from(value)))
+    }
+
+    fn double(&mut self, value: f64) -> Result<(), ReadError> {
+        let value = serde_json::Number::from_f64(value).ok_or(ReadError::FailedToDecodeNumber)?;
+        self.push_value(Value::Number(value))
+    }
+
+    fn string(&mut self, value: &str) -> Result<(), ReadError> {
+        self.push_value(Value::String(value.to_owned()))
+    }
+
+    fn begin_array(&mut self, length: usize) -> Result<(), ReadError> {
+        self.stack
+            .push(PartialContainer::Array(Vec::with_capacity(length)));
+        Ok(())
+    }
+
+    fn end_array(&mut self) -> Result<(), ReadError> {
+        match self.stack.pop() {
+            Some(PartialContainer::Array(values)) => self.push_value(Value::Array(values)),
+            _ => panic!("end_array called without a matching begin_array"),
+        }
+    }
+
+    fn begin_object(&mut self, _length: usize) -> Result<(), ReadError> {
+        self.stack.push(PartialContainer::Object(Map::new(), None));
+        Ok(())
+    }
+
+    fn key(&mut self, key: &str) -> Result<(), ReadError> {
+        match self.stack.last_mut() {
+            Some(PartialContainer::Object(_, next_key)) => *next_key = Some(key.to_owned()),
+            _ => panic!("key called outside of an object"),
+        }
+        Ok(())
+    }
+
+    fn end_object(&mut self) -> Result<(), ReadError> {
+        match self.stack.pop() {
+            Some(PartialContainer::Object(entries, _)) => self.push_value(Value::Object(entries)),
+            _ => panic!("end_object called without a matching begin_object"),
+        }
     }
 }
@@ -0,0 +1,77 @@
+use serde_json::Value;
+
+use crate::analysis::{self, header, metadata};
+use crate::qbjs::{self, DeserializeError};
+
+// Document found in the scanned data and the position of its header
+pub type ScannedDocument = (usize, Result<Value, DeserializeError>);
+
+// Iterator over the documents embedded in arbitrary data, such as core dumps
+pub struct Scan<'a> {
+    data: &'a [u8],
+    position: usize, // Where the search for the next header starts
+}
+
+// Looks for headers followed by a plausible root container base and decodes the documents
+// they start. Documents that decode are skipped over, documents that don't are reported
+// with their error and the search goes on right after their header.
+pub fn scan(data: &[u8]) -> Scan<'_> {
+    Scan { data, position: 0 }
+}
+
+const TAG: &[u8] = b"qbjs";
+
+impl Scan<'_> {
+    // Returns the end of the document starting at the given position when its header is
+    // valid and its root container base fits in the data and is consistent
+    fn plausible_document_end(&self, start: usize) -> Option<usize> {
+        let header_data = self.data.get(start..(start + header::HEADER_LENGTH))?;
+        header::QbjsHeader::from_data(header_data).ok()?;
+
+        let base_start = start + header::HEADER_LENGTH;
+        let base = analysis::analyze_container_base(self.data, base_start).ok()?;
+
+        let size = base.size as usize;
+        let table_offset = base.table_offset as usize;
+        let table_length = base.length as usize * metadata::OFFSET_TABLE_ENTRY_BYTE_SIZE;
+        let is_consistent = size >= metadata::CONTAINER_BASE_LENGTH
+            && size % 4 == 0
+            && if base.length == 0 {
+                table_offset <= size
+            } else {
+                table_offset >= metadata::CONTAINER_BASE_LENGTH
+                    && table_offset + table_length <= size
+            };
+
+        let end = base_start + size;
+        (is_consistent && end <= self.data.len()).then_some(end)
+    }
+}
+
+impl Iterator for Scan<'_> {
+    type Item = ScannedDocument;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(found) = self.data[self.position..]
+            .windows(TAG.len())
+            .position(|window| window == TAG)
+        {
+            let start = self.position + found;
+            self.position = start + 1;
+
+            let end = match self.plausible_document_end(start) {
+                Some(end) => end,
+                None => continue,
+            };
+
+            let document = qbjs::deserialize_to_json(&self.data[start..end]);
+            if document.is_ok() {
+                self.position = end;
+            }
+            return Some((start, document));
+        }
+
+        self.position = self.data.len();
+        None
+    }
+}
@@ -0,0 +1,333 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde_json::{Map, Value};
+
+use crate::analysis::header;
+use crate::qbjs::{self, DeserializeError};
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum SettingsError {
+    IoError(io::ErrorKind),
+    InvalidLine(usize), // Line number, from 1
+    DeserializeError {
+        key: String,
+        error: DeserializeError,
+    },
+}
+
+// Reads a settings file written by QSettings in the INI format
+pub fn settings_file_to_json<P: AsRef<Path>>(path: P) -> Result<Value, SettingsError> {
+    let ini = fs::read(path).map_err(|err| SettingsError::IoError(err.kind()))?;
+    settings_to_json(&ini)
+}
+
+// Returns an object holding every setting by its QSettings key, such as "group/key".
+// Values are decoded the same way QSettings does: string lists are arrays, @Invalid() is null
+// and @ByteArray() values are decoded as documents when they start with a qbjs header,
+// other byte arrays are read as UTF-8 strings. Other @ values are kept as strings.
+// Raw non ASCII bytes are read as latin1, the same as QSettings without an INI codec.
+pub fn settings_to_json(ini: &[u8]) -> Result<Value, SettingsError> {
+    let mut settings = Map::new();
+    let mut section = String::new();
+
+    for (line_number, line) in logical_lines(ini) {
+        let line = trim(line);
+        if line.is_empty() || line[0] == b';' || line[0] == b'#' {
+            continue;
+        }
+
+        if line[0] == b'[' {
+            let name_end = line
+                .iter()
+                .position(|byte| *byte == b']')
+                .ok_or(SettingsError::InvalidLine(line_number))?;
+            section = section_name(trim(&line[1..name_end]));
+            continue;
+        }
+
+        let separator = line
+            .iter()
+            .position(|byte| *byte == b'=')
+            .ok_or(SettingsError::InvalidLine(line_number))?;
+        let key = unescape_key(trim(&line[..separator]));
+        let key = if section.is_empty() {
+            key
+        } else {
+            format!("{}/{}", section, key)
+        };
+
+        let value = match unescape_value(&line[(separator + 1)..]) {
+            UnescapedValue::String(string) => string_to_json(&key, &string)?,
+            UnescapedValue::List(strings) => Value::Array(
+                strings
+                    .iter()
+                    .map(|string| string_to_json(&key, string))
+                    .collect::<Result<_, _>>()?,
+            ),
+        };
+        settings.insert(key, value);
+    }
+
+    Ok(Value::Object(settings))
+}
+
+// Lines ending with an escaping backslash continue on the next line
+fn logical_lines(ini: &[u8]) -> Vec<(usize, &[u8])> {
+    let mut lines = Vec::new();
+    let mut line_start = 0;
+    let mut line_number = 1;
+    let mut logical_line_number = 1;
+
+    for (position, byte) in ini.iter().enumerate() {
+        if *byte != b'\n' {
+            continue;
+        }
+        let line = &ini[line_start..position];
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        let backslashes = line.iter().rev().take_while(|byte| **byte == b'\\').count();
+        let is_continued = backslashes % 2 == 1;
+        line_number += 1;
+        if !is_continued {
+            lines.push((logical_line_number, &ini[line_start..position]));
+            line_start = position + 1;
+            logical_line_number = line_number;
+        }
+    }
+    if line_start < ini.len() {
+        lines.push((logical_line_number, &ini[line_start..]));
+    }
+
+    lines
+}
+
+fn trim(mut bytes: &[u8]) -> &[u8] {
+    while let [first, rest @ ..] = bytes {
+        if !first.is_ascii_whitespace() {
+            break;
+        }
+        bytes = rest;
+    }
+    while let [rest @ .., last] = bytes {
+        if !last.is_ascii_whitespace() {
+            break;
+        }
+        bytes = rest;
+    }
+    bytes
+}
+
+// [General] holds the keys that aren't in a group, a group named General is written [%General]
+fn section_name(name: &[u8]) -> String {
+    if name.eq_ignore_ascii_case(b"general") {
+        String::new()
+    } else if name.eq_ignore_ascii_case(b"%general") {
+        unescape_key(&name[1..])
+    } else {
+        unescape_key(name)
+    }
+}
+
+fn hex_value(digits: &[u8]) -> Option<u32> {
+    std::str::from_utf8(digits)
+        .ok()
+        .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+}
+
+// Keys escape "/" as "\", other characters as %XX or %UXXXX
+fn unescape_key(key: &[u8]) -> String {
+    let mut unescaped = String::with_capacity(key.len());
+    let mut position = 0;
+    while position < key.len() {
+        let byte = key[position];
+        if byte == b'\\' {
+            unescaped.push('/');
+            position += 1;
+            continue;
+        }
+
+        if byte == b'%' {
+            let (digits_start, digits_length) = if key.get(position + 1) == Some(&b'U') {
+                (position + 2, 4)
+            } else {
+                (position + 1, 2)
+            };
+            let c = key
+                .get(digits_start..(digits_start + digits_length))
+                .and_then(hex_value)
+                .and_then(char::from_u32);
+            if let Some(c) = c {
+                unescaped.push(c);
+                position = digits_start + digits_length;
+                continue;
+            }
+        }
+
+        unescaped.push(char::from(byte));
+        position += 1;
+    }
+    unescaped
+}
+
+enum UnescapedValue {
+    String(Vec<u16>),
+    List(Vec<Vec<u16>>), // Values with commas outside of quotes
+}
+
+fn chop_trailing_spaces(string: &mut Vec<u16>, limit: usize) {
+    while string.len() > limit && matches!(string.last(), Some(0x20) | Some(0x09)) {
+        string.pop();
+    }
+}
+
+// Same as QSettingsPrivate::iniUnescapedStringList, which reads UTF-16 code units.
+// Quotes protect commas and semicolons, which start a comment.
+fn unescape_value(value: &[u8]) -> UnescapedValue {
+    let mut strings = Vec::new();
+    let mut string = Vec::new();
+    let mut is_list = false;
+    let mut in_quotes = false;
+    let mut is_quoted = false;
+    let mut chop_limit = 0;
+    let mut skip_spaces = true;
+    let mut position = 0;
+
+    while position < value.len() {
+        let byte = value[position];
+        if skip_spaces {
+            if byte == b' ' || byte == b'\t' {
+                position += 1;
+                continue;
+            }
+            skip_spaces = false;
+            chop_limit = string.len();
+        }
+
+        match byte {
+            b'\\' => {
+                position += 1;
+                let escaped = match value.get(position) {
+                    Some(escaped) => *escaped,
+                    None => break,
+                };
+                position += 1;
+                match escaped {
+                    b'a' => string.push(0x07),
+                    b'b' => string.push(0x08),
+                    b'f' => string.push(0x0c),
+                    b'n' => string.push(u16::from(b'\n')),
+                    b'r' => string.push(u16::from(b'\r')),
+                    b't' => string.push(u16::from(b'\t')),
+                    b'v' => string.push(0x0b),
+                    b'"' | b'?' | b'\'' | b'\\' => string.push(u16::from(escaped)),
+                    b'x' | b'0'..=b'7' => {
+                        let (radix, first_digit) = if escaped == b'x' {
+                            match value.get(position) {
+                                Some(digit) if digit.is_ascii_hexdigit() => (16, None),
+                                // Skipped, the same as unknown escapes
+                                _ => {
+                                    chop_limit = string.len();
+                                    continue;
+                                }
+                            }
+                        } else {
+                            (8, Some(u32::from(escaped - b'0')))
+                        };
+                        let mut code = first_digit.unwrap_or(0);
+                        while let Some(digit) = value
+                            .get(position)
+                            .and_then(|digit| char::from(*digit).to_digit(radix))
+                        {
+                            code = code.wrapping_mul(radix).wrapping_add(digit);
+                            position += 1;
+                        }
+                        string.push(code as u16);
+                    }
+                    // Line continuation
+                    b'\n' | b'\r' => {
+                        if let Some(next) = value.get(position) {
+                            if (*next == b'\n' || *next == b'\r') && *next != escaped {
+                                position += 1;
+                            }
+                        }
+                    }
+                    // Unknown escapes are skipped
+                    _ => {}
+                }
+                chop_limit = string.len();
+            }
+            b'"' => {
+                position += 1;
+                is_quoted = true;
+                in_quotes = !in_quotes;
+                skip_spaces = !in_quotes;
+            }
+            b',' if !in_quotes => {
+                if !is_quoted {
+                    chop_trailing_spaces(&mut string, chop_limit);
+                }
+                is_list = true;
+                strings.push(std::mem::take(&mut string));
+                is_quoted = false;
+                position += 1;
+                skip_spaces = true;
+            }
+            b';' if !in_quotes => break,
+            _ => {
+                string.push(u16::from(byte));
+                position += 1;
+            }
+        }
+    }
+
+    if !is_quoted {
+        chop_trailing_spaces(&mut string, chop_limit);
+    }
+
+    if is_list {
+        strings.push(string);
+        UnescapedValue::List(strings)
+    } else {
+        UnescapedValue::String(string)
+    }
+}
+
+const BYTE_ARRAY_PREFIX: &str = "@ByteArray(";
+
+// Same as QSettingsPrivate::stringToVariant
+fn string_to_json(key: &str, string: &[u16]) -> Result<Value, SettingsError> {
+    let string = String::from_utf16_lossy(string);
+
+    if let Some(escaped) = string.strip_prefix("@@") {
+        return Ok(Value::String(format!("@{}", escaped)));
+    }
+    if string == "@Invalid()" {
+        return Ok(Value::Null);
+    }
+
+    let payload = string
+        .strip_prefix(BYTE_ARRAY_PREFIX)
+        .and_then(|payload| payload.strip_suffix(')'));
+    let payload = match payload {
+        Some(payload) => payload,
+        None => return Ok(Value::String(string)),
+    };
+
+    // The characters of byte arrays are their bytes, the same as QString::toLatin1
+    let bytes = payload
+        .chars()
+        .map(|c| u8::try_from(u32::from(c)).unwrap_or(b'?'))
+        .collect::<Vec<_>>();
+
+    if header::has_qbjs_header(&bytes) {
+        return qbjs::deserialize_to_json(&bytes).map_err(|error| {
+            SettingsError::DeserializeError {
+                key: key.to_owned(),
+                error,
+            }
+        });
+    }
+
+    Ok(Value::String(String::from_utf8_lossy(&bytes).into_owned()))
+}
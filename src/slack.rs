@@ -0,0 +1,311 @@
+use std::ops::Range;
+
+use serde_json::Value;
+
+use crate::analysis::{self, data, metadata};
+use crate::qbjs::{self, DeserializeError};
+use crate::read;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Recovered {
+    Container(Value), // Array or object whose whole tree lies in the gap
+    // Entry header followed by its key. The value is only known when stored in the header,
+    // other values are stored at an offset relative to a container that isn't known.
+    Entry {
+        key: String,
+        qt_value_type: u8,
+        value: Option<Value>,
+    },
+    Latin1String(String),
+    Utf16String(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecoveredValue {
+    pub range: Range<usize>,
+    pub recovered: Recovered,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlackReport {
+    pub referenced_bytes: usize,
+    pub gaps: Vec<Range<usize>>, // Bytes no value reachable from the root refers to
+    pub recovered: Vec<RecoveredValue>, // Plausible stale values found in the gaps
+}
+
+// Marks every byte reachable from the root, reports the gaps between them
+// and tries to decode the stale values left in these gaps.
+pub fn analyze_slack(qbjs: &[u8]) -> Result<SlackReport, DeserializeError> {
+    if qbjs.is_empty() {
+        return Ok(SlackReport {
+            referenced_bytes: 0,
+            gaps: Vec::new(),
+            recovered: Vec::new(),
+        });
+    }
+
+    let document = qbjs::analyze_root_container(qbjs)?;
+    let referenced = referenced_bytes(qbjs, &document);
+
+    let gaps = gaps(&referenced);
+    let recovered = gaps
+        .iter()
+        .flat_map(|gap| recover_values(qbjs, gap.clone()))
+        .collect();
+
+    Ok(SlackReport {
+        referenced_bytes: referenced.iter().filter(|byte| **byte).count(),
+        gaps,
+        recovered,
+    })
+}
+
+// Tells for each byte of the document whether a value reachable from the root refers to it
+pub(crate) fn referenced_bytes(qbjs: &[u8], document: &data::Value) -> Vec<bool> {
+    let mut referenced = vec![false; qbjs.len()];
+    mark(&mut referenced, 0..analysis::header::HEADER_LENGTH);
+    mark_value(&mut referenced, document);
+    referenced
+}
+
+fn mark(referenced: &mut [bool], range: Range<usize>) {
+    let end = range.end.min(referenced.len());
+    let start = range.start.min(end);
+    referenced[start..end].fill(true);
+}
+
+// Strings are aligned to 4 bytes, their padding is referenced too
+pub(crate) fn aligned(position: usize) -> usize {
+    (position + 3) / 4 * 4
+}
+
+fn mark_string(referenced: &mut [bool], bytefield: &data::ByteField, size_field_length: usize) {
+    let start = bytefield.range.start - size_field_length;
+    mark(referenced, start..aligned(bytefield.range.end));
+}
+
+fn mark_key(referenced: &mut [bool], key: &data::Key) {
+    match key {
+        data::Key::Latin1String(bytefield) => {
+            mark_string(referenced, bytefield, metadata::LATIN1_SIZE_FIELD_LENGTH)
+        }
+        data::Key::Utf16String(bytefield) => {
+            mark_string(referenced, bytefield, metadata::UTF16_SIZE_FIELD_LENGTH)
+        }
+    }
+}
+
+fn mark_container_base(referenced: &mut [bool], container: &data::ByteField) {
+    let start = container.range.start;
+    mark(referenced, start..(start + metadata::CONTAINER_BASE_LENGTH));
+}
+
+// Headers of array values are in the array's table, headers of entries are marked with them
+fn mark_value(referenced: &mut [bool], value: &data::Value) {
+    match value {
+        data::Value::Null(_) | data::Value::Bool(_) | data::Value::SelfContainedNumber(_) => {}
+        data::Value::Number(bytefield) => mark(referenced, bytefield.range.clone()),
+        data::Value::Latin1String(bytefield) => {
+            mark_string(referenced, bytefield, metadata::LATIN1_SIZE_FIELD_LENGTH)
+        }
+        data::Value::Utf16String(bytefield) => {
+            mark_string(referenced, bytefield, metadata::UTF16_SIZE_FIELD_LENGTH)
+        }
+        data::Value::Array(array) => {
+            mark_container_base(referenced, &array.container);
+            mark(referenced, array.table.range.clone());
+            for value in &array.values {
+                mark_value(referenced, value);
+            }
+        }
+        data::Value::Object(object) => {
+            mark_container_base(referenced, &object.container);
+            mark(referenced, object.table.range.clone());
+            for entry in &object.entries {
+                let header_end = entry.position + metadata::VALUE_HEADER_BYTE_SIZE;
+                mark(referenced, entry.position..header_end);
+                mark_key(referenced, &entry.key);
+                mark_value(referenced, &entry.value);
+            }
+        }
+    }
+}
+
+fn gaps(referenced: &[bool]) -> Vec<Range<usize>> {
+    let mut gaps = Vec::new();
+    let mut gap_start = None;
+    for (position, is_referenced) in referenced.iter().enumerate() {
+        match (gap_start, is_referenced) {
+            (None, false) => gap_start = Some(position),
+            (Some(start), true) => {
+                gaps.push(start..position);
+                gap_start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(start) = gap_start {
+        gaps.push(start..referenced.len());
+    }
+    gaps
+}
+
+// Values are aligned to 4 bytes, every aligned position of the gap is tried.
+// Decoding only sees the data up to the end of the gap so that recovered values can't
+// refer to bytes outside of it.
+fn recover_values(qbjs: &[u8], gap: Range<usize>) -> Vec<RecoveredValue> {
+    let data = &qbjs[..gap.end];
+    let mut recovered_values = Vec::new();
+
+    let mut position = aligned(gap.start);
+    while position < gap.end {
+        let recovered = recover_container(data, position)
+            .or_else(|| recover_entry(data, position))
+            .or_else(|| recover_latin1_string(data, position))
+            .or_else(|| recover_utf16_string(data, position));
+
+        match recovered {
+            Some((recovered, end)) => {
+                recovered_values.push(RecoveredValue {
+                    range: position..end,
+                    recovered,
+                });
+                position = aligned(end.max(position + 1));
+            }
+            None => position += 4,
+        }
+    }
+
+    recovered_values
+}
+
+fn is_plausible_char(c: char) -> bool {
+    !c.is_control() || matches!(c, '\t' | '\n' | '\r')
+}
+
+fn is_plausible_string(string: &str) -> bool {
+    !string.is_empty() && string.chars().all(is_plausible_char)
+}
+
+fn recover_container(data: &[u8], base_start: usize) -> Option<(Recovered, usize)> {
+    let base = analysis::analyze_container_base(data, base_start).ok()?;
+    let table_end =
+        base.table_offset as usize + base.length as usize * metadata::OFFSET_TABLE_ENTRY_BYTE_SIZE;
+    let is_plausible = base.length > 0
+        && base.table_offset as usize >= metadata::CONTAINER_BASE_LENGTH
+        && table_end <= base.size as usize
+        && base_start + base.size as usize <= data.len();
+    if !is_plausible || !has_forward_references(data, base_start) {
+        return None;
+    }
+
+    let (container, end) = analysis::analyze_container(data, base_start).ok()?;
+    let value = read::read_value(data, &container).ok()?;
+    Some((Recovered::Container(value), end))
+}
+
+// Values are stored after the base of their container. Checking nested containers do so
+// before analyzing stale bytes ensures the analysis doesn't loop over cyclic references.
+fn has_forward_references(data: &[u8], base_start: usize) -> bool {
+    let base = match analysis::analyze_container_base(data, base_start) {
+        Ok(base) => base,
+        Err(_) => return false,
+    };
+    let table_start = base_start + base.table_offset as usize;
+
+    (0..base.length as usize).all(|index| {
+        let table_entry_start = table_start + index * metadata::OFFSET_TABLE_ENTRY_BYTE_SIZE;
+        let header_start = if base.is_object {
+            match analysis::analyze_offset_table_entry(data, table_entry_start) {
+                Ok(offset) => base_start + offset,
+                Err(_) => return false,
+            }
+        } else {
+            table_entry_start
+        };
+
+        match analysis::analyze_value_header(data, header_start) {
+            Ok(header) => match header.qt_value_type {
+                analysis::QT_ARRAY_VALUE | analysis::QT_OBJECT_VALUE => {
+                    let offset = header.value_bit_field as usize;
+                    offset >= metadata::CONTAINER_BASE_LENGTH
+                        && has_forward_references(data, base_start + offset)
+                }
+                _ => true,
+            },
+            Err(_) => false,
+        }
+    })
+}
+
+fn recover_entry(data: &[u8], header_start: usize) -> Option<(Recovered, usize)> {
+    let header = analysis::analyze_value_header(data, header_start).ok()?;
+    if header.qt_value_type > analysis::QT_OBJECT_VALUE {
+        return None;
+    }
+
+    let (key, key_end) = analysis::analyze_key(data, &header).ok()?;
+    let key = match &key {
+        data::Key::Latin1String(bytefield) => read::latin1_str(data, bytefield),
+        data::Key::Utf16String(bytefield) => read::utf16_str(data, bytefield),
+    }
+    .ok()?;
+    if !is_plausible_string(&key) || key_end > data.len() {
+        return None;
+    }
+
+    let value = match header.qt_value_type {
+        analysis::QT_NULL_VALUE => Some(Value::Null),
+        analysis::QT_BOOL_VALUE => read::decode_bool(data, header_start).ok().map(Value::from),
+        analysis::QT_NUMBER_VALUE if header.latin_or_int_value_flag => {
+            read::decode_self_contained_number(data, header_start)
+                .ok()
+                .map(Value::from)
+        }
+        _ => None,
+    };
+
+    let recovered = Recovered::Entry {
+        key: key.into_owned(),
+        qt_value_type: header.qt_value_type,
+        value,
+    };
+    Some((recovered, key_end))
+}
+
+fn recover_latin1_string(data: &[u8], string_start: usize) -> Option<(Recovered, usize)> {
+    let size_field = data.get(string_start..(string_start + metadata::LATIN1_SIZE_FIELD_LENGTH))?;
+    let length = u16::from_le_bytes([size_field[0], size_field[1]]) as usize;
+    let data_start = string_start + metadata::LATIN1_SIZE_FIELD_LENGTH;
+    let bytefield = data::ByteField {
+        range: data_start..(data_start + length),
+    };
+
+    let string = read::latin1_str(data, &bytefield).ok()?;
+    if !is_plausible_string(&string) {
+        return None;
+    }
+    Some((
+        Recovered::Latin1String(string.into_owned()),
+        aligned(bytefield.range.end).min(data.len()),
+    ))
+}
+
+fn recover_utf16_string(data: &[u8], string_start: usize) -> Option<(Recovered, usize)> {
+    let size_field = data.get(string_start..(string_start + metadata::UTF16_SIZE_FIELD_LENGTH))?;
+    let length = u32::from_le_bytes(size_field.try_into().ok()?) as usize;
+    let data_start = string_start + metadata::UTF16_SIZE_FIELD_LENGTH;
+    let data_end = data_start.checked_add(length.checked_mul(metadata::UTF16_CHAR_LENGTH)?)?;
+    let bytefield = data::ByteField {
+        range: data_start..data_end,
+    };
+
+    let string = read::utf16_str(data, &bytefield).ok()?;
+    if !is_plausible_string(&string) {
+        return None;
+    }
+    Some((
+        Recovered::Utf16String(string.into_owned()),
+        aligned(bytefield.range.end).min(data.len()),
+    ))
+}
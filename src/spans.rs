@@ -0,0 +1,183 @@
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+use crate::analysis::{data, metadata};
+use crate::read::{self, ReadError};
+use crate::visit::{self, Visitor};
+
+// Bytes a decoded value was read from.
+// The payload of null, bool and self contained numbers is their value header, the one of arrays
+// and objects is the whole container and the one of strings includes their size field.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Span {
+    pub header: Option<Range<usize>>, // Value header, None for the root container
+    pub key: Option<Range<usize>>,    // Key of object entries, size field included
+    pub payload: Range<usize>,
+}
+
+// Maps the JSON Pointer (RFC 6901) of every value of the analyzed tree to its span
+pub fn analyze_spans(
+    data: &[u8],
+    value: &data::Value,
+) -> Result<BTreeMap<String, Span>, ReadError> {
+    let mut visitor = SpanVisitor {
+        data,
+        pointer: Vec::new(),
+        header: None,
+        key: None,
+        spans: BTreeMap::new(),
+    };
+
+    visit::walk(value, &mut visitor)?;
+
+    Ok(visitor.spans)
+}
+
+// Escapes a key to be used as a JSON Pointer reference token
+pub fn escape_pointer_token(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+// Returns None for a "~" that isn't followed by "0" or "1"
+pub(crate) fn unescape_pointer_token(token: &str) -> Option<String> {
+    let mut unescaped = String::with_capacity(token.len());
+    let mut chars = token.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '~' => match chars.next() {
+                Some('0') => unescaped.push('~'),
+                Some('1') => unescaped.push('/'),
+                _ => return None,
+            },
+            c => unescaped.push(c),
+        }
+    }
+    Some(unescaped)
+}
+
+// Returns the unescaped reference tokens of a JSON Pointer (RFC 6901)
+pub(crate) fn parse_pointer(pointer: &str) -> Option<Vec<String>> {
+    if pointer.is_empty() {
+        return Some(Vec::new());
+    }
+
+    pointer
+        .strip_prefix('/')?
+        .split('/')
+        .map(unescape_pointer_token)
+        .collect()
+}
+
+// Array indexes are decimal numbers without leading zeros
+pub(crate) fn parse_index(token: &str) -> Option<usize> {
+    if token.is_empty() || (token.len() > 1 && token.starts_with('0')) {
+        return None;
+    }
+    if !token.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    token.parse().ok()
+}
+
+struct SpanVisitor<'a> {
+    data: &'a [u8],
+    pointer: Vec<String>, // Escaped reference tokens
+    header: Option<Range<usize>>,
+    key: Option<Range<usize>>,
+    spans: BTreeMap<String, Span>,
+}
+
+impl SpanVisitor<'_> {
+    fn insert_span(&mut self, payload: Range<usize>) -> Result<(), ReadError> {
+        let pointer = self
+            .pointer
+            .iter()
+            .map(|token| format!("/{}", token))
+            .collect::<String>();
+
+        let span = Span {
+            header: self.header.take(),
+            key: self.key.take(),
+            payload,
+        };
+
+        self.spans.insert(pointer, span);
+        Ok(())
+    }
+
+    fn insert_inline_value_span(&mut self, position: usize) -> Result<(), ReadError> {
+        self.insert_span(position..(position + metadata::VALUE_HEADER_BYTE_SIZE))
+    }
+}
+
+impl Visitor for SpanVisitor<'_> {
+    type Error = ReadError;
+
+    fn visit_null(&mut self, position: usize) -> Result<(), ReadError> {
+        self.insert_inline_value_span(position)
+    }
+
+    fn visit_bool(&mut self, position: usize) -> Result<(), ReadError> {
+        self.insert_inline_value_span(position)
+    }
+
+    fn visit_self_contained_number(&mut self, position: usize) -> Result<(), ReadError> {
+        self.insert_inline_value_span(position)
+    }
+
+    fn visit_number(&mut self, bytefield: &data::ByteField) -> Result<(), ReadError> {
+        self.insert_span(bytefield.range.clone())
+    }
+
+    fn visit_latin1_string(&mut self, bytefield: &data::ByteField) -> Result<(), ReadError> {
+        let size_field_start = bytefield.range.start - metadata::LATIN1_SIZE_FIELD_LENGTH;
+        self.insert_span(size_field_start..bytefield.range.end)
+    }
+
+    fn visit_utf16_string(&mut self, bytefield: &data::ByteField) -> Result<(), ReadError> {
+        let size_field_start = bytefield.range.start - metadata::UTF16_SIZE_FIELD_LENGTH;
+        self.insert_span(size_field_start..bytefield.range.end)
+    }
+
+    fn enter_array(&mut self, array: &data::Array) -> Result<(), ReadError> {
+        self.insert_span(array.container.range.clone())
+    }
+
+    fn enter_array_value(&mut self, index: usize, header_position: usize) -> Result<(), ReadError> {
+        self.pointer.push(index.to_string());
+        self.header = Some(header_position..(header_position + metadata::VALUE_HEADER_BYTE_SIZE));
+        Ok(())
+    }
+
+    fn leave_array_value(&mut self, _index: usize) -> Result<(), ReadError> {
+        self.pointer.pop();
+        Ok(())
+    }
+
+    fn enter_object(&mut self, object: &data::Object) -> Result<(), ReadError> {
+        self.insert_span(object.container.range.clone())
+    }
+
+    fn enter_entry(&mut self, _index: usize, entry: &data::Entry) -> Result<(), ReadError> {
+        let (key, key_range) = match &entry.key {
+            data::Key::Latin1String(bytefield) => (
+                read::latin1_str(self.data, bytefield)?,
+                (bytefield.range.start - metadata::LATIN1_SIZE_FIELD_LENGTH)..bytefield.range.end,
+            ),
+            data::Key::Utf16String(bytefield) => (
+                read::utf16_str(self.data, bytefield)?,
+                (bytefield.range.start - metadata::UTF16_SIZE_FIELD_LENGTH)..bytefield.range.end,
+            ),
+        };
+
+        self.pointer.push(escape_pointer_token(&key));
+        self.header = Some(entry.position..(entry.position + metadata::VALUE_HEADER_BYTE_SIZE));
+        self.key = Some(key_range);
+        Ok(())
+    }
+
+    fn leave_entry(&mut self, _index: usize, _entry: &data::Entry) -> Result<(), ReadError> {
+        self.pointer.pop();
+        Ok(())
+    }
+}
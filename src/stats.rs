@@ -0,0 +1,226 @@
+use crate::analysis::{data, header, metadata};
+use crate::qbjs::{self, DeserializeError};
+use crate::read::{self, ReadError};
+use crate::slack;
+use crate::spans::escape_pointer_token;
+use crate::visit::{self, Visitor};
+
+const LARGEST_CONTAINERS_COUNT: usize = 5;
+
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct ValueCounts {
+    pub nulls: usize,
+    pub bools: usize,
+    pub self_contained_numbers: usize,
+    pub doubles: usize,
+    pub latin1_strings: usize,
+    pub utf16_strings: usize,
+    pub arrays: usize,
+    pub objects: usize,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ContainerStats {
+    pub pointer: String,
+    pub is_object: bool,
+    pub length: usize,
+    pub size: usize, // Bytes from the container base to the end of the container
+}
+
+// Bytes of the document by usage. Bytes referred to more than once are counted each time,
+// so the categories only add up to the total when nothing is shared, as written by Qt.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct ByteUsage {
+    pub total: usize,
+    pub header: usize, // qbjs tag and version
+    pub container_bases: usize,
+    pub tables: usize, // Value headers of arrays and entry offsets of objects
+    pub entry_headers: usize, // Value headers of object entries
+    pub keys: usize,   // Size fields and characters
+    pub strings: usize, // Size fields and characters
+    pub doubles: usize,
+    pub padding: usize, // Alignment of keys and strings
+    pub wasted: usize,  // Bytes no value reachable from the root refers to
+}
+
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Stats {
+    pub values: ValueCounts,
+    pub latin1_keys: usize,
+    pub utf16_keys: usize,
+    pub max_depth: usize, // The root container is at depth 1
+    pub largest_containers: Vec<ContainerStats>, // Largest first
+    pub bytes: ByteUsage,
+}
+
+pub fn stats(qbjs: &[u8]) -> Result<Stats, DeserializeError> {
+    if qbjs.is_empty() {
+        return Ok(Stats::default());
+    }
+
+    let document = qbjs::analyze_root_container(qbjs)?;
+
+    let mut collector = StatsCollector {
+        data: qbjs,
+        stats: Stats::default(),
+        pointer: Vec::new(),
+        depth: 0,
+        containers: Vec::new(),
+    };
+    visit::walk(&document, &mut collector).map_err(DeserializeError::ReadError)?;
+
+    let mut stats = collector.stats;
+
+    let mut containers = collector.containers;
+    containers.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.pointer.cmp(&b.pointer)));
+    containers.truncate(LARGEST_CONTAINERS_COUNT);
+    stats.largest_containers = containers;
+
+    let referenced = slack::referenced_bytes(qbjs, &document);
+    stats.bytes.total = qbjs.len();
+    stats.bytes.header = header::HEADER_LENGTH;
+    stats.bytes.wasted = referenced.iter().filter(|byte| !**byte).count();
+
+    Ok(stats)
+}
+
+struct StatsCollector<'a> {
+    data: &'a [u8],
+    stats: Stats,
+    pointer: Vec<String>, // Reference tokens of the current value
+    depth: usize,
+    containers: Vec<ContainerStats>,
+}
+
+impl StatsCollector<'_> {
+    fn count_string(&mut self, bytefield: &data::ByteField, size_field_length: usize) -> usize {
+        self.stats.bytes.padding += slack::aligned(bytefield.range.end) - bytefield.range.end;
+        size_field_length + bytefield.range.len()
+    }
+
+    fn enter_container(
+        &mut self,
+        container: &data::ByteField,
+        table: &data::ByteField,
+        is_object: bool,
+        length: usize,
+    ) {
+        self.depth += 1;
+        self.stats.max_depth = self.stats.max_depth.max(self.depth);
+
+        self.stats.bytes.container_bases += metadata::CONTAINER_BASE_LENGTH;
+        self.stats.bytes.tables += table.range.len();
+
+        let pointer = self
+            .pointer
+            .iter()
+            .map(|token| format!("/{}", token))
+            .collect();
+        self.containers.push(ContainerStats {
+            pointer,
+            is_object,
+            length,
+            size: container.range.len(),
+        });
+    }
+}
+
+impl Visitor for StatsCollector<'_> {
+    type Error = ReadError;
+
+    fn visit_null(&mut self, _position: usize) -> Result<(), ReadError> {
+        self.stats.values.nulls += 1;
+        Ok(())
+    }
+
+    fn visit_bool(&mut self, _position: usize) -> Result<(), ReadError> {
+        self.stats.values.bools += 1;
+        Ok(())
+    }
+
+    fn visit_self_contained_number(&mut self, _position: usize) -> Result<(), ReadError> {
+        self.stats.values.self_contained_numbers += 1;
+        Ok(())
+    }
+
+    fn visit_number(&mut self, bytefield: &data::ByteField) -> Result<(), ReadError> {
+        self.stats.values.doubles += 1;
+        self.stats.bytes.doubles += bytefield.range.len();
+        Ok(())
+    }
+
+    fn visit_latin1_string(&mut self, bytefield: &data::ByteField) -> Result<(), ReadError> {
+        self.stats.values.latin1_strings += 1;
+        self.stats.bytes.strings +=
+            self.count_string(bytefield, metadata::LATIN1_SIZE_FIELD_LENGTH);
+        Ok(())
+    }
+
+    fn visit_utf16_string(&mut self, bytefield: &data::ByteField) -> Result<(), ReadError> {
+        self.stats.values.utf16_strings += 1;
+        self.stats.bytes.strings += self.count_string(bytefield, metadata::UTF16_SIZE_FIELD_LENGTH);
+        Ok(())
+    }
+
+    fn enter_array(&mut self, array: &data::Array) -> Result<(), ReadError> {
+        self.stats.values.arrays += 1;
+        self.enter_container(&array.container, &array.table, false, array.values.len());
+        Ok(())
+    }
+
+    fn enter_array_value(
+        &mut self,
+        index: usize,
+        _header_position: usize,
+    ) -> Result<(), ReadError> {
+        self.pointer.push(index.to_string());
+        Ok(())
+    }
+
+    fn leave_array_value(&mut self, _index: usize) -> Result<(), ReadError> {
+        self.pointer.pop();
+        Ok(())
+    }
+
+    fn leave_array(&mut self, _array: &data::Array) -> Result<(), ReadError> {
+        self.depth -= 1;
+        Ok(())
+    }
+
+    fn enter_object(&mut self, object: &data::Object) -> Result<(), ReadError> {
+        self.stats.values.objects += 1;
+        self.enter_container(&object.container, &object.table, true, object.entries.len());
+        Ok(())
+    }
+
+    fn enter_entry(&mut self, _index: usize, entry: &data::Entry) -> Result<(), ReadError> {
+        self.stats.bytes.entry_headers += metadata::VALUE_HEADER_BYTE_SIZE;
+
+        let key = match &entry.key {
+            data::Key::Latin1String(bytefield) => {
+                self.stats.latin1_keys += 1;
+                self.stats.bytes.keys +=
+                    self.count_string(bytefield, metadata::LATIN1_SIZE_FIELD_LENGTH);
+                read::latin1_str(self.data, bytefield)?
+            }
+            data::Key::Utf16String(bytefield) => {
+                self.stats.utf16_keys += 1;
+                self.stats.bytes.keys +=
+                    self.count_string(bytefield, metadata::UTF16_SIZE_FIELD_LENGTH);
+                read::utf16_str(self.data, bytefield)?
+            }
+        };
+        self.pointer.push(escape_pointer_token(&key));
+        Ok(())
+    }
+
+    fn leave_entry(&mut self, _index: usize, _entry: &data::Entry) -> Result<(), ReadError> {
+        self.pointer.pop();
+        Ok(())
+    }
+
+    fn leave_object(&mut self, _object: &data::Object) -> Result<(), ReadError> {
+        self.depth -= 1;
+        Ok(())
+    }
+}
@@ -0,0 +1,138 @@
+use std::error::Error;
+use std::fmt;
+
+use serde_json::Value;
+
+use crate::qbjs::{self, DeserializeError};
+
+// Offsets are byte offsets in the original text
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum TextDecodeError {
+    InvalidCharacter(usize),
+    InvalidPadding(usize),
+    Truncated(usize), // Start of the incomplete base64 group or the unpaired hex digit
+    DeserializeError(DeserializeError),
+}
+
+impl fmt::Display for TextDecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TextDecodeError::InvalidCharacter(offset) => {
+                write!(f, "invalid character at byte {}", offset)
+            }
+            TextDecodeError::InvalidPadding(offset) => {
+                write!(f, "invalid padding at byte {}", offset)
+            }
+            TextDecodeError::Truncated(offset) => write!(f, "text truncated at byte {}", offset),
+            TextDecodeError::DeserializeError(err) => err.fmt(f),
+        }
+    }
+}
+
+impl Error for TextDecodeError {}
+
+// Documents found in logs, REST payloads or databases are often base64 encoded
+pub fn deserialize_base64(text: &str) -> Result<Value, TextDecodeError> {
+    let qbjs = decode_base64(text.as_bytes())?;
+    qbjs::deserialize_to_json(&qbjs).map_err(TextDecodeError::DeserializeError)
+}
+
+pub fn deserialize_hex(text: &str) -> Result<Value, TextDecodeError> {
+    let qbjs = decode_hex(text.as_bytes())?;
+    qbjs::deserialize_to_json(&qbjs).map_err(TextDecodeError::DeserializeError)
+}
+
+fn base64_value(byte: u8) -> Option<u8> {
+    match byte {
+        b'A'..=b'Z' => Some(byte - b'A'),
+        b'a'..=b'z' => Some(byte - b'a' + 26),
+        b'0'..=b'9' => Some(byte - b'0' + 52),
+        b'+' | b'-' => Some(62), // Standard and URL safe alphabets
+        b'/' | b'_' => Some(63),
+        _ => None,
+    }
+}
+
+// Accepts the standard and URL safe alphabets, with or without padding. Whitespace is
+// skipped since encoders often wrap lines.
+pub fn decode_base64(text: &[u8]) -> Result<Vec<u8>, TextDecodeError> {
+    let mut decoded = Vec::with_capacity(text.len() / 4 * 3);
+    let mut group = 0u32;
+    let mut group_length = 0;
+    let mut group_start = 0;
+    let mut padding = 0;
+
+    for (offset, byte) in text.iter().copied().enumerate() {
+        if byte.is_ascii_whitespace() {
+            continue;
+        }
+
+        if byte == b'=' {
+            // Padding completes a group of at least two characters
+            if group_length + padding < 2 || group_length + padding == 4 {
+                return Err(TextDecodeError::InvalidPadding(offset));
+            }
+            padding += 1;
+            continue;
+        }
+
+        let value = base64_value(byte).ok_or(TextDecodeError::InvalidCharacter(offset))?;
+        if padding > 0 {
+            return Err(TextDecodeError::InvalidPadding(offset));
+        }
+
+        if group_length == 0 {
+            group_start = offset;
+        }
+        group = (group << 6) | u32::from(value);
+        group_length += 1;
+        if group_length == 4 {
+            decoded.extend_from_slice(&group.to_be_bytes()[1..]);
+            group = 0;
+            group_length = 0;
+        }
+    }
+
+    // The padding, when there's some, has to complete the last group
+    if padding > 0 && group_length + padding != 4 {
+        return Err(TextDecodeError::Truncated(group_start));
+    }
+
+    // Remaining characters hold whole bytes followed by unused bits
+    match group_length {
+        0 => {}
+        1 => return Err(TextDecodeError::Truncated(group_start)),
+        _ => {
+            let bits = group_length * 6;
+            let group = group << (24 - bits);
+            decoded.extend_from_slice(&group.to_be_bytes()[1..(bits / 8 + 1)]);
+        }
+    }
+
+    Ok(decoded)
+}
+
+// Accepts hex digits of either case, optionally separated by whitespace
+pub fn decode_hex(text: &[u8]) -> Result<Vec<u8>, TextDecodeError> {
+    let mut decoded = Vec::with_capacity(text.len() / 2);
+    let mut high_digit = None;
+
+    for (offset, byte) in text.iter().copied().enumerate() {
+        if byte.is_ascii_whitespace() {
+            continue;
+        }
+
+        let digit = char::from(byte)
+            .to_digit(16)
+            .ok_or(TextDecodeError::InvalidCharacter(offset))? as u8;
+        match high_digit.take() {
+            Some((_, high)) => decoded.push((high << 4) | digit),
+            None => high_digit = Some((offset, digit)),
+        }
+    }
+
+    match high_digit {
+        Some((offset, _)) => Err(TextDecodeError::Truncated(offset)),
+        None => Ok(decoded),
+    }
+}
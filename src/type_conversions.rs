@@ -19,7 +19,7 @@ pub fn as_u64(data: &[u8]) -> u64 {
 // Converts the 27 most significant bits of the u32 to a signed integer over 27 bits
 // Used to read bit field of number headers
 pub fn as_i27(raw_value: u32) -> i32 {
-    let mask = u32::MAX & !0b11111;
+    let mask = !0b11111_u32;
     let bit_field = (raw_value & mask) >> 5;
     let sign_mask = 0b1 << 26;
     let is_negative_value = bit_field & sign_mask != 0;
@@ -34,6 +34,6 @@ pub fn as_i27(raw_value: u32) -> i32 {
 // Converts the 27 most significant bits of the u32 to an unsigned integer over 27 bits
 // Used to read bit field of number headers
 pub fn as_u27(raw_value: u32) -> u32 {
-    let mask = u32::MAX & !0b11111;
+    let mask = !0b11111_u32;
     (raw_value & mask) >> 5
 }
@@ -0,0 +1,125 @@
+use crate::analysis::{data, metadata};
+
+// Hooks called by `walk` for each node of the analysis tree, in document order.
+// Every hook does nothing by default, implementors only override the ones they need.
+pub trait Visitor {
+    type Error;
+
+    fn visit_null(&mut self, position: usize) -> Result<(), Self::Error> {
+        let _ = position;
+        Ok(())
+    }
+
+    fn visit_bool(&mut self, position: usize) -> Result<(), Self::Error> {
+        let _ = position;
+        Ok(())
+    }
+
+    fn visit_self_contained_number(&mut self, position: usize) -> Result<(), Self::Error> {
+        let _ = position;
+        Ok(())
+    }
+
+    fn visit_number(&mut self, bytefield: &data::ByteField) -> Result<(), Self::Error> {
+        let _ = bytefield;
+        Ok(())
+    }
+
+    fn visit_latin1_string(&mut self, bytefield: &data::ByteField) -> Result<(), Self::Error> {
+        let _ = bytefield;
+        Ok(())
+    }
+
+    fn visit_utf16_string(&mut self, bytefield: &data::ByteField) -> Result<(), Self::Error> {
+        let _ = bytefield;
+        Ok(())
+    }
+
+    fn enter_array(&mut self, array: &data::Array) -> Result<(), Self::Error> {
+        let _ = array;
+        Ok(())
+    }
+
+    // Called before the value at `index`, `header_position` is its value header in the offset table
+    fn enter_array_value(
+        &mut self,
+        index: usize,
+        header_position: usize,
+    ) -> Result<(), Self::Error> {
+        let _ = (index, header_position);
+        Ok(())
+    }
+
+    fn leave_array_value(&mut self, index: usize) -> Result<(), Self::Error> {
+        let _ = index;
+        Ok(())
+    }
+
+    fn leave_array(&mut self, array: &data::Array) -> Result<(), Self::Error> {
+        let _ = array;
+        Ok(())
+    }
+
+    fn enter_object(&mut self, object: &data::Object) -> Result<(), Self::Error> {
+        let _ = object;
+        Ok(())
+    }
+
+    // Called before the value of the entry at `index`, the entry holds the key
+    fn enter_entry(&mut self, index: usize, entry: &data::Entry) -> Result<(), Self::Error> {
+        let _ = (index, entry);
+        Ok(())
+    }
+
+    fn leave_entry(&mut self, index: usize, entry: &data::Entry) -> Result<(), Self::Error> {
+        let _ = (index, entry);
+        Ok(())
+    }
+
+    fn leave_object(&mut self, object: &data::Object) -> Result<(), Self::Error> {
+        let _ = object;
+        Ok(())
+    }
+}
+
+// Walks the analysis tree depth first, stopping at the first error returned by a hook
+pub fn walk<V: Visitor>(value: &data::Value, visitor: &mut V) -> Result<(), V::Error> {
+    match value {
+        data::Value::Null(position) => visitor.visit_null(*position),
+        data::Value::Bool(position) => visitor.visit_bool(*position),
+        data::Value::SelfContainedNumber(position) => {
+            visitor.visit_self_contained_number(*position)
+        }
+        data::Value::Number(bytefield) => visitor.visit_number(bytefield),
+        data::Value::Latin1String(bytefield) => visitor.visit_latin1_string(bytefield),
+        data::Value::Utf16String(bytefield) => visitor.visit_utf16_string(bytefield),
+        data::Value::Array(array) => walk_array(array, visitor),
+        data::Value::Object(object) => walk_object(object, visitor),
+    }
+}
+
+fn walk_array<V: Visitor>(array: &data::Array, visitor: &mut V) -> Result<(), V::Error> {
+    visitor.enter_array(array)?;
+
+    for (index, value) in array.values.iter().enumerate() {
+        let header_position = array.table.range.start + index * metadata::VALUE_HEADER_BYTE_SIZE;
+
+        visitor.enter_array_value(index, header_position)?;
+        walk(value, visitor)?;
+        visitor.leave_array_value(index)?;
+    }
+
+    visitor.leave_array(array)
+}
+
+fn walk_object<V: Visitor>(object: &data::Object, visitor: &mut V) -> Result<(), V::Error> {
+    visitor.enter_object(object)?;
+
+    for (index, entry) in object.entries.iter().enumerate() {
+        visitor.enter_entry(index, entry)?;
+        walk(&entry.value, visitor)?;
+        visitor.leave_entry(index, entry)?;
+    }
+
+    visitor.leave_object(object)
+}
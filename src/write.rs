@@ -0,0 +1,418 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::analysis::{self, data, header, metadata};
+use crate::read::{self, ReadError};
+
+// Document tree keeping the storage of each value: the encoding of strings and keys and
+// whether numbers are stored in their header. Strings hold their stored code units so that
+// they are written back exactly as they were read.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    SelfContainedNumber(i32), // Signed integer over 27 bits
+    Number(f64),
+    Latin1String(Vec<u8>),
+    Utf16String(Vec<u16>),
+    Array(Vec<Value>),
+    Object(Vec<Entry>), // In the order of the offset table
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Key {
+    Latin1String(Vec<u8>),
+    Utf16String(Vec<u16>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub key: Key,
+    pub value: Value,
+}
+
+fn decode_latin1(string: &[u8]) -> String {
+    string.iter().map(|byte| char::from(*byte)).collect()
+}
+
+fn decode_utf16(string: &[u16]) -> Result<String, ReadError> {
+    String::from_utf16(string).map_err(|_| ReadError::FailedToDecodeUtf16String)
+}
+
+// Same as Qt's useCompressed: short strings only made of latin1 characters are stored as latin1
+fn latin1_code_units(string: &str) -> Option<Vec<u8>> {
+    if string.chars().count() >= 0x8000 {
+        return None;
+    }
+    string
+        .chars()
+        .map(|c| u8::try_from(u32::from(c)).ok())
+        .collect()
+}
+
+// Same as Qt's compressedNumber: integers whose exponent is between 0 and 25 are stored in
+// their header, which leaves out zero
+fn compressed_number(number: f64) -> Option<i32> {
+    let bits = number.to_bits();
+    let exponent = ((bits >> 52) & 0x7ff) as i32 - 1023;
+    if !(0..=25).contains(&exponent) {
+        return None;
+    }
+
+    let fraction = bits & 0x000f_ffff_ffff_ffff;
+    if fraction & (0x000f_ffff_ffff_ffff >> exponent) != 0 {
+        return None;
+    }
+
+    let magnitude = ((fraction | (1 << 52)) >> (52 - exponent)) as i32;
+    Some(if bits >> 63 != 0 {
+        -magnitude
+    } else {
+        magnitude
+    })
+}
+
+impl Key {
+    // Stores the key the same way Qt does
+    pub fn new(key: &str) -> Key {
+        match latin1_code_units(key) {
+            Some(key) => Key::Latin1String(key),
+            None => Key::Utf16String(key.encode_utf16().collect()),
+        }
+    }
+
+    pub fn decode(&self) -> Result<String, ReadError> {
+        match self {
+            Key::Latin1String(key) => Ok(decode_latin1(key)),
+            Key::Utf16String(key) => decode_utf16(key),
+        }
+    }
+
+    // Qt sorts the keys of objects by their UTF-16 code units to look them up by bisection
+    pub(crate) fn code_units(&self) -> Vec<u16> {
+        match self {
+            Key::Latin1String(key) => key.iter().map(|byte| u16::from(*byte)).collect(),
+            Key::Utf16String(key) => key.clone(),
+        }
+    }
+}
+
+impl Value {
+    // Qt stores every number as a double, which may then fit in its header
+    pub fn number(number: f64) -> Value {
+        match compressed_number(number) {
+            Some(number) => Value::SelfContainedNumber(number),
+            None => Value::Number(number),
+        }
+    }
+
+    pub fn string(string: &str) -> Value {
+        match latin1_code_units(string) {
+            Some(string) => Value::Latin1String(string),
+            None => Value::Utf16String(string.encode_utf16().collect()),
+        }
+    }
+
+    // Stores the value the same way Qt does, with the keys of objects sorted
+    pub fn from_json(value: &serde_json::Value) -> Value {
+        match value {
+            serde_json::Value::Null => Value::Null,
+            serde_json::Value::Bool(value) => Value::Bool(*value),
+            serde_json::Value::Number(number) => Value::number(number.as_f64().unwrap_or(f64::NAN)),
+            serde_json::Value::String(string) => Value::string(string),
+            serde_json::Value::Array(values) => {
+                Value::Array(values.iter().map(Value::from_json).collect())
+            }
+            serde_json::Value::Object(entries) => {
+                let mut entries = entries
+                    .iter()
+                    .map(|(key, value)| Entry {
+                        key: Key::new(key),
+                        value: Value::from_json(value),
+                    })
+                    .collect::<Vec<_>>();
+                entries.sort_by_cached_key(|entry| entry.key.code_units());
+                Value::Object(entries)
+            }
+        }
+    }
+
+    // Same decoding as read_value
+    pub fn to_json(&self) -> Result<serde_json::Value, ReadError> {
+        match self {
+            Value::Null => Ok(serde_json::Value::Null),
+            Value::Bool(value) => Ok(serde_json::Value::Bool(*value)),
+            Value::SelfContainedNumber(number) => Ok(serde_json::Value::from(*number)),
+            Value::Number(number) => serde_json::Number::from_f64(*number)
+                .map(serde_json::Value::Number)
+                .ok_or(ReadError::FailedToDecodeNumber),
+            Value::Latin1String(string) => Ok(serde_json::Value::String(decode_latin1(string))),
+            Value::Utf16String(string) => decode_utf16(string).map(serde_json::Value::String),
+            Value::Array(values) => values
+                .iter()
+                .map(Value::to_json)
+                .collect::<Result<_, _>>()
+                .map(serde_json::Value::Array),
+            Value::Object(entries) => entries
+                .iter()
+                .map(|entry| Ok((entry.key.decode()?, entry.value.to_json()?)))
+                .collect::<Result<_, _>>()
+                .map(serde_json::Value::Object),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum EncodeError {
+    InvalidRootContainer,
+    ContainerTooLarge, // Offsets are stored over 27 bits
+    StringTooLong,     // Latin1 sizes are stored over 16 bits
+    NumberOutOfRange,  // Self contained numbers are stored over 27 bits
+}
+
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            EncodeError::InvalidRootContainer => "the root value isn't an array or an object",
+            EncodeError::ContainerTooLarge => "a container is too large for 27 bit offsets",
+            EncodeError::StringTooLong => "a latin1 string is longer than 65535 bytes",
+            EncodeError::NumberOutOfRange => "a self contained number doesn't fit in 27 bits",
+        })
+    }
+}
+
+impl Error for EncodeError {}
+
+// -(1 << 26) has no representation, Qt reads its bits back as 0
+const SELF_CONTAINED_NUMBER_RANGE: std::ops::Range<i32> = -(1 << 26) + 1..(1 << 26);
+pub(crate) const MAX_OFFSET: usize = (1 << 27) - 1;
+
+fn latin1_data(data: &[u8], bytefield: &data::ByteField) -> Result<Vec<u8>, ReadError> {
+    read::latin1_string_data(data, bytefield).map(<[u8]>::to_vec)
+}
+
+fn utf16_data(data: &[u8], bytefield: &data::ByteField) -> Result<Vec<u16>, ReadError> {
+    let string_data = read::utf16_string_data(data, bytefield)?;
+    Ok(string_data
+        .chunks_exact(metadata::UTF16_CHAR_LENGTH)
+        .map(|code_unit| u16::from_le_bytes([code_unit[0], code_unit[1]]))
+        .collect())
+}
+
+// Copies the analyzed values, keeping their storage
+pub fn value_from_analysis(data: &[u8], value: &data::Value) -> Result<Value, ReadError> {
+    match value {
+        data::Value::Null(_) => Ok(Value::Null),
+        data::Value::Bool(position) => read::decode_bool(data, *position).map(Value::Bool),
+        data::Value::SelfContainedNumber(position) => {
+            read::decode_self_contained_number(data, *position).map(Value::SelfContainedNumber)
+        }
+        data::Value::Number(bytefield) => read::decode_number(data, bytefield).map(Value::Number),
+        data::Value::Latin1String(bytefield) => {
+            latin1_data(data, bytefield).map(Value::Latin1String)
+        }
+        data::Value::Utf16String(bytefield) => utf16_data(data, bytefield).map(Value::Utf16String),
+        data::Value::Array(array) => array
+            .values
+            .iter()
+            .map(|value| value_from_analysis(data, value))
+            .collect::<Result<_, _>>()
+            .map(Value::Array),
+        data::Value::Object(object) => object
+            .entries
+            .iter()
+            .map(|entry| {
+                let key = match &entry.key {
+                    data::Key::Latin1String(bytefield) => {
+                        Key::Latin1String(latin1_data(data, bytefield)?)
+                    }
+                    data::Key::Utf16String(bytefield) => {
+                        Key::Utf16String(utf16_data(data, bytefield)?)
+                    }
+                };
+                let value = value_from_analysis(data, &entry.value)?;
+                Ok(Entry { key, value })
+            })
+            .collect::<Result<_, _>>()
+            .map(Value::Object),
+    }
+}
+
+// Encodes the document with every container tightly packed, the same way Qt does:
+// the values of a container follow its base in the order of its table, which ends it.
+pub fn encode_document(root: &Value) -> Result<Vec<u8>, EncodeError> {
+    match root {
+        Value::Array(_) | Value::Object(_) => {}
+        _ => return Err(EncodeError::InvalidRootContainer),
+    }
+
+    let mut document = Vec::new();
+    document.extend_from_slice(b"qbjs");
+    document.extend_from_slice(&1_u32.to_le_bytes());
+    debug_assert_eq!(document.len(), header::HEADER_LENGTH);
+
+    encode_container(root, &mut document)?;
+    Ok(document)
+}
+
+fn value_header(qt_value_type: u8, latin_or_int: bool, latin_key: bool, value: u32) -> u32 {
+    u32::from(qt_value_type)
+        | (u32::from(latin_or_int) << 3)
+        | (u32::from(latin_key) << 4)
+        | (value << 5)
+}
+
+fn push_u32(buffer: &mut Vec<u8>, value: u32) {
+    buffer.extend_from_slice(&value.to_le_bytes());
+}
+
+pub(crate) fn write_u32(buffer: &mut [u8], position: usize, value: u32) {
+    buffer[position..(position + 4)].copy_from_slice(&value.to_le_bytes());
+}
+
+fn offset(position: usize, base_start: usize) -> Result<u32, EncodeError> {
+    let offset = position - base_start;
+    if offset > MAX_OFFSET {
+        return Err(EncodeError::ContainerTooLarge);
+    }
+    Ok(offset as u32)
+}
+
+// Strings are filled with 0 to be aligned to 4 bytes
+fn pad(buffer: &mut Vec<u8>) {
+    while buffer.len() % 4 != 0 {
+        buffer.push(0);
+    }
+}
+
+fn push_latin1_string(buffer: &mut Vec<u8>, string: &[u8]) -> Result<(), EncodeError> {
+    let length = u16::try_from(string.len()).map_err(|_| EncodeError::StringTooLong)?;
+    buffer.extend_from_slice(&length.to_le_bytes());
+    buffer.extend_from_slice(string);
+    pad(buffer);
+    Ok(())
+}
+
+fn push_utf16_string(buffer: &mut Vec<u8>, string: &[u16]) -> Result<(), EncodeError> {
+    let length = u32::try_from(string.len()).map_err(|_| EncodeError::StringTooLong)?;
+    push_u32(buffer, length);
+    for code_unit in string {
+        buffer.extend_from_slice(&code_unit.to_le_bytes());
+    }
+    pad(buffer);
+    Ok(())
+}
+
+// Writes the data of the value that isn't stored in its header, if any,
+// and returns the header with the given key flag
+fn encode_value(
+    value: &Value,
+    latin_key: bool,
+    base_start: usize,
+    buffer: &mut Vec<u8>,
+) -> Result<u32, EncodeError> {
+    let data_offset = offset(buffer.len(), base_start)?;
+
+    let header = match value {
+        Value::Null => value_header(analysis::QT_NULL_VALUE, false, latin_key, 0),
+        Value::Bool(value) => {
+            value_header(analysis::QT_BOOL_VALUE, false, latin_key, u32::from(*value))
+        }
+        Value::SelfContainedNumber(number) => {
+            if !SELF_CONTAINED_NUMBER_RANGE.contains(number) {
+                return Err(EncodeError::NumberOutOfRange);
+            }
+            let bits = (*number as u32) & ((1 << 27) - 1);
+            value_header(analysis::QT_NUMBER_VALUE, true, latin_key, bits)
+        }
+        Value::Number(number) => {
+            buffer.extend_from_slice(&number.to_bits().to_le_bytes());
+            value_header(analysis::QT_NUMBER_VALUE, false, latin_key, data_offset)
+        }
+        Value::Latin1String(string) => {
+            push_latin1_string(buffer, string)?;
+            value_header(analysis::QT_STRING_VALUE, true, latin_key, data_offset)
+        }
+        Value::Utf16String(string) => {
+            push_utf16_string(buffer, string)?;
+            value_header(analysis::QT_STRING_VALUE, false, latin_key, data_offset)
+        }
+        Value::Array(_) => {
+            encode_container(value, buffer)?;
+            value_header(analysis::QT_ARRAY_VALUE, false, latin_key, data_offset)
+        }
+        Value::Object(_) => {
+            encode_container(value, buffer)?;
+            value_header(analysis::QT_OBJECT_VALUE, false, latin_key, data_offset)
+        }
+    };
+
+    Ok(header)
+}
+
+// Encodes the value on its own: its header refers to its data, if any, at offset 0
+pub(crate) fn encode_detached_value(
+    value: &Value,
+    latin_key: bool,
+) -> Result<(u32, Vec<u8>), EncodeError> {
+    let mut buffer = Vec::new();
+    let header = encode_value(value, latin_key, 0, &mut buffer)?;
+    Ok((header, buffer))
+}
+
+fn encode_container(container: &Value, buffer: &mut Vec<u8>) -> Result<(), EncodeError> {
+    let base_start = buffer.len();
+    buffer.resize(base_start + metadata::CONTAINER_BASE_LENGTH, 0);
+
+    // Arrays' tables hold the value headers, objects' tables the offsets of the entries
+    let (is_object, table) = match container {
+        Value::Array(values) => {
+            let mut headers = Vec::with_capacity(values.len());
+            for value in values {
+                headers.push(encode_value(value, false, base_start, buffer)?);
+            }
+            (false, headers)
+        }
+        Value::Object(entries) => {
+            let mut entry_offsets = Vec::with_capacity(entries.len());
+            for entry in entries {
+                let entry_start = buffer.len();
+                entry_offsets.push(offset(entry_start, base_start)?);
+
+                push_u32(buffer, 0); // Header, known once the value is written
+                let latin_key = match &entry.key {
+                    Key::Latin1String(key) => {
+                        push_latin1_string(buffer, key)?;
+                        true
+                    }
+                    Key::Utf16String(key) => {
+                        push_utf16_string(buffer, key)?;
+                        false
+                    }
+                };
+                let header = encode_value(&entry.value, latin_key, base_start, buffer)?;
+                write_u32(buffer, entry_start, header);
+            }
+            (true, entry_offsets)
+        }
+        _ => unreachable!("only containers have a base"),
+    };
+
+    // Same as Qt, empty containers have no table
+    let table_offset = if table.is_empty() {
+        0
+    } else {
+        offset(buffer.len(), base_start)?
+    };
+    for table_entry in &table {
+        push_u32(buffer, *table_entry);
+    }
+
+    let size =
+        u32::try_from(buffer.len() - base_start).map_err(|_| EncodeError::ContainerTooLarge)?;
+    let length = u32::try_from(table.len()).map_err(|_| EncodeError::ContainerTooLarge)?;
+    write_u32(buffer, base_start, size);
+    write_u32(buffer, base_start + 4, (length << 1) | u32::from(is_object));
+    write_u32(buffer, base_start + 8, table_offset);
+    Ok(())
+}
@@ -0,0 +1,181 @@
+mod common;
+
+use common::read_qbjs_test_file;
+use qbjs_deserializer::analysis::analyze_document;
+use qbjs_deserializer::read::{self, ReadError, ValueBuilder};
+
+// Flattens a document to "path = value" lines
+#[derive(Default)]
+struct FlatteningBuilder {
+    path: Vec<String>,
+    next_index: Vec<Option<usize>>, // None for objects
+    lines: Vec<String>,
+}
+
+impl FlatteningBuilder {
+    fn push_line(&mut self, value: String) -> Result<(), ReadError> {
+        if let Some(Some(index)) = self.next_index.last_mut() {
+            self.path.push(index.to_string());
+            *index += 1;
+        }
+        self.lines
+            .push(format!("/{} = {}", self.path.join("/"), value));
+        self.path.pop();
+        Ok(())
+    }
+
+    fn begin_container(&mut self, index: Option<usize>) -> Result<(), ReadError> {
+        if let Some(Some(parent_index)) = self.next_index.last_mut() {
+            self.path.push(parent_index.to_string());
+            *parent_index += 1;
+        }
+        self.next_index.push(index);
+        Ok(())
+    }
+
+    fn end_container(&mut self) -> Result<(), ReadError> {
+        self.next_index.pop();
+        self.path.pop();
+        Ok(())
+    }
+}
+
+impl ValueBuilder for FlatteningBuilder {
+    type Error = ReadError;
+
+    fn null(&mut self) -> Result<(), ReadError> {
+        self.push_line("null".to_owned())
+    }
+
+    fn bool(&mut self, value: bool) -> Result<(), ReadError> {
+        self.push_line(value.to_string())
+    }
+
+    fn int(&mut self, value: i32) -> Result<(), ReadError> {
+        self.push_line(format!("{}i", value))
+    }
+
+    fn double(&mut self, value: f64) -> Result<(), ReadError> {
+        self.push_line(format!("{}d", value))
+    }
+
+    fn string(&mut self, value: &str) -> Result<(), ReadError> {
+        self.push_line(format!("{:?}", value))
+    }
+
+    fn begin_array(&mut self, _length: usize) -> Result<(), ReadError> {
+        self.begin_container(Some(0))
+    }
+
+    fn end_array(&mut self) -> Result<(), ReadError> {
+        self.end_container()
+    }
+
+    fn begin_object(&mut self, _length: usize) -> Result<(), ReadError> {
+        self.begin_container(None)
+    }
+
+    fn key(&mut self, key: &str) -> Result<(), ReadError> {
+        self.path.push(key.to_owned());
+        Ok(())
+    }
+
+    fn end_object(&mut self) -> Result<(), ReadError> {
+        self.end_container()
+    }
+}
+
+#[test]
+fn custom_builder_receives_every_value() {
+    let qbjs_content = read_qbjs_test_file("201_array_object_document");
+    let document = analyze_document(&qbjs_content).unwrap();
+
+    let mut builder = FlatteningBuilder::default();
+    read::build_value(&qbjs_content, &document, &mut builder).unwrap();
+
+    let expected_lines = vec![
+        "/an array/0 = \"value 1\"".to_owned(),
+        "/an array/1 = \"value 2\"".to_owned(),
+    ];
+
+    assert_eq!(builder.lines, expected_lines);
+}
+
+#[derive(Debug, PartialEq)]
+enum TomlLikeError {
+    NullUnsupported,
+    Read(ReadError),
+}
+
+impl From<ReadError> for TomlLikeError {
+    fn from(err: ReadError) -> Self {
+        TomlLikeError::Read(err)
+    }
+}
+
+// Builder for a format without null, only counting the values it accepts
+#[derive(Default)]
+struct NoNullBuilder {
+    values: usize,
+}
+
+impl ValueBuilder for NoNullBuilder {
+    type Error = TomlLikeError;
+
+    fn null(&mut self) -> Result<(), TomlLikeError> {
+        Err(TomlLikeError::NullUnsupported)
+    }
+
+    fn bool(&mut self, _value: bool) -> Result<(), TomlLikeError> {
+        self.values += 1;
+        Ok(())
+    }
+
+    fn int(&mut self, _value: i32) -> Result<(), TomlLikeError> {
+        self.values += 1;
+        Ok(())
+    }
+
+    fn double(&mut self, _value: f64) -> Result<(), TomlLikeError> {
+        self.values += 1;
+        Ok(())
+    }
+
+    fn string(&mut self, _value: &str) -> Result<(), TomlLikeError> {
+        self.values += 1;
+        Ok(())
+    }
+
+    fn begin_array(&mut self, _length: usize) -> Result<(), TomlLikeError> {
+        Ok(())
+    }
+
+    fn end_array(&mut self) -> Result<(), TomlLikeError> {
+        Ok(())
+    }
+
+    fn begin_object(&mut self, _length: usize) -> Result<(), TomlLikeError> {
+        Ok(())
+    }
+
+    fn key(&mut self, _key: &str) -> Result<(), TomlLikeError> {
+        Ok(())
+    }
+
+    fn end_object(&mut self) -> Result<(), TomlLikeError> {
+        Ok(())
+    }
+}
+
+#[test]
+fn builder_errors_stop_the_build() {
+    let qbjs_content = read_qbjs_test_file("105_various_values_array_document");
+    let document = analyze_document(&qbjs_content).unwrap();
+
+    let mut builder = NoNullBuilder::default();
+
+    assert_eq!(
+        read::build_value(&qbjs_content, &document, &mut builder),
+        Err(TomlLikeError::NullUnsupported)
+    );
+}
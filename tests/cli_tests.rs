@@ -0,0 +1,164 @@
+use std::process::{Command, Output};
+
+const EXAMPLE_FILE: &str = "tests/test_data/qbjs_data/400_example_from_qbjs_source_document.qbjs";
+
+fn qbjs(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_qbjs"))
+        .args(args)
+        .output()
+        .expect("Couldn't run qbjs")
+}
+
+#[test]
+fn cli_query() {
+    let output = qbjs(&[
+        "query",
+        EXAMPLE_FILE,
+        ".phoneNumber[] | select(.type == \"fax\")",
+    ]);
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "{\n  \"number\": \"646 555-4567\",\n  \"type\": \"fax\"\n}\n"
+    );
+}
+
+#[test]
+fn cli_query_compact() {
+    let output = qbjs(&["query", "--compact", EXAMPLE_FILE, ".phoneNumber[].type"]);
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "\"home\"\n\"fax\"\n"
+    );
+}
+
+#[test]
+fn cli_query_errors() {
+    let output = qbjs(&["query", EXAMPLE_FILE]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .starts_with("usage:"));
+
+    let output = qbjs(&["query", EXAMPLE_FILE, ".age[]"]);
+    assert!(!output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "cannot iterate over number\n"
+    );
+
+    let output = qbjs(&["query", EXAMPLE_FILE, ".age |"]);
+    assert!(!output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "invalid expression at byte 6\n"
+    );
+
+    let output = qbjs(&["query", "missing.qbjs", "."]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn cli_explain() {
+    let output = qbjs(&["explain", EXAMPLE_FILE]);
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("00000000  71 62 6a 73"));
+    assert!(stdout.contains("object base: size"));
+
+    let output = qbjs(&[
+        "explain",
+        "tests/test_data/qbjs_data/302_invalid_qbjs_tag_document.qbjs",
+    ]);
+    assert!(!output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("00000000  61 62 63 64"));
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "invalid qbjs document: the header doesn't start with \"qbjs\"\n"
+    );
+}
+
+#[test]
+fn cli_stats() {
+    let output = qbjs(&["stats", EXAMPLE_FILE]);
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("    latin1 strings          10\n"));
+    assert!(stdout.contains("max depth: 3\n"));
+    assert!(stdout.contains("    /phoneNumber            array of 2 values, 156 bytes\n"));
+    assert!(stdout.contains("    wasted                  0\n"));
+}
+
+#[test]
+fn cli_diff() {
+    let output = qbjs(&[
+        "diff",
+        "--compact",
+        "tests/test_data/qbjs_data/001_bool_true_object_document.qbjs",
+        "tests/test_data/qbjs_data/002_bool_false_object_document.qbjs",
+    ]);
+
+    assert!(output.status.success());
+    let operations: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(operations.is_array());
+    assert!(!operations.as_array().unwrap().is_empty());
+
+    let output = qbjs(&["diff", EXAMPLE_FILE, EXAMPLE_FILE]);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "[]\n");
+}
+
+#[test]
+fn cli_scan() {
+    let output = qbjs(&["scan", "--compact", "tests/test_data/plugins/rodata.so"]);
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let found = stdout
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0]["document"]["className"], "QXcbIntegrationPlugin");
+}
+
+#[test]
+fn cli_text_input() {
+    let base64_file = "tests/test_data/text/400_example_from_qbjs_source_document.b64";
+    let hex_file = "tests/test_data/text/400_example_from_qbjs_source_document.hex";
+    let expected = qbjs(&["query", "--compact", EXAMPLE_FILE, ".address.city"]);
+
+    let output = qbjs(&[
+        "query",
+        "--compact",
+        "--base64",
+        base64_file,
+        ".address.city",
+    ]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, expected.stdout);
+
+    let output = qbjs(&["query", "--compact", "--hex", hex_file, ".address.city"]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, expected.stdout);
+
+    // The binary file isn't hex text
+    let output = qbjs(&["stats", "--hex", EXAMPLE_FILE]);
+    assert!(!output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        format!(
+            "couldn't decode {}: invalid character at byte 0\n",
+            EXAMPLE_FILE
+        )
+    );
+
+    let output = qbjs(&["stats", "--base64", "--hex", hex_file]);
+    assert!(!output.status.success());
+}
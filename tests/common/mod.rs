@@ -0,0 +1,13 @@
+#![allow(dead_code)] // Each test file uses its own subset of the helpers
+
+use std::fs;
+
+// Reads a file of tests/test_data
+pub fn read_test_file(file_path: &str) -> Vec<u8> {
+    let file_path = format!("tests/test_data/{}", file_path);
+    fs::read(&file_path).unwrap_or_else(|_| panic!("Couldn't read file: {}", file_path))
+}
+
+pub fn read_qbjs_test_file(file_name: &str) -> Vec<u8> {
+    read_test_file(&format!("qbjs_data/{}.qbjs", file_name))
+}
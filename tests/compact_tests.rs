@@ -0,0 +1,161 @@
+mod common;
+
+use common::read_qbjs_test_file;
+use qbjs_deserializer::qbjs::{self, write};
+
+macro_rules! create_test {
+    ($test_name: ident) => {
+        #[test]
+        #[allow(non_snake_case)]
+        fn $test_name() {
+            let file_name = &stringify!($test_name)[1..];
+            let qbjs_content = read_qbjs_test_file(file_name);
+
+            // Documents written by Qt are already compact
+            assert_eq!(qbjs::compact(&qbjs_content).unwrap(), qbjs_content);
+        }
+    };
+}
+
+macro_rules! create_tests {
+    ($($test_name: ident),*) => {
+        $(
+            create_test!($test_name);
+        )*
+    };
+}
+
+create_tests!(
+    _00_null_document,
+    _000_null_object_document,
+    _001_bool_true_object_document,
+    _002_bool_false_object_document,
+    _003_double_object_document,
+    _004_double_zero_object_document,
+    _005_negative_double_object_document,
+    _006_int_object_document,
+    _007_int_zero_object_document,
+    _008_negative_int_object_document,
+    _009_string_object_document,
+    _010_strings_object_document,
+    _011_japanese_string_object_document,
+    _012_various_values_object_document,
+    _100_null_array_document,
+    _101_bool_array_document,
+    _102_double_array_document,
+    _103_int_array_document,
+    _104_string_array_document,
+    _105_various_values_array_document,
+    _200_object_object_document,
+    _201_array_object_document,
+    _202_tree_object_document,
+    _203_tree_array_document,
+    _204_array_in_array_document,
+    _205_tree_array_in_array_document,
+    _206_objects_in_array_document,
+    _207_tree_empty_arrays_in_object_document,
+    _208_tree_empty_objects_in_object_document,
+    _400_example_from_qbjs_source_document
+);
+
+#[test]
+fn compact_removes_unreferenced_bytes() {
+    let mut qbjs_content = read_qbjs_test_file("206_objects_in_array_document");
+    // The first object loses its second entry
+    qbjs_content[0x18] = (1 << 1) | 1;
+    // Trailing bytes after the root
+    qbjs_content.extend_from_slice(&[0xff; 8]);
+
+    let compacted = qbjs::compact(&qbjs_content).unwrap();
+
+    assert_eq!(compacted.len(), qbjs_content.len() - 8 - 0x1c);
+    assert_eq!(
+        qbjs::deserialize_to_json(&compacted).unwrap(),
+        qbjs::deserialize_to_json(&qbjs_content).unwrap()
+    );
+    let report = qbjs::analyze_slack(&compacted).unwrap();
+    assert!(report.gaps.is_empty());
+}
+
+#[test]
+fn compact_keeps_storage() {
+    let root = write::Value::Object(vec![
+        write::Entry {
+            key: write::Key::Utf16String("double".encode_utf16().collect()),
+            value: write::Value::Number(1.0),
+        },
+        write::Entry {
+            key: write::Key::Latin1String(b"int".to_vec()),
+            value: write::Value::SelfContainedNumber(-1),
+        },
+        write::Entry {
+            key: write::Key::Latin1String(b"string".to_vec()),
+            value: write::Value::Utf16String("abc".encode_utf16().collect()),
+        },
+        // Unpaired surrogates can't be decoded but are kept
+        write::Entry {
+            key: write::Key::Latin1String(b"surrogate".to_vec()),
+            value: write::Value::Utf16String(vec![0xd800]),
+        },
+    ]);
+    let qbjs_content = write::encode_document(&root).unwrap();
+
+    let compacted = qbjs::compact(&qbjs_content).unwrap();
+    assert_eq!(compacted, qbjs_content);
+
+    let document = qbjs::analyze_document(&compacted).unwrap();
+    assert_eq!(
+        write::value_from_analysis(&compacted, &document).unwrap(),
+        root
+    );
+}
+
+#[test]
+fn compact_empty_document() {
+    assert_eq!(qbjs::compact(&[]).unwrap(), Vec::<u8>::new());
+}
+
+#[test]
+fn encode_invalid_values() {
+    assert_eq!(
+        write::encode_document(&write::Value::Null),
+        Err(write::EncodeError::InvalidRootContainer)
+    );
+    assert_eq!(
+        write::encode_document(&write::Value::Array(vec![
+            write::Value::SelfContainedNumber(1 << 26)
+        ])),
+        Err(write::EncodeError::NumberOutOfRange)
+    );
+    assert_eq!(
+        write::encode_document(&write::Value::Array(vec![
+            write::Value::SelfContainedNumber(-(1 << 26))
+        ])),
+        Err(write::EncodeError::NumberOutOfRange)
+    );
+    assert_eq!(
+        write::encode_document(&write::Value::Array(vec![write::Value::Latin1String(
+            vec![b'a'; 1 << 16]
+        )])),
+        Err(write::EncodeError::StringTooLong)
+    );
+}
+
+#[test]
+fn encode_self_contained_number_bounds() {
+    let root = write::Value::Array(vec![
+        write::Value::SelfContainedNumber(-(1 << 26) + 1),
+        write::Value::SelfContainedNumber((1 << 26) - 1),
+    ]);
+    let qbjs_content = write::encode_document(&root).unwrap();
+
+    let document = qbjs::analyze_document(&qbjs_content).unwrap();
+    assert_eq!(
+        write::value_from_analysis(&qbjs_content, &document).unwrap(),
+        root
+    );
+    assert_eq!(
+        qbjs::deserialize_to_json(&qbjs_content).unwrap(),
+        serde_json::json!([-67108863, 67108863])
+    );
+}
@@ -0,0 +1,105 @@
+#![cfg(feature = "compression")]
+
+mod common;
+
+use common::{read_qbjs_test_file, read_test_file};
+use qbjs_deserializer::qbjs::{self, compression, Compression, CompressionError};
+
+use std::borrow::Cow;
+use std::io::Write;
+
+use flate2::write::ZlibEncoder;
+
+const EXAMPLE: &str = "400_example_from_qbjs_source_document";
+
+#[test]
+fn decompress_formats() {
+    let document = read_qbjs_test_file(EXAMPLE);
+
+    for (extension, expected_compression) in [
+        ("qcompress", Compression::QCompress),
+        ("zlib", Compression::Zlib),
+        ("gz", Compression::Gzip),
+    ] {
+        let compressed = read_test_file(&format!("compressed/{}.{}", EXAMPLE, extension));
+        assert_eq!(
+            compression::detect_compression(&compressed),
+            Some(expected_compression)
+        );
+
+        let decompressed = qbjs::decompress(&compressed, compression::DEFAULT_SIZE_LIMIT).unwrap();
+        assert_eq!(decompressed, document);
+
+        let (data, analysis) =
+            qbjs::analyze_compressed_document(&compressed, compression::DEFAULT_SIZE_LIMIT)
+                .unwrap();
+        assert_eq!(data, document);
+        assert!(matches!(analysis, qbjs::data::Value::Object(_)));
+
+        assert_eq!(
+            qbjs::deserialize_compressed_to_json(&compressed, compression::DEFAULT_SIZE_LIMIT),
+            Ok(qbjs::deserialize_to_json(&document).unwrap())
+        );
+    }
+}
+
+#[test]
+fn uncompressed_documents_are_borrowed() {
+    let document = read_qbjs_test_file(EXAMPLE);
+    assert_eq!(
+        compression::detect_compression(&document),
+        Some(Compression::None)
+    );
+    assert!(matches!(
+        qbjs::decompress(&document, 0),
+        Ok(Cow::Borrowed(_))
+    ));
+}
+
+#[test]
+fn size_limit() {
+    let document = read_qbjs_test_file(EXAMPLE);
+    let compressed = read_test_file(&format!("compressed/{}.qcompress", EXAMPLE));
+
+    assert!(qbjs::decompress(&compressed, document.len()).is_ok());
+    assert_eq!(
+        qbjs::decompress(&compressed, document.len() - 1),
+        Err(CompressionError::SizeLimitExceeded(document.len() - 1))
+    );
+
+    // A few KiB of zlib data that would decompress to 16 MiB
+    let mut encoder = ZlibEncoder::new(Vec::new(), flate2::Compression::best());
+    let zeros = vec![0; 1 << 20];
+    for _ in 0..16 {
+        encoder.write_all(&zeros).unwrap();
+    }
+    let bomb = encoder.finish().unwrap();
+    assert_eq!(
+        qbjs::deserialize_compressed_to_json(&bomb, 1 << 20),
+        Err(CompressionError::SizeLimitExceeded(1 << 20))
+    );
+}
+
+#[test]
+fn invalid_data() {
+    assert_eq!(
+        qbjs::decompress(b"not a document", compression::DEFAULT_SIZE_LIMIT),
+        Err(CompressionError::UnknownFormat)
+    );
+
+    let mut compressed = read_test_file(&format!("compressed/{}.gz", EXAMPLE));
+    compressed.truncate(compressed.len() / 2);
+    assert_eq!(
+        qbjs::decompress(&compressed, compression::DEFAULT_SIZE_LIMIT),
+        Err(CompressionError::InvalidCompressedData(Compression::Gzip))
+    );
+
+    // Decompressed data that isn't a document
+    let mut encoder = ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(b"not a document").unwrap();
+    let compressed = encoder.finish().unwrap();
+    assert!(matches!(
+        qbjs::analyze_compressed_document(&compressed, compression::DEFAULT_SIZE_LIMIT),
+        Err(CompressionError::AnalysisError(_))
+    ));
+}
@@ -0,0 +1,87 @@
+#![cfg(feature = "config")]
+
+use config::{Config, ConfigError, File, Source};
+use qbjs_deserializer::qbjs::{QbjsFile, QbjsFormat};
+
+const EXAMPLE_FILE: &str = "tests/test_data/qbjs_data/400_example_from_qbjs_source_document.qbjs";
+
+#[test]
+fn config_qbjs_file() {
+    let settings = Config::builder()
+        .add_source(QbjsFile::new(EXAMPLE_FILE))
+        .build()
+        .unwrap();
+
+    assert_eq!(settings.get_string("address.city").unwrap(), "New York");
+    assert_eq!(settings.get_int("age").unwrap(), 25);
+    assert_eq!(
+        settings.get_string("phoneNumber[1].number").unwrap(),
+        "646 555-4567"
+    );
+
+    let settings = Config::builder()
+        .add_source(QbjsFile::new("missing.qbjs").required(false))
+        .build()
+        .unwrap();
+    assert!(settings.get_string("age").is_err());
+}
+
+#[test]
+fn config_qbjs_format() {
+    // Documents without bytes above 0x7F go through config's text conversion
+    let settings = Config::builder()
+        .add_source(File::new(
+            "tests/test_data/qbjs_data/208_tree_empty_objects_in_object_document.qbjs",
+            QbjsFormat,
+        ))
+        .build()
+        .unwrap();
+    let root_object = settings
+        .collect()
+        .unwrap()
+        .remove("root object")
+        .unwrap()
+        .into_table()
+        .unwrap();
+    assert!(root_object.contains_key("child object 1"));
+
+    let result = Config::builder()
+        .add_source(File::new(EXAMPLE_FILE, QbjsFormat))
+        .build();
+    assert!(matches!(result, Err(ConfigError::FileParse { .. })));
+}
+
+#[test]
+fn config_errors() {
+    let result = Config::builder()
+        .add_source(QbjsFile::new(
+            "tests/test_data/qbjs_data/302_invalid_qbjs_tag_document.qbjs",
+        ))
+        .build();
+    match result {
+        Err(ConfigError::FileParse { uri, cause }) => {
+            assert_eq!(
+                uri.as_deref(),
+                Some("tests/test_data/qbjs_data/302_invalid_qbjs_tag_document.qbjs")
+            );
+            assert_eq!(
+                cause.to_string(),
+                "invalid qbjs document: the header doesn't start with \"qbjs\""
+            );
+        }
+        result => panic!("unexpected result: {:?}", result),
+    }
+
+    // The root of the document has to be an object
+    let result = Config::builder()
+        .add_source(QbjsFile::new(
+            "tests/test_data/qbjs_data/100_null_array_document.qbjs",
+        ))
+        .build();
+    assert!(matches!(result, Err(ConfigError::FileParse { .. })));
+
+    let result = Config::builder()
+        .add_source(QbjsFile::new("missing.qbjs"))
+        .build();
+    assert!(matches!(result, Err(ConfigError::Foreign(_))));
+}
@@ -0,0 +1,68 @@
+mod common;
+
+use common::read_qbjs_test_file;
+use qbjs_deserializer::qbjs::{self, ByteArray, DataStreamError};
+
+// Same as QDataStream's operator<< for QByteArray
+fn write_byte_array(stream: &mut Vec<u8>, bytes: &[u8]) {
+    stream.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+    stream.extend_from_slice(bytes);
+}
+
+#[test]
+fn read_byte_array_records() {
+    let document = read_qbjs_test_file("400_example_from_qbjs_source_document");
+    let mut invalid_document = read_qbjs_test_file("001_bool_true_object_document");
+    invalid_document.truncate(20);
+
+    let mut stream = Vec::new();
+    write_byte_array(&mut stream, &document);
+    stream.extend_from_slice(&[0xff; 4]);
+    write_byte_array(&mut stream, &[]);
+    write_byte_array(&mut stream, b"not a document");
+    write_byte_array(&mut stream, &invalid_document);
+
+    let records = qbjs::read_byte_arrays(stream.as_slice()).collect::<Vec<_>>();
+
+    assert_eq!(
+        records,
+        vec![
+            Ok(ByteArray::Document(qbjs::deserialize_to_json(&document))),
+            Ok(ByteArray::Null),
+            Ok(ByteArray::Empty),
+            Ok(ByteArray::Data(b"not a document".to_vec())),
+            Ok(ByteArray::Document(qbjs::deserialize_to_json(
+                &invalid_document
+            ))),
+        ]
+    );
+    assert!(matches!(records[4], Ok(ByteArray::Document(Err(_)))));
+}
+
+#[test]
+fn read_truncated_records() {
+    let mut stream = Vec::new();
+    write_byte_array(&mut stream, b"data");
+    let record_start = stream.len();
+    write_byte_array(&mut stream, b"truncated data");
+    stream.truncate(stream.len() - 1);
+
+    let records = qbjs::read_byte_arrays(stream.as_slice()).collect::<Vec<_>>();
+    assert_eq!(
+        records,
+        vec![
+            Ok(ByteArray::Data(b"data".to_vec())),
+            Err(DataStreamError::TruncatedRecord(record_start)),
+        ]
+    );
+
+    // A corrupted length doesn't allocate the bytes it announces
+    let records =
+        qbjs::read_byte_arrays([0xff, 0xff, 0xff, 0xfe, 0x00].as_slice()).collect::<Vec<_>>();
+    assert_eq!(records, vec![Err(DataStreamError::TruncatedRecord(0))]);
+
+    let records = qbjs::read_byte_arrays([0x00, 0x00].as_slice()).collect::<Vec<_>>();
+    assert_eq!(records, vec![Err(DataStreamError::TruncatedRecord(0))]);
+
+    assert_eq!(qbjs::read_byte_arrays([].as_slice()).count(), 0);
+}
@@ -0,0 +1,176 @@
+mod common;
+
+use common::read_qbjs_test_file;
+use qbjs_deserializer::qbjs::{self, write, DiffOptions};
+
+use serde_json::json;
+
+fn latin1_key(key: &str) -> write::Key {
+    write::Key::Latin1String(key.as_bytes().to_vec())
+}
+
+fn utf16_key(key: &str) -> write::Key {
+    write::Key::Utf16String(key.encode_utf16().collect())
+}
+
+fn entry(key: write::Key, value: write::Value) -> write::Entry {
+    write::Entry { key, value }
+}
+
+fn document(entries: Vec<write::Entry>) -> Vec<u8> {
+    write::encode_document(&write::Value::Object(entries)).unwrap()
+}
+
+const STORAGE: DiffOptions = DiffOptions { storage: true };
+
+#[test]
+fn diff_identical_documents() {
+    let qbjs_content = read_qbjs_test_file("400_example_from_qbjs_source_document");
+
+    assert!(qbjs::diff(&qbjs_content, &qbjs_content, STORAGE)
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn diff_values() {
+    let a = document(vec![
+        entry(latin1_key("kept"), write::Value::Bool(true)),
+        entry(latin1_key("removed"), write::Value::Null),
+        entry(
+            latin1_key("values"),
+            write::Value::Array(vec![
+                write::Value::SelfContainedNumber(1),
+                write::Value::SelfContainedNumber(2),
+                write::Value::SelfContainedNumber(3),
+            ]),
+        ),
+    ]);
+    let b = document(vec![
+        entry(latin1_key("added"), write::Value::Array(vec![])),
+        entry(latin1_key("kept"), write::Value::Bool(false)),
+        entry(
+            latin1_key("values"),
+            write::Value::Array(vec![write::Value::SelfContainedNumber(4)]),
+        ),
+    ]);
+
+    assert_eq!(
+        qbjs::diff(&a, &b, DiffOptions::default()).unwrap(),
+        vec![
+            json!({"op": "remove", "path": "/removed"}),
+            json!({"op": "replace", "path": "/kept", "value": false}),
+            json!({"op": "replace", "path": "/values/0", "value": 4}),
+            json!({"op": "remove", "path": "/values/2"}),
+            json!({"op": "remove", "path": "/values/1"}),
+            json!({"op": "add", "path": "/added", "value": []}),
+        ]
+    );
+}
+
+#[test]
+fn diff_storage() {
+    let a = document(vec![
+        entry(latin1_key("int"), write::Value::SelfContainedNumber(1)),
+        entry(latin1_key("key"), write::Value::Null),
+        entry(
+            latin1_key("string"),
+            write::Value::Latin1String(b"abc".to_vec()),
+        ),
+    ]);
+    let b = document(vec![
+        entry(latin1_key("int"), write::Value::Number(1.0)),
+        entry(utf16_key("key"), write::Value::Null),
+        entry(
+            utf16_key("string"),
+            write::Value::Utf16String("abc".encode_utf16().collect()),
+        ),
+    ]);
+
+    assert!(qbjs::diff(&a, &b, DiffOptions::default())
+        .unwrap()
+        .is_empty());
+    assert_eq!(
+        qbjs::diff(&a, &b, STORAGE).unwrap(),
+        vec![
+            json!({
+                "op": "replace",
+                "path": "/int",
+                "value": 1.0,
+                "storage": {"value": {"from": "self-contained int", "to": "double"}}
+            }),
+            json!({
+                "op": "replace",
+                "path": "/key",
+                "value": null,
+                "storage": {"key": {"from": "latin1", "to": "utf16"}}
+            }),
+            json!({
+                "op": "replace",
+                "path": "/string",
+                "value": "abc",
+                "storage": {
+                    "value": {"from": "latin1", "to": "utf16"},
+                    "key": {"from": "latin1", "to": "utf16"}
+                }
+            }),
+        ]
+    );
+}
+
+#[test]
+fn diff_type_changes() {
+    let a = document(vec![entry(
+        latin1_key("value"),
+        write::Value::Array(vec![]),
+    )]);
+    let b = document(vec![entry(
+        latin1_key("value"),
+        write::Value::Object(vec![]),
+    )]);
+
+    assert_eq!(
+        qbjs::diff(&a, &b, STORAGE).unwrap(),
+        vec![json!({"op": "replace", "path": "/value", "value": {}})]
+    );
+    assert_eq!(
+        qbjs::diff(&[], &b, STORAGE).unwrap(),
+        vec![json!({"op": "add", "path": "/value", "value": {}})]
+    );
+}
+
+#[test]
+fn diff_escapes_paths() {
+    let a = document(vec![entry(latin1_key("a/b~c"), write::Value::Null)]);
+
+    assert_eq!(
+        qbjs::diff(&a, &[], STORAGE).unwrap(),
+        vec![json!({"op": "remove", "path": "/a~1b~0c"})]
+    );
+}
+
+#[test]
+fn diff_duplicated_keys() {
+    let a = document(vec![
+        entry(latin1_key("removed"), write::Value::Null),
+        entry(latin1_key("removed"), write::Value::Bool(true)),
+        entry(latin1_key("kept"), write::Value::Bool(true)),
+        entry(latin1_key("kept"), write::Value::Bool(true)),
+    ]);
+    let b = document(vec![
+        entry(latin1_key("kept"), write::Value::Bool(false)),
+        entry(latin1_key("kept"), write::Value::Null),
+        entry(latin1_key("added"), write::Value::Null),
+        entry(latin1_key("added"), write::Value::Bool(true)),
+    ]);
+
+    // Duplicated keys are matched by their first entry
+    assert_eq!(
+        qbjs::diff(&a, &b, DiffOptions::default()).unwrap(),
+        vec![
+            json!({"op": "remove", "path": "/removed"}),
+            json!({"op": "replace", "path": "/kept", "value": false}),
+            json!({"op": "add", "path": "/added", "value": null}),
+        ]
+    );
+}
@@ -0,0 +1,153 @@
+mod common;
+
+use common::read_qbjs_test_file;
+use qbjs_deserializer::qbjs::{self, EditError, QbjsDocumentMut};
+
+use serde_json::json;
+
+// Number of 4 bytes words that differ between two documents of the same size
+fn changed_words(a: &[u8], b: &[u8]) -> usize {
+    assert_eq!(a.len(), b.len());
+    a.chunks(4).zip(b.chunks(4)).filter(|(a, b)| a != b).count()
+}
+
+// The data of replaced values is left unreferenced and dropped by compact
+fn assert_compacts_to(document: &mut QbjsDocumentMut, expected: serde_json::Value) {
+    let json = qbjs::deserialize_to_json(document.data()).unwrap();
+    assert_eq!(json, expected);
+
+    document.compact().unwrap();
+    assert_eq!(
+        qbjs::deserialize_to_json(document.data()).unwrap(),
+        expected
+    );
+    assert!(qbjs::analyze_slack(document.data())
+        .unwrap()
+        .gaps
+        .is_empty());
+}
+
+#[test]
+fn set_in_headers() {
+    let qbjs_content = read_qbjs_test_file("012_various_values_object_document");
+    let mut document = QbjsDocumentMut::from_data(qbjs_content.clone()).unwrap();
+
+    document.set_bool("/bool value key 2", true).unwrap();
+    document
+        .set_number("/positive int value key", -7.0)
+        .unwrap();
+    document.set_null("/bool value key 1").unwrap();
+
+    // Only the value headers changed
+    assert_eq!(changed_words(&qbjs_content, document.data()), 3);
+    let mut expected = qbjs::deserialize_to_json(&qbjs_content).unwrap();
+    expected["bool value key 2"] = json!(true);
+    expected["positive int value key"] = json!(-7);
+    expected["bool value key 1"] = json!(null);
+    assert_eq!(
+        qbjs::deserialize_to_json(document.data()).unwrap(),
+        expected
+    );
+}
+
+#[test]
+fn set_over_old_data() {
+    let qbjs_content = read_qbjs_test_file("400_example_from_qbjs_source_document");
+    let mut document = QbjsDocumentMut::from_data(qbjs_content.clone()).unwrap();
+
+    document.set_string("/address/city", "Boston").unwrap();
+    document
+        .set_value("/phoneNumber/1", &json!({"type": "fax"}))
+        .unwrap();
+    assert_eq!(document.data().len(), qbjs_content.len());
+
+    let mut expected = qbjs::deserialize_to_json(&qbjs_content).unwrap();
+    expected["address"]["city"] = json!("Boston");
+    expected["phoneNumber"][1] = json!({"type": "fax"});
+    assert_compacts_to(&mut document, expected);
+}
+
+#[test]
+fn set_with_relinking() {
+    let qbjs_content = read_qbjs_test_file("400_example_from_qbjs_source_document");
+    let mut document = QbjsDocumentMut::from_data(qbjs_content.clone()).unwrap();
+
+    document.set_number("/age", 25.5).unwrap();
+    assert_eq!(document.data().len(), qbjs_content.len() + 8);
+    document
+        .set_string("/phoneNumber/0/number", "+1 212 555-1234 \u{260e}")
+        .unwrap();
+    document
+        .set_value("/address/state", &json!(["New York", "NY"]))
+        .unwrap();
+    // Doubles that are already stored are overwritten
+    let length = document.data().len();
+    document.set_number("/age", 0.0).unwrap();
+    assert_eq!(document.data().len(), length);
+
+    let mut expected = qbjs::deserialize_to_json(&qbjs_content).unwrap();
+    expected["age"] = json!(0.0);
+    expected["phoneNumber"][0]["number"] = json!("+1 212 555-1234 \u{260e}");
+    expected["address"]["state"] = json!(["New York", "NY"]);
+    assert_compacts_to(&mut document, expected);
+}
+
+#[test]
+fn set_in_arrays() {
+    let qbjs_content = read_qbjs_test_file("205_tree_array_in_array_document");
+    let mut document = QbjsDocumentMut::from_data(qbjs_content.clone()).unwrap();
+    let mut expected = qbjs::deserialize_to_json(&qbjs_content).unwrap();
+
+    document
+        .set_value("/0/0", &json!({"a": [1.5, "b"]}))
+        .unwrap();
+    document.set_value("/1", &json!("c")).unwrap();
+    expected[0][0] = json!({"a": [1.5, "b"]});
+    expected[1] = json!("c");
+
+    assert_compacts_to(&mut document, expected);
+}
+
+#[test]
+fn set_root() {
+    let mut document = QbjsDocumentMut::from_data(Vec::new()).unwrap();
+    assert_eq!(
+        qbjs::deserialize_to_json(document.data()).unwrap(),
+        json!({})
+    );
+
+    document.set_value("", &json!({"a": true})).unwrap();
+    document.set_bool("/a", false).unwrap();
+    assert_eq!(
+        qbjs::deserialize_to_json(document.data()).unwrap(),
+        json!({"a": false})
+    );
+}
+
+#[test]
+fn set_errors() {
+    let qbjs_content = read_qbjs_test_file("400_example_from_qbjs_source_document");
+    let mut document = QbjsDocumentMut::from_data(qbjs_content.clone()).unwrap();
+
+    assert_eq!(
+        document.set_bool("age", true),
+        Err(EditError::InvalidPointer("age".to_owned()))
+    );
+    assert_eq!(
+        document.set_bool("/phoneNumber/-", true),
+        Err(EditError::InvalidPointer("/phoneNumber/-".to_owned()))
+    );
+    assert_eq!(
+        document.set_bool("/phoneNumber/2", true),
+        Err(EditError::PathNotFound("/phoneNumber/2".to_owned()))
+    );
+    assert_eq!(
+        document.set_bool("/nickname", true),
+        Err(EditError::PathNotFound("/nickname".to_owned()))
+    );
+    assert_eq!(
+        document.set_bool("/age/0", true),
+        Err(EditError::PathNotFound("/age/0".to_owned()))
+    );
+    assert_eq!(document.data(), qbjs_content);
+}
@@ -0,0 +1,128 @@
+mod common;
+
+use common::read_qbjs_test_file;
+use qbjs_deserializer::qbjs::{self, Event, QbjsEvents};
+
+use serde_json::{Map, Value};
+
+// Rebuilds a JSON value from the events, consuming them up to the end of the value
+fn build_value<'a>(events: &mut impl Iterator<Item = Event<'a>>) -> Value {
+    match events.next().unwrap() {
+        Event::StartObject(length) => {
+            let mut object = Map::new();
+            for _ in 0..length {
+                let key = match events.next().unwrap() {
+                    Event::Key(key) => key.into_owned(),
+                    event => panic!("Expected a key, got {:?}", event),
+                };
+                object.insert(key, build_value(events));
+            }
+            assert_eq!(events.next(), Some(Event::End));
+            Value::Object(object)
+        }
+        Event::StartArray(length) => {
+            let values = (0..length).map(|_| build_value(events)).collect();
+            assert_eq!(events.next(), Some(Event::End));
+            Value::Array(values)
+        }
+        Event::Null => Value::Null,
+        Event::Bool(value) => Value::Bool(value),
+        Event::Int(value) => Value::from(value),
+        Event::Double(value) => Value::from(value),
+        Event::Str(value) => Value::String(value.into_owned()),
+        event => panic!("Unexpected event {:?}", event),
+    }
+}
+
+macro_rules! create_test {
+    // Rebuilds the document from the events and compares it to deserialize_to_json's output
+    ($test_name:ident) => {
+        #[test]
+        fn $test_name() {
+            let test_name = stringify!($test_name);
+            let file_name = &test_name[1..];
+
+            let qbjs_content = read_qbjs_test_file(file_name);
+            let mut events = QbjsEvents::new(&qbjs_content).map(|event| event.unwrap().1);
+
+            assert_eq!(
+                build_value(&mut events),
+                qbjs::deserialize_to_json(&qbjs_content).unwrap()
+            );
+            assert_eq!(events.next(), None);
+        }
+    };
+}
+
+macro_rules! create_tests {
+    ($test_name:ident) => {
+        create_test!($test_name);
+    };
+    ($test_name:ident, $($test_names:ident),+) => {
+        create_test!($test_name);
+        create_tests!($($test_names),+);
+    };
+}
+
+create_tests!(
+    _012_various_values_object_document,
+    _105_various_values_array_document,
+    _205_tree_array_in_array_document,
+    _207_tree_empty_arrays_in_object_document,
+    _300_empty_document,
+    _400_example_from_qbjs_source_document
+);
+
+#[test]
+fn events_positions() {
+    let qbjs_content = read_qbjs_test_file("206_objects_in_array_document");
+    let events = QbjsEvents::new(&qbjs_content)
+        .take(4)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    assert_eq!(
+        events,
+        vec![
+            (0x08, Event::StartArray(2)),
+            (0x14, Event::StartObject(2)),
+            (0x24, Event::Key("key 1".into())),
+            (0x2c, Event::Str("value 1".into())),
+        ]
+    );
+}
+
+#[test]
+fn events_stop_after_an_error() {
+    let qbjs_content = read_qbjs_test_file("302_invalid_qbjs_tag_document");
+    let mut events = QbjsEvents::new(&qbjs_content);
+
+    assert_eq!(
+        events.next(),
+        Some(Err(qbjs::DeserializeError::AnalysisError(
+            qbjs_deserializer::analysis::AnalysisError::HeaderAnalysisError(
+                qbjs_deserializer::analysis::header::Error::InvalidTag
+            )
+        )))
+    );
+    assert_eq!(events.next(), None);
+}
+
+#[test]
+fn events_of_cyclic_document() {
+    // Root array whose only value is an array at offset 0, which is the root array itself
+    let qbjs_content =
+        b"qbjs\x01\x00\x00\x00\x10\x00\x00\x00\x02\x00\x00\x00\x0c\x00\x00\x00\x04\x00\x00\x00";
+    let mut events = QbjsEvents::new(qbjs_content);
+
+    assert_eq!(events.next(), Some(Ok((8, Event::StartArray(1)))));
+    assert_eq!(
+        events.next(),
+        Some(Err(qbjs::DeserializeError::AnalysisError(
+            qbjs_deserializer::analysis::AnalysisError::DataAnalysisError(
+                qbjs_deserializer::analysis::data::Error::InvalidContainerOffset
+            )
+        )))
+    );
+    assert_eq!(events.next(), None);
+}
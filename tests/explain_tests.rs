@@ -0,0 +1,141 @@
+mod common;
+
+use common::read_qbjs_test_file;
+use qbjs_deserializer::qbjs::{self, analysis, Region};
+
+fn region(range: std::ops::Range<usize>, depth: usize, description: &str) -> Region {
+    Region {
+        range,
+        depth,
+        description: description.to_owned(),
+    }
+}
+
+#[test]
+fn explain_null_object_document() {
+    let qbjs_content = read_qbjs_test_file("00_null_document");
+    let explanation = qbjs::explain(&qbjs_content);
+
+    assert_eq!(explanation.error, None);
+    assert_eq!(
+        explanation.regions,
+        vec![
+            region(0..4, 0, "tag \"qbjs\""),
+            region(4..8, 0, "version 1"),
+            region(
+                8..20,
+                0,
+                "object base: size 32, is_object 1, length 1, table_offset 28"
+            ),
+            region(
+                20..24,
+                1,
+                "entry header: type 0 (null), latin_or_int 0, latin_key 1, value 0"
+            ),
+            region(24..33, 1, "latin1 key: \"nullObj\""),
+            region(33..36, 1, "padding"),
+            region(36..40, 1, "offset table [0]: entry at 12"),
+        ]
+    );
+
+    let mut text = Vec::new();
+    explanation.write(&mut text).unwrap();
+    let text = String::from_utf8(text).unwrap();
+    assert_eq!(text.lines().count(), 7);
+    assert!(text
+        .starts_with("00000000  71 62 6a 73                                      tag \"qbjs\"\n"));
+    assert!(text.ends_with(
+        "00000024  0c 00 00 00                                        offset table [0]: entry at 12\n"
+    ));
+}
+
+#[test]
+fn explain_covers_valid_documents() {
+    let file_names = [
+        "012_various_values_object_document",
+        "105_various_values_array_document",
+        "205_tree_array_in_array_document",
+        "400_example_from_qbjs_source_document",
+    ];
+
+    for file_name in file_names {
+        let qbjs_content = read_qbjs_test_file(file_name);
+        let explanation = qbjs::explain(&qbjs_content);
+
+        assert_eq!(explanation.error, None);
+
+        // Regions of documents written by Qt are contiguous
+        let mut end = 0;
+        for region in &explanation.regions {
+            assert_eq!(region.range.start, end, "{}: {:?}", file_name, region);
+            end = region.range.end;
+        }
+        assert_eq!(end, qbjs_content.len());
+    }
+}
+
+#[test]
+fn explain_unreferenced_bytes() {
+    let mut qbjs_content = read_qbjs_test_file("00_null_document");
+    qbjs_content.extend_from_slice(&[0xff; 4]);
+    let explanation = qbjs::explain(&qbjs_content);
+
+    assert_eq!(explanation.error, None);
+    assert_eq!(
+        explanation.regions.last(),
+        Some(&region(40..44, 0, "unreferenced bytes"))
+    );
+}
+
+#[test]
+fn explain_invalid_documents() {
+    let qbjs_content = read_qbjs_test_file("303_invalid_qbjs_version_document");
+    let explanation = qbjs::explain(&qbjs_content);
+
+    assert_eq!(
+        explanation.error,
+        Some(qbjs::DeserializeError::AnalysisError(
+            analysis::AnalysisError::HeaderAnalysisError(analysis::header::Error::InvalidVersion)
+        ))
+    );
+    assert_eq!(explanation.regions[0], region(0..4, 0, "tag \"qbjs\""));
+
+    // Regions explained before the error are kept
+    let qbjs_content = read_qbjs_test_file("206_objects_in_array_document");
+    let explanation = qbjs::explain(&qbjs_content[..100]);
+
+    assert!(explanation.error.is_some());
+    assert_eq!(
+        explanation.regions[2],
+        region(
+            8..20,
+            0,
+            "array base: size 156, is_object 0, length 2, table_offset 148"
+        )
+    );
+}
+
+#[test]
+fn explain_cyclic_document() {
+    // Root array whose only value is an array at offset 0, which is the root array itself
+    let qbjs_content =
+        b"qbjs\x01\x00\x00\x00\x10\x00\x00\x00\x02\x00\x00\x00\x0c\x00\x00\x00\x04\x00\x00\x00";
+    let explanation = qbjs::explain(qbjs_content);
+
+    assert_eq!(
+        explanation.error,
+        Some(qbjs::DeserializeError::AnalysisError(
+            analysis::AnalysisError::DataAnalysisError(
+                analysis::data::Error::InvalidContainerOffset
+            )
+        ))
+    );
+}
+
+#[test]
+fn explain_empty_document() {
+    let explanation = qbjs::explain(&[]);
+
+    assert_eq!(explanation.error, None);
+    assert!(explanation.regions.is_empty());
+}
@@ -0,0 +1,128 @@
+mod common;
+
+use common::read_qbjs_test_file;
+use qbjs_deserializer::qbjs::{self, analysis, write};
+
+use serde_json::json;
+
+#[test]
+fn extract_pointers() {
+    let qbjs_content = read_qbjs_test_file("400_example_from_qbjs_source_document");
+    let extracted =
+        qbjs::extract(&qbjs_content, &["/address/city", "/age", "/phoneNumber/1"]).unwrap();
+
+    assert_eq!(
+        extracted.keys().collect::<Vec<_>>(),
+        vec!["/address/city", "/age", "/phoneNumber/1"]
+    );
+    assert_eq!(extracted["/address/city"], json!("New York"));
+    assert_eq!(extracted["/age"], json!(25));
+    assert_eq!(
+        extracted["/phoneNumber/1"],
+        json!({"type": "fax", "number": "646 555-4567"})
+    );
+}
+
+#[test]
+fn extract_wildcards() {
+    let qbjs_content = read_qbjs_test_file("400_example_from_qbjs_source_document");
+    let extracted = qbjs::extract(&qbjs_content, &["/phoneNumber/*/type"]).unwrap();
+
+    assert_eq!(extracted.len(), 2);
+    assert_eq!(extracted["/phoneNumber/0/type"], json!("home"));
+    assert_eq!(extracted["/phoneNumber/1/type"], json!("fax"));
+
+    let qbjs_content = read_qbjs_test_file("206_objects_in_array_document");
+    let extracted = qbjs::extract(&qbjs_content, &["/*/*"]).unwrap();
+
+    assert_eq!(
+        extracted.keys().collect::<Vec<_>>(),
+        vec!["/0/key 1", "/0/key 2", "/1/key 3", "/1/key 4"]
+    );
+}
+
+#[test]
+fn extract_matches_deserialized_values() {
+    let qbjs_content = read_qbjs_test_file("400_example_from_qbjs_source_document");
+    let value = qbjs::deserialize_to_json(&qbjs_content).unwrap();
+    let extracted = qbjs::extract(&qbjs_content, &["", "/*", "/*/*", "/*/*/*"]).unwrap();
+
+    let spans = qbjs::deserialize_with_spans(&qbjs_content).unwrap().1;
+    assert_eq!(
+        extracted.keys().collect::<Vec<_>>(),
+        spans.keys().collect::<Vec<_>>()
+    );
+    for (pointer, extracted_value) in &extracted {
+        assert_eq!(Some(extracted_value), value.pointer(pointer));
+    }
+}
+
+#[test]
+fn extract_missing_values() {
+    let qbjs_content = read_qbjs_test_file("400_example_from_qbjs_source_document");
+    let extracted = qbjs::extract(
+        &qbjs_content,
+        &[
+            "/missing",
+            "/age/0",
+            "/phoneNumber/2",
+            "/phoneNumber/01",
+            "/phoneNumber/+1",
+        ],
+    )
+    .unwrap();
+
+    assert!(extracted.is_empty());
+}
+
+#[test]
+fn extract_from_empty_document() {
+    let extracted = qbjs::extract(&[], &["", "/a"]).unwrap();
+
+    assert_eq!(extracted.len(), 1);
+    assert_eq!(extracted[""], json!({}));
+}
+
+#[test]
+fn extract_invalid_pointer() {
+    let qbjs_content = read_qbjs_test_file("400_example_from_qbjs_source_document");
+
+    assert_eq!(
+        qbjs::extract(&qbjs_content, &["age"]),
+        Err(qbjs::DeserializeError::InvalidJsonPointer("age".to_owned()))
+    );
+    assert_eq!(
+        qbjs::extract(&qbjs_content, &["/a~2"]),
+        Err(qbjs::DeserializeError::InvalidJsonPointer(
+            "/a~2".to_owned()
+        ))
+    );
+}
+
+#[test]
+fn extract_container_of_the_wrong_type() {
+    let root = write::Value::Object(vec![write::Entry {
+        key: write::Key::Latin1String(b"a".to_vec()),
+        value: write::Value::Array(vec![write::Value::SelfContainedNumber(1)]),
+    }]);
+    let mut qbjs_content = write::encode_document(&root).unwrap();
+
+    // The value header of "a", followed by its key length and key, claims an object
+    let key_start = qbjs_content
+        .windows(3)
+        .position(|window| window == b"\x01\x00a")
+        .unwrap();
+    qbjs_content[key_start - 4] = (qbjs_content[key_start - 4] & !0b111) | 5;
+
+    let expected_error = qbjs::DeserializeError::AnalysisError(
+        analysis::AnalysisError::DataAnalysisError(analysis::data::Error::InvalidObjectContainer),
+    );
+    assert_eq!(
+        qbjs::extract(&qbjs_content, &["/a/*"]),
+        Err(expected_error.clone())
+    );
+    assert_eq!(
+        qbjs::deserialize_to_json(&qbjs_content),
+        Err(expected_error)
+    );
+}
@@ -0,0 +1,161 @@
+#![cfg(feature = "ffi")]
+
+mod common;
+
+use common::read_qbjs_test_file;
+use qbjs_deserializer::ffi::*;
+
+use std::ffi::{c_char, CStr, CString};
+use std::ptr;
+
+fn no_error() -> QbjsError {
+    QbjsError {
+        code: QbjsErrorCode::QBJS_OK,
+        message: ptr::null_mut(),
+    }
+}
+
+// Takes the string returned by the library and frees it
+unsafe fn take_string(string: *mut c_char) -> String {
+    let owned = CStr::from_ptr(string).to_str().unwrap().to_owned();
+    qbjs_free(string);
+    owned
+}
+
+unsafe fn take_error(err: &mut QbjsError) -> (QbjsErrorCode, String) {
+    let message = take_string(err.message);
+    err.message = ptr::null_mut();
+    (err.code, message)
+}
+
+const EXAMPLE: &str = "400_example_from_qbjs_source_document";
+
+#[test]
+fn ffi_to_json() {
+    let qbjs = read_qbjs_test_file(EXAMPLE);
+    let mut out = ptr::null_mut();
+    let mut err = no_error();
+
+    unsafe {
+        assert!(qbjs_to_json(qbjs.as_ptr(), qbjs.len(), &mut out, &mut err));
+        assert_eq!(err.code, QbjsErrorCode::QBJS_OK);
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&take_string(out)).unwrap(),
+            qbjs_deserializer::qbjs::deserialize_to_json(&qbjs).unwrap()
+        );
+
+        assert!(qbjs_to_json(ptr::null(), 0, &mut out, &mut err));
+        assert_eq!(take_string(out), "{}");
+
+        assert!(!qbjs_to_json(qbjs.as_ptr(), 4, &mut out, &mut err));
+        assert_eq!(
+            take_error(&mut err),
+            (
+                QbjsErrorCode::QBJS_DESERIALIZE_ERROR,
+                "invalid qbjs document: the data ends before the document".to_owned()
+            )
+        );
+
+        assert!(!qbjs_to_json(ptr::null(), 4, &mut out, ptr::null_mut()));
+    }
+}
+
+#[test]
+fn ffi_document() {
+    let qbjs = read_qbjs_test_file(EXAMPLE);
+    let mut err = no_error();
+    let mut string = ptr::null_mut();
+    let mut number = 0.0;
+    let mut boolean = false;
+
+    unsafe {
+        let document = qbjs_document_new(qbjs.as_ptr(), qbjs.len(), &mut err);
+        assert!(!document.is_null());
+        let root = qbjs_document_root(document);
+        assert_eq!(qbjs_value_type(root), QbjsType::QBJS_OBJECT);
+
+        let address = CString::new("address").unwrap();
+        let city = CString::new("city").unwrap();
+        let address = qbjs_value_get(document, root, address.as_ptr(), &mut err);
+        let city = qbjs_value_get(document, address, city.as_ptr(), &mut err);
+        assert_eq!(qbjs_value_type(city), QbjsType::QBJS_STRING);
+        assert!(qbjs_value_string(document, city, &mut string, &mut err));
+        assert_eq!(take_string(string), "New York");
+
+        let age = CString::new("age").unwrap();
+        let age = qbjs_value_get(document, root, age.as_ptr(), &mut err);
+        assert!(qbjs_value_number(document, age, &mut number, &mut err));
+        assert_eq!(number, 25.0);
+        assert!(!qbjs_value_bool(document, age, &mut boolean, &mut err));
+        assert_eq!(
+            take_error(&mut err),
+            (QbjsErrorCode::QBJS_TYPE_MISMATCH, "not a bool".to_owned())
+        );
+
+        let phone_numbers = CString::new("phoneNumber").unwrap();
+        let phone_numbers = qbjs_value_get(document, root, phone_numbers.as_ptr(), &mut err);
+        assert_eq!(qbjs_value_type(phone_numbers), QbjsType::QBJS_ARRAY);
+        assert_eq!(qbjs_value_length(phone_numbers), 2);
+        let fax = qbjs_value_index(phone_numbers, 1, &mut err);
+        assert!(qbjs_value_to_json(document, fax, &mut string, &mut err));
+        assert_eq!(
+            take_string(string),
+            r#"{"number":"646 555-4567","type":"fax"}"#
+        );
+        assert!(qbjs_value_index(phone_numbers, 2, &mut err).is_null());
+        assert_eq!(
+            take_error(&mut err),
+            (QbjsErrorCode::QBJS_NOT_FOUND, "2".to_owned())
+        );
+
+        // Objects are indexed in storage order, keys are sorted
+        assert!(qbjs_value_key(document, root, 0, &mut string, &mut err));
+        assert_eq!(take_string(string), "address");
+        let first = qbjs_value_index(root, 0, &mut err);
+        assert_eq!(first, address);
+
+        let missing = CString::new("missing").unwrap();
+        assert!(qbjs_value_get(document, root, missing.as_ptr(), &mut err).is_null());
+        assert_eq!(
+            take_error(&mut err),
+            (QbjsErrorCode::QBJS_NOT_FOUND, "\"missing\"".to_owned())
+        );
+        assert!(qbjs_value_get(document, city, missing.as_ptr(), &mut err).is_null());
+        assert_eq!(
+            take_error(&mut err),
+            (
+                QbjsErrorCode::QBJS_TYPE_MISMATCH,
+                "not an object".to_owned()
+            )
+        );
+
+        qbjs_document_free(document);
+    }
+}
+
+#[test]
+fn ffi_document_errors() {
+    let mut err = no_error();
+
+    unsafe {
+        let document = qbjs_document_new(ptr::null(), 0, &mut err);
+        assert_eq!(err.code, QbjsErrorCode::QBJS_OK);
+        let root = qbjs_document_root(document);
+        assert_eq!(qbjs_value_type(root), QbjsType::QBJS_OBJECT);
+        assert_eq!(qbjs_value_length(root), 0);
+        qbjs_document_free(document);
+
+        let document = qbjs_document_new(b"qbjs".as_ptr(), 4, &mut err);
+        assert!(document.is_null());
+        assert_eq!(
+            take_error(&mut err),
+            (
+                QbjsErrorCode::QBJS_DESERIALIZE_ERROR,
+                "invalid qbjs document: the data ends before the document".to_owned()
+            )
+        );
+
+        assert!(qbjs_document_root(ptr::null()).is_null());
+        assert_eq!(qbjs_value_type(ptr::null()), QbjsType::QBJS_NULL);
+    }
+}
@@ -0,0 +1,62 @@
+#![cfg(feature = "figment")]
+
+use figment::value::Dict;
+use figment::{Figment, Profile};
+use qbjs_deserializer::qbjs::Qbjs;
+
+use std::fs;
+
+const EXAMPLE_FILE: &str = "tests/test_data/qbjs_data/400_example_from_qbjs_source_document.qbjs";
+
+#[test]
+fn figment_qbjs_file() {
+    let figment = Figment::from(Qbjs::file(EXAMPLE_FILE));
+
+    assert_eq!(
+        figment.extract_inner::<String>("address.city").unwrap(),
+        "New York"
+    );
+    assert_eq!(figment.extract_inner::<i64>("age").unwrap(), 25);
+    assert_eq!(figment.find_metadata("age").unwrap().name, "qbjs file");
+
+    // Missing files provide no value
+    let figment = Figment::from(Qbjs::file("missing.qbjs"));
+    assert!(figment.extract_inner::<i64>("age").is_err());
+}
+
+#[test]
+fn figment_qbjs_profiles() {
+    let qbjs = fs::read(EXAMPLE_FILE).unwrap();
+
+    let figment = Figment::from(Qbjs::bytes(qbjs.clone()).profile("example")).select("example");
+    assert_eq!(figment.extract_inner::<i64>("age").unwrap(), 25);
+
+    // The top level keys are profiles
+    let qbjs = fs::read("tests/test_data/qbjs_data/208_tree_empty_objects_in_object_document.qbjs")
+        .unwrap();
+    let figment = Figment::from(Qbjs::bytes(qbjs).nested()).select("root object");
+    assert_eq!(figment.profile(), &Profile::new("root object"));
+    assert!(figment
+        .extract_inner::<Dict>("child object 2")
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn figment_errors() {
+    let figment = Figment::from(Qbjs::file(
+        "tests/test_data/qbjs_data/302_invalid_qbjs_tag_document.qbjs",
+    ));
+    let err = figment.extract_inner::<i64>("age").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid qbjs document: the header doesn't start with \"qbjs\" \
+        in tests/test_data/qbjs_data/302_invalid_qbjs_tag_document.qbjs qbjs file"
+    );
+
+    // The root of the document has to be an object
+    let qbjs = fs::read("tests/test_data/qbjs_data/100_null_array_document.qbjs").unwrap();
+    assert!(Figment::from(Qbjs::bytes(qbjs))
+        .extract_inner::<i64>("age")
+        .is_err());
+}
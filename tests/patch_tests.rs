@@ -0,0 +1,294 @@
+mod common;
+
+use common::read_qbjs_test_file;
+use qbjs_deserializer::qbjs::{self, write, PatchError};
+
+use serde_json::{json, Value};
+
+macro_rules! create_test {
+    ($test_name: ident) => {
+        #[test]
+        #[allow(non_snake_case)]
+        fn $test_name() {
+            let file_name = &stringify!($test_name)[1..];
+            let qbjs_content = read_qbjs_test_file(file_name);
+
+            // Untouched documents keep their storage
+            assert_eq!(
+                qbjs::patch(&qbjs_content, &json!([])).unwrap(),
+                qbjs_content
+            );
+
+            // Values converted from JSON are stored the same way Qt stores them
+            let json = qbjs::deserialize_to_json(&qbjs_content).unwrap();
+            assert_eq!(
+                write::encode_document(&write::Value::from_json(&json)).unwrap(),
+                qbjs_content
+            );
+        }
+    };
+}
+
+macro_rules! create_tests {
+    ($($test_name: ident),*) => {
+        $(
+            create_test!($test_name);
+        )*
+    };
+}
+
+create_tests!(
+    _00_null_document,
+    _000_null_object_document,
+    _001_bool_true_object_document,
+    _002_bool_false_object_document,
+    _003_double_object_document,
+    _004_double_zero_object_document,
+    _005_negative_double_object_document,
+    _006_int_object_document,
+    _007_int_zero_object_document,
+    _008_negative_int_object_document,
+    _009_string_object_document,
+    _010_strings_object_document,
+    _011_japanese_string_object_document,
+    _012_various_values_object_document,
+    _100_null_array_document,
+    _101_bool_array_document,
+    _102_double_array_document,
+    _103_int_array_document,
+    _104_string_array_document,
+    _105_various_values_array_document,
+    _200_object_object_document,
+    _201_array_object_document,
+    _202_tree_object_document,
+    _203_tree_array_document,
+    _204_array_in_array_document,
+    _205_tree_array_in_array_document,
+    _206_objects_in_array_document,
+    _207_tree_empty_arrays_in_object_document,
+    _208_tree_empty_objects_in_object_document,
+    _400_example_from_qbjs_source_document
+);
+
+fn storage_tree(qbjs_content: &[u8]) -> write::Value {
+    let document = qbjs::analyze_document(qbjs_content).unwrap();
+    write::value_from_analysis(qbjs_content, &document).unwrap()
+}
+
+fn entry_value<'a>(value: &'a write::Value, key: &str) -> &'a write::Value {
+    match value {
+        write::Value::Object(entries) => {
+            &entries
+                .iter()
+                .find(|entry| entry.key.decode().unwrap() == key)
+                .unwrap()
+                .value
+        }
+        _ => panic!("not an object"),
+    }
+}
+
+fn keys(value: &write::Value) -> Vec<String> {
+    match value {
+        write::Value::Object(entries) => entries
+            .iter()
+            .map(|entry| entry.key.decode().unwrap())
+            .collect(),
+        _ => panic!("not an object"),
+    }
+}
+
+#[test]
+fn patch_operations() {
+    let qbjs_content = read_qbjs_test_file("400_example_from_qbjs_source_document");
+    let json_patch = json!([
+        {"op": "test", "path": "/age", "value": 25.0},
+        {"op": "replace", "path": "/age", "value": 26},
+        {"op": "add", "path": "/phoneNumber/-", "value": {"type": "mobile", "number": "0"}},
+        {"op": "remove", "path": "/phoneNumber/0"},
+        {"op": "move", "from": "/address/city", "path": "/city"},
+        {"op": "copy", "from": "/city", "path": "/address/town"},
+        {"op": "add", "path": "/nickname", "value": "J\u{f6}hn \u{263a}"},
+    ]);
+
+    let patched = qbjs::patch(&qbjs_content, &json_patch).unwrap();
+
+    assert_eq!(
+        qbjs::deserialize_to_json(&patched).unwrap(),
+        json!({
+            "firstName": "John",
+            "lastName": "Smith",
+            "age": 26,
+            "address": {
+                "streetAddress": "21 2nd Street",
+                "state": "NY",
+                "postalCode": "10021",
+                "town": "New York"
+            },
+            "phoneNumber": [
+                {"type": "fax", "number": "646 555-4567"},
+                {"type": "mobile", "number": "0"}
+            ],
+            "city": "New York",
+            "nickname": "J\u{f6}hn \u{263a}"
+        })
+    );
+
+    // New keys are inserted in the order Qt looks them up
+    let root = storage_tree(&patched);
+    assert_eq!(
+        keys(&root),
+        [
+            "address",
+            "age",
+            "city",
+            "firstName",
+            "lastName",
+            "nickname",
+            "phoneNumber"
+        ]
+    );
+    assert_eq!(
+        keys(entry_value(&root, "address")),
+        ["postalCode", "state", "streetAddress", "town"]
+    );
+    assert_eq!(
+        entry_value(&root, "age"),
+        &write::Value::SelfContainedNumber(26)
+    );
+    assert_eq!(
+        entry_value(&root, "nickname"),
+        &write::Value::Utf16String("J\u{f6}hn \u{263a}".encode_utf16().collect())
+    );
+    // Values that weren't replaced are compacted
+    assert!(qbjs::analyze_slack(&patched).unwrap().gaps.is_empty());
+}
+
+#[test]
+fn patch_keeps_storage() {
+    let qbjs_content = write::encode_document(&write::Value::Object(vec![
+        write::Entry {
+            key: write::Key::Utf16String("kept".encode_utf16().collect()),
+            value: write::Value::Number(1.0),
+        },
+        write::Entry {
+            key: write::Key::Latin1String(b"replaced".to_vec()),
+            value: write::Value::Utf16String("abc".encode_utf16().collect()),
+        },
+    ]))
+    .unwrap();
+
+    let patched = qbjs::patch(
+        &qbjs_content,
+        &json!([{"op": "replace", "path": "/replaced", "value": "abc"}]),
+    )
+    .unwrap();
+
+    assert_eq!(
+        storage_tree(&patched),
+        write::Value::Object(vec![
+            write::Entry {
+                key: write::Key::Utf16String("kept".encode_utf16().collect()),
+                value: write::Value::Number(1.0),
+            },
+            write::Entry {
+                key: write::Key::Latin1String(b"replaced".to_vec()),
+                value: write::Value::Latin1String(b"abc".to_vec()),
+            },
+        ])
+    );
+}
+
+#[test]
+fn patch_errors() {
+    let qbjs_content = read_qbjs_test_file("400_example_from_qbjs_source_document");
+    let patch = |json_patch: Value| qbjs::patch(&qbjs_content, &json_patch);
+
+    assert_eq!(patch(json!({})), Err(PatchError::InvalidPatch));
+    assert_eq!(
+        patch(json!([{"op": "test", "path": "/age", "value": 25}, {"op": "jump", "path": ""}])),
+        Err(PatchError::InvalidOperation(1))
+    );
+    assert_eq!(
+        patch(json!([{"op": "add", "path": "/age"}])),
+        Err(PatchError::InvalidOperation(0))
+    );
+    assert_eq!(
+        patch(json!([{"op": "move", "from": "/address", "path": "/address/a"}])),
+        Err(PatchError::InvalidOperation(0))
+    );
+    assert_eq!(
+        patch(json!([{"op": "remove", "path": "age"}])),
+        Err(PatchError::InvalidPointer("age".to_owned()))
+    );
+    assert_eq!(
+        patch(json!([{"op": "remove", "path": "/phoneNumber/01"}])),
+        Err(PatchError::InvalidPointer("/phoneNumber/01".to_owned()))
+    );
+    assert_eq!(
+        patch(json!([{"op": "remove", "path": "/phoneNumber/2"}])),
+        Err(PatchError::PathNotFound("/phoneNumber/2".to_owned()))
+    );
+    assert_eq!(
+        patch(json!([{"op": "replace", "path": "/a~1b", "value": 1}])),
+        Err(PatchError::PathNotFound("/a~1b".to_owned()))
+    );
+    assert_eq!(
+        patch(json!([{"op": "test", "path": "/firstName", "value": "Jane"}])),
+        Err(PatchError::TestFailed("/firstName".to_owned()))
+    );
+    assert_eq!(
+        patch(json!([{"op": "replace", "path": "", "value": 1}])),
+        Err(PatchError::DeserializeError(
+            qbjs::DeserializeError::EncodeError(write::EncodeError::InvalidRootContainer)
+        ))
+    );
+}
+
+#[test]
+fn merge_patch_document() {
+    let qbjs_content = read_qbjs_test_file("400_example_from_qbjs_source_document");
+
+    let patched = qbjs::merge_patch(
+        &qbjs_content,
+        &json!({
+            "age": null,
+            "address": {"city": "Boston", "postalCode": null, "country": {"code": "US", "name": null}},
+            "phoneNumber": [],
+            "unknown": null
+        }),
+    )
+    .unwrap();
+
+    assert_eq!(
+        qbjs::deserialize_to_json(&patched).unwrap(),
+        json!({
+            "firstName": "John",
+            "lastName": "Smith",
+            "address": {
+                "streetAddress": "21 2nd Street",
+                "city": "Boston",
+                "state": "NY",
+                "country": {"code": "US"}
+            },
+            "phoneNumber": []
+        })
+    );
+    let root = storage_tree(&patched);
+    assert_eq!(
+        keys(entry_value(&root, "address")),
+        ["city", "country", "state", "streetAddress"]
+    );
+}
+
+#[test]
+fn patch_empty_document() {
+    let patched = qbjs::patch(&[], &json!([{"op": "add", "path": "/a", "value": [1]}])).unwrap();
+    assert_eq!(
+        qbjs::deserialize_to_json(&patched).unwrap(),
+        json!({"a": [1]})
+    );
+
+    let patched = qbjs::merge_patch(&[], &json!([true])).unwrap();
+    assert_eq!(qbjs::deserialize_to_json(&patched).unwrap(), json!([true]));
+}
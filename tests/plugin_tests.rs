@@ -0,0 +1,95 @@
+use qbjs_deserializer::qbjs::{self, DeserializeError, PluginMetadataError};
+
+use serde_json::json;
+use std::fs;
+use std::io;
+
+const PLUGINS_DIRECTORY: &str = "tests/test_data/plugins";
+
+fn expected_metadata() -> serde_json::Value {
+    json!({
+        "IID": "org.qt-project.Qt.QPA.QPlatformIntegrationFactoryInterface.5.3",
+        "MetaData": {"Keys": ["xcb"]},
+        "className": "QXcbIntegrationPlugin",
+        "debug": false,
+        "version": 331520
+    })
+}
+
+#[test]
+fn plugin_metadata_in_section() {
+    let metadata =
+        qbjs::plugin_metadata_from_elf(format!("{}/section.so", PLUGINS_DIRECTORY)).unwrap();
+
+    assert_eq!(metadata, expected_metadata());
+}
+
+#[test]
+fn plugin_metadata_in_rodata() {
+    // A marker without document precedes the metadata
+    let metadata =
+        qbjs::plugin_metadata_from_elf(format!("{}/rodata.so", PLUGINS_DIRECTORY)).unwrap();
+
+    assert_eq!(metadata, expected_metadata());
+}
+
+#[test]
+fn plugin_metadata_errors() {
+    assert_eq!(
+        qbjs::plugin_metadata_from_elf(format!("{}/missing.so", PLUGINS_DIRECTORY)),
+        Err(PluginMetadataError::IoError(io::ErrorKind::NotFound))
+    );
+    assert_eq!(
+        qbjs::plugin_metadata_from_elf(format!("{}/section.c", PLUGINS_DIRECTORY)),
+        Err(PluginMetadataError::InvalidElfFile)
+    );
+
+    let elf = fs::read(format!("{}/section.so", PLUGINS_DIRECTORY)).unwrap();
+    let marker_position = elf
+        .windows(qbjs::plugin::PLUGIN_METADATA_MARKER.len())
+        .position(|window| window == qbjs::plugin::PLUGIN_METADATA_MARKER)
+        .unwrap();
+
+    let mut without_marker = elf.clone();
+    without_marker[marker_position] = b'X';
+    assert_eq!(
+        qbjs::plugin_metadata(&without_marker),
+        Err(PluginMetadataError::MetadataNotFound)
+    );
+
+    // Without its section headers, the truncated file is searched whole
+    let truncated = &elf[..(marker_position + 24)];
+    assert_eq!(
+        qbjs::plugin_metadata(truncated),
+        Err(PluginMetadataError::DeserializeError(
+            DeserializeError::InsufficientData
+        ))
+    );
+}
+
+#[test]
+fn plugin_metadata_section_header_table() {
+    let elf = fs::read(format!("{}/section.so", PLUGINS_DIRECTORY)).unwrap();
+
+    // e_shoff so large the table would end past the address space
+    let mut oversized = elf.clone();
+    oversized[0x28..0x30].copy_from_slice(&(u64::MAX - 0x40).to_le_bytes());
+    assert_eq!(
+        qbjs::plugin_metadata(&oversized),
+        Err(PluginMetadataError::InvalidElfFile)
+    );
+
+    // e_shstrndx past the last section header
+    let mut invalid_names_index = elf.clone();
+    let entry_count = u16::from_le_bytes([elf[0x3c], elf[0x3d]]);
+    invalid_names_index[0x3e..0x40].copy_from_slice(&entry_count.to_le_bytes());
+    assert_eq!(
+        qbjs::plugin_metadata(&invalid_names_index),
+        Err(PluginMetadataError::InvalidElfFile)
+    );
+
+    // The table is cut short, the file is searched whole
+    let table_start = u64::from_le_bytes(elf[0x28..0x30].try_into().unwrap()) as usize;
+    let truncated = &elf[..(table_start + 0x100)];
+    assert_eq!(qbjs::plugin_metadata(truncated), Ok(expected_metadata()));
+}
@@ -0,0 +1,114 @@
+mod common;
+
+use common::{read_qbjs_test_file, read_test_file};
+use qbjs_deserializer::qbjs::{self, write, WriteOptions};
+
+fn write_qt_json(qbjs_content: &[u8], options: WriteOptions) -> String {
+    let mut output = Vec::new();
+    qbjs::write_json(qbjs_content, &mut output, options).unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+// Array document containing a single double, stored out of the value header
+fn double_array_document(value: f64) -> Vec<u8> {
+    let mut document = b"qbjs".to_vec();
+    document.extend_from_slice(&1_u32.to_le_bytes());
+    document.extend_from_slice(&24_u32.to_le_bytes()); // size
+    document.extend_from_slice(&(1_u32 << 1).to_le_bytes()); // length, array flag
+    document.extend_from_slice(&20_u32.to_le_bytes()); // table offset
+    document.extend_from_slice(&value.to_bits().to_le_bytes());
+    document.extend_from_slice(&(2_u32 | (12 << 5)).to_le_bytes()); // double stored at offset 12
+    document
+}
+
+macro_rules! create_test {
+    // Compares the Qt emulation output to the files in tests/test_data/expected_qt_json
+    ($test_name:ident) => {
+        #[test]
+        fn $test_name() {
+            let test_name = stringify!($test_name);
+            let file_name = &test_name[1..];
+
+            let qbjs_content = read_qbjs_test_file(file_name);
+
+            for (format, options) in [
+                ("indented", WriteOptions::qt_indented()),
+                ("compact", WriteOptions::qt_compact()),
+            ] {
+                let expected_json_file_path =
+                    format!("expected_qt_json/{}_{}.json", file_name, format);
+                let expected_json =
+                    String::from_utf8(read_test_file(&expected_json_file_path)).unwrap();
+
+                assert_eq!(write_qt_json(&qbjs_content, options), expected_json);
+            }
+        }
+    };
+}
+
+macro_rules! create_tests {
+    ($test_name:ident) => {
+        create_test!($test_name);
+    };
+    ($test_name:ident, $($test_names:ident),+) => {
+        create_test!($test_name);
+        create_tests!($($test_names),+);
+    };
+}
+
+create_tests!(
+    _012_various_values_object_document,
+    _208_tree_empty_objects_in_object_document,
+    _400_example_from_qbjs_source_document
+);
+
+#[test]
+fn qt_double_formatting() {
+    let expectations = [
+        (1.5, "1.5"),
+        (-0.25, "-0.25"),
+        (-0.0, "0"),
+        (100000.0, "100000"),
+        (1000000.0, "1000000"),
+        (-1e15, "-1000000000000000"),
+        (123456789.0, "123456789"),
+        (9007199254740992.0, "9007199254740992"),
+        (-9223372036854775808.0, "-9223372036854775808"),
+        (9223372036854775808.0, "9223372036854776000"),
+        (1e20, "1e+20"),
+        (1234567.5, "1234567.5"),
+        (1.25e120, "1.25e+120"),
+        (0.0001, "0.0001"),
+        (0.00001, "1e-05"),
+        (f64::NAN, "null"),
+        (f64::INFINITY, "null"),
+    ];
+
+    for (value, expected_text) in expectations {
+        let document = double_array_document(value);
+        assert_eq!(
+            write_qt_json(&document, WriteOptions::qt_compact()),
+            format!("[{}]", expected_text)
+        );
+    }
+}
+
+#[test]
+fn qt_unpaired_surrogates_are_escaped() {
+    // Both orders of unpaired surrogates, in keys and values
+    let root = write::Value::Object(vec![write::Entry {
+        key: write::Key::Utf16String(vec![0xdc00, 0x6b]),
+        value: write::Value::Utf16String(vec![0x61, 0xd83d, 0x62, 0xdfff]),
+    }]);
+    let document = write::encode_document(&root).unwrap();
+
+    assert_eq!(
+        write_qt_json(&document, WriteOptions::qt_compact()),
+        r#"{"\udc00k":"a\ud83db\udfff"}"#
+    );
+}
+
+#[test]
+fn qt_empty_document_has_no_text() {
+    assert_eq!(write_qt_json(&[], WriteOptions::qt_indented()), "");
+}
@@ -0,0 +1,149 @@
+mod common;
+
+use common::read_qbjs_test_file;
+use qbjs_deserializer::qbjs::{self, query::QueryError, Query};
+
+use serde_json::json;
+
+fn query(file_name: &str, expression: &str) -> Vec<serde_json::Value> {
+    let qbjs_content = read_qbjs_test_file(file_name);
+    let query = Query::parse(expression).unwrap();
+    let results = query.evaluate(&qbjs_content).unwrap();
+
+    // Evaluating the decoded document gives the same results
+    let value = qbjs::deserialize_to_json(&qbjs_content).unwrap();
+    assert_eq!(results, query.evaluate_value(&value).unwrap());
+
+    results
+}
+
+#[test]
+fn query_paths() {
+    let example = "400_example_from_qbjs_source_document";
+
+    assert_eq!(
+        query(example, "."),
+        vec![qbjs::deserialize_to_json(&read_qbjs_test_file(example)).unwrap()]
+    );
+    assert_eq!(query(example, ".address.city"), vec![json!("New York")]);
+    assert_eq!(
+        query(example, ".[\"address\"] | .\"city\""),
+        vec![json!("New York")]
+    );
+    assert_eq!(query(example, ".phoneNumber[1].type"), vec![json!("fax")]);
+    assert_eq!(query(example, ".phoneNumber[-1].type"), vec![json!("fax")]);
+    assert_eq!(query(example, ".missing"), vec![json!(null)]);
+    assert_eq!(query(example, ".phoneNumber[5]"), vec![json!(null)]);
+    assert_eq!(query(example, ".missing.key"), vec![json!(null)]);
+}
+
+#[test]
+fn query_wildcards() {
+    assert_eq!(
+        query(
+            "400_example_from_qbjs_source_document",
+            ".phoneNumber[].number"
+        ),
+        vec![json!("212 555-1234"), json!("646 555-4567")]
+    );
+    assert_eq!(
+        query("206_objects_in_array_document", ".[][]"),
+        vec![
+            json!("value 1"),
+            json!("value 2"),
+            json!("value 3"),
+            json!("value 4")
+        ]
+    );
+}
+
+#[test]
+fn query_slices() {
+    let example = "400_example_from_qbjs_source_document";
+
+    assert_eq!(
+        query(example, ".phoneNumber[1:] | .[].type"),
+        vec![json!("fax")]
+    );
+    assert_eq!(query(example, ".phoneNumber[:-2]"), vec![json!([])]);
+    assert_eq!(query(example, ".firstName[1:3]"), vec![json!("oh")]);
+}
+
+#[test]
+fn query_select() {
+    let example = "400_example_from_qbjs_source_document";
+
+    assert_eq!(
+        query(
+            example,
+            ".phoneNumber[] | select(.type == \"home\") | .number"
+        ),
+        vec![json!("212 555-1234")]
+    );
+    assert_eq!(
+        query(
+            example,
+            ".phoneNumber[] | select(.type != \"home\") | .number"
+        ),
+        vec![json!("646 555-4567")]
+    );
+    assert_eq!(
+        query(example, "select(.age == 25.0) | .lastName"),
+        vec![json!("Smith")]
+    );
+    assert!(query(example, "select(.age == 26)").is_empty());
+}
+
+#[test]
+fn query_keys_and_length() {
+    let example = "400_example_from_qbjs_source_document";
+
+    assert_eq!(
+        query(example, ".address | keys"),
+        vec![json!(["city", "postalCode", "state", "streetAddress"])]
+    );
+    assert_eq!(query(example, ".phoneNumber | keys"), vec![json!([0, 1])]);
+    assert_eq!(query(example, "length"), vec![json!(5)]);
+    assert_eq!(query(example, ".phoneNumber | length"), vec![json!(2)]);
+    assert_eq!(query(example, ".firstName | length"), vec![json!(4)]);
+    assert_eq!(query(example, ".age | length"), vec![json!(25)]);
+    assert_eq!(query(example, ".missing | length"), vec![json!(0)]);
+}
+
+#[test]
+fn query_empty_document() {
+    let query = Query::parse("keys").unwrap();
+    assert_eq!(query.evaluate(&[]).unwrap(), vec![json!([])]);
+}
+
+#[test]
+fn query_errors() {
+    let qbjs_content = read_qbjs_test_file("400_example_from_qbjs_source_document");
+    let evaluate = |expression| Query::parse(expression).unwrap().evaluate(&qbjs_content);
+
+    assert_eq!(
+        evaluate(".age.value"),
+        Err(QueryError::CannotIndex("number"))
+    );
+    assert_eq!(
+        evaluate(".address[0]"),
+        Err(QueryError::CannotIndex("object"))
+    );
+    assert_eq!(
+        evaluate(".firstName[]"),
+        Err(QueryError::CannotIterate("string"))
+    );
+    assert_eq!(
+        evaluate(".address[1:]"),
+        Err(QueryError::CannotSlice("object"))
+    );
+    assert_eq!(evaluate(".age | keys"), Err(QueryError::NoKeys("number")));
+
+    assert_eq!(Query::parse(""), Err(QueryError::InvalidExpression(0)));
+    assert_eq!(Query::parse(".a |"), Err(QueryError::InvalidExpression(4)));
+    assert_eq!(Query::parse(".a b"), Err(QueryError::InvalidExpression(3)));
+    assert_eq!(
+        Query::parse("select(.a)"),
+        Err(QueryError::InvalidExpression(9))
+    );
+}
@@ -0,0 +1,92 @@
+mod common;
+
+use common::read_qbjs_test_file;
+use qbjs_deserializer::qbjs::{self, analysis::AnalysisError, data, DeserializeError};
+
+use std::fs;
+
+#[test]
+fn scan_embedded_documents() {
+    let object_document = read_qbjs_test_file("400_example_from_qbjs_source_document");
+    let array_document = read_qbjs_test_file("104_string_array_document");
+
+    let mut data = b"garbage qbjs".to_vec();
+    let object_start = data.len();
+    data.extend_from_slice(&object_document);
+    // Header followed by an implausible base
+    data.extend_from_slice(b"qbjs\x01\x00\x00\x00\xff\xff\xff\xff\x00\x00\x00\x00\x00\x00\x00\x00");
+    data.extend_from_slice(&[0; 3]);
+    let array_start = data.len();
+    data.extend_from_slice(&array_document);
+    // Truncated document
+    data.extend_from_slice(&object_document[..32]);
+
+    let documents = qbjs::scan(&data).collect::<Vec<_>>();
+
+    assert_eq!(
+        documents,
+        vec![
+            (object_start, qbjs::deserialize_to_json(&object_document)),
+            (array_start, qbjs::deserialize_to_json(&array_document)),
+        ]
+    );
+}
+
+#[test]
+fn scan_reports_invalid_documents() {
+    let valid_document = read_qbjs_test_file("001_bool_true_object_document");
+    let mut invalid_document = valid_document.clone();
+    // The value type of the only entry is unknown
+    invalid_document[0x14] |= 0b111;
+    let data = [invalid_document.as_slice(), valid_document.as_slice()].concat();
+
+    let documents = qbjs::scan(&data).collect::<Vec<_>>();
+
+    assert_eq!(
+        documents,
+        vec![
+            (
+                0,
+                Err(DeserializeError::AnalysisError(AnalysisError::data(
+                    data::Error::UnknownQtValue
+                )))
+            ),
+            (
+                invalid_document.len(),
+                qbjs::deserialize_to_json(&valid_document)
+            ),
+        ]
+    );
+}
+
+#[test]
+fn scan_plugin() {
+    let elf = fs::read("tests/test_data/plugins/section.so").unwrap();
+
+    let documents = qbjs::scan(&elf).collect::<Vec<_>>();
+
+    assert_eq!(documents.len(), 1);
+    assert_eq!(
+        documents[0].1.as_ref().unwrap(),
+        &qbjs::plugin_metadata(&elf).unwrap()
+    );
+}
+
+#[test]
+fn scan_rejects_cyclic_containers() {
+    // Root array whose only value is an array at offset 0, which is the root array itself
+    let data =
+        b"qbjs\x01\x00\x00\x00\x10\x00\x00\x00\x02\x00\x00\x00\x0c\x00\x00\x00\x04\x00\x00\x00";
+
+    let documents = qbjs::scan(data).collect::<Vec<_>>();
+
+    assert_eq!(
+        documents,
+        vec![(
+            0,
+            Err(DeserializeError::AnalysisError(AnalysisError::data(
+                data::Error::InvalidContainerOffset
+            )))
+        )]
+    );
+}
@@ -0,0 +1,70 @@
+use qbjs_deserializer::qbjs::{self, QbjsDocument};
+
+use std::fs;
+
+macro_rules! create_test {
+    // Serializes the borrowed document with serde_json and compares it to deserialize_to_json's output
+    ($test_name:ident) => {
+        #[test]
+        fn $test_name() {
+            let test_name = stringify!($test_name);
+            let file_name = &test_name[1..];
+            let qbjs_file_path = format!("tests/test_data/qbjs_data/{}.qbjs", file_name);
+
+            let qbjs_content = fs::read(&qbjs_file_path)
+                .unwrap_or_else(|_| panic!("Couldn't read file: {}", qbjs_file_path));
+
+            let document = QbjsDocument::from_data(&qbjs_content).unwrap();
+            let serialized_json = serde_json::to_value(&document).unwrap();
+
+            assert_eq!(
+                serialized_json,
+                qbjs::deserialize_to_json(&qbjs_content).unwrap()
+            );
+        }
+    };
+}
+
+macro_rules! create_tests {
+    ($test_name:ident) => {
+        create_test!($test_name);
+    };
+    ($test_name:ident, $($test_names:ident),+) => {
+        create_test!($test_name);
+        create_tests!($($test_names),+);
+    };
+}
+
+create_tests!(
+    _011_japanese_string_object_document,
+    _012_various_values_object_document,
+    _105_various_values_array_document,
+    _205_tree_array_in_array_document,
+    _207_tree_empty_arrays_in_object_document,
+    _300_empty_document,
+    _400_example_from_qbjs_source_document
+);
+
+#[test]
+fn document_analysis_errors_are_reported() {
+    let qbjs_content = fs::read("tests/test_data/qbjs_data/301_insufficient_data_document.qbjs")
+        .expect("Couldn't read file");
+
+    assert_eq!(
+        QbjsDocument::from_data(&qbjs_content).unwrap_err(),
+        qbjs::DeserializeError::InsufficientData
+    );
+}
+
+#[test]
+fn document_read_errors_are_reported() {
+    // Root array holding a latin1 string whose size goes past the end of the data
+    let qbjs_content = b"qbjs\x01\x00\x00\x00\x14\x00\x00\x00\x02\x00\x00\x00\x10\x00\x00\x00\xff\x00ab\x8b\x01\x00\x00";
+
+    let document = QbjsDocument::from_data(qbjs_content).unwrap();
+
+    assert_eq!(
+        serde_json::to_value(&document).unwrap_err().to_string(),
+        "failed to read qbjs value: a latin1 string is past the end of the data"
+    );
+}
@@ -0,0 +1,89 @@
+mod common;
+
+use common::read_qbjs_test_file;
+use qbjs_deserializer::qbjs::{self, DeserializeError, SettingsError};
+
+use serde_json::json;
+use std::io;
+
+// Written with the escaping rules of QSettings' INI format, without INI codec
+const SETTINGS_FILE: &str = "tests/test_data/settings/settings.ini";
+
+#[test]
+fn settings_to_json() {
+    let settings = qbjs::settings_file_to_json(SETTINGS_FILE).unwrap();
+
+    let geometry = qbjs::deserialize_to_json(&read_qbjs_test_file(
+        "400_example_from_qbjs_source_document",
+    ))
+    .unwrap();
+    let metadata =
+        qbjs::deserialize_to_json(&read_qbjs_test_file("012_various_values_object_document"))
+            .unwrap();
+    assert_eq!(
+        settings,
+        json!({
+            "geometry": geometry,
+            "name": "main window",
+            "recentFiles": ["/tmp/a.txt", "/tmp/b,c.txt", "/tmp/d.txt"],
+            "General/state": null,
+            "plugins/xcb/metadata": metadata,
+            "plugins/xcb/enabled": "true",
+            "plugins/notes": "@first line\nsecond line ; with \"quotes\"",
+            "plugins/token": "secret",
+            "plugins/greeting": "%U263A caf\u{e9}",
+            "plugins/\u{263a}key": "smile",
+            "plugins/long": "first   second"
+        })
+    );
+}
+
+#[test]
+fn settings_values() {
+    let settings = qbjs::settings_to_json(
+        b"a = value ; comment\n\
+          b=\"  quoted  \" \n\
+          c=\\0\\x31\\101\n\
+          d=\n\
+          e=@@@\n\
+          f=@ByteArray()\n",
+    )
+    .unwrap();
+
+    assert_eq!(
+        settings,
+        json!({
+            "a": "value",
+            "b": "  quoted  ",
+            "c": "\u{0}1A",
+            "d": "",
+            "e": "@@",
+            "f": ""
+        })
+    );
+}
+
+#[test]
+fn settings_errors() {
+    assert_eq!(
+        qbjs::settings_file_to_json("tests/test_data/settings/missing.ini"),
+        Err(SettingsError::IoError(io::ErrorKind::NotFound))
+    );
+    assert_eq!(
+        qbjs::settings_to_json(b"a=1\n\n[group\nb=2\n"),
+        Err(SettingsError::InvalidLine(3))
+    );
+    assert_eq!(
+        qbjs::settings_to_json(b"a=1\\\n1\nb\n"),
+        Err(SettingsError::InvalidLine(3))
+    );
+    assert_eq!(
+        qbjs::settings_to_json(b"[group]\nstate=@ByteArray(qbjs\\x1\\0\\0\\0)\n"),
+        Err(SettingsError::DeserializeError {
+            key: "group/state".to_owned(),
+            error: DeserializeError::AnalysisError(qbjs::analysis::AnalysisError::metadata(
+                qbjs::analysis::metadata::Error::InvalidContainerBaseLength
+            ))
+        })
+    );
+}
@@ -0,0 +1,110 @@
+mod common;
+
+use common::read_qbjs_test_file;
+use qbjs_deserializer::qbjs::{self, Recovered, RecoveredValue};
+
+use serde_json::json;
+
+#[test]
+fn no_slack_in_documents_written_by_qt() {
+    let file_names = [
+        "012_various_values_object_document",
+        "105_various_values_array_document",
+        "205_tree_array_in_array_document",
+        "208_tree_empty_objects_in_object_document",
+        "400_example_from_qbjs_source_document",
+    ];
+
+    for file_name in file_names {
+        let qbjs_content = read_qbjs_test_file(file_name);
+        let report = qbjs::analyze_slack(&qbjs_content).unwrap();
+
+        assert_eq!(report.referenced_bytes, qbjs_content.len(), "{}", file_name);
+        assert!(report.gaps.is_empty(), "{}", file_name);
+        assert!(report.recovered.is_empty(), "{}", file_name);
+    }
+}
+
+#[test]
+fn recover_removed_entry() {
+    let mut qbjs_content = read_qbjs_test_file("206_objects_in_array_document");
+    // The first object loses its second entry: its length goes from 2 to 1
+    qbjs_content[0x18] = (1 << 1) | 1;
+
+    let report = qbjs::analyze_slack(&qbjs_content).unwrap();
+
+    assert_eq!(report.gaps, vec![0x38..0x50, 0x54..0x58]);
+    assert_eq!(report.referenced_bytes, qbjs_content.len() - 0x1c);
+    assert_eq!(
+        report.recovered,
+        vec![
+            RecoveredValue {
+                range: 0x38..0x44,
+                recovered: Recovered::Entry {
+                    key: "key 2".to_owned(),
+                    qt_value_type: 3,
+                    value: None,
+                },
+            },
+            RecoveredValue {
+                range: 0x44..0x50,
+                recovered: Recovered::Latin1String("value 2".to_owned()),
+            },
+        ]
+    );
+}
+
+#[test]
+fn recover_stale_container() {
+    let mut qbjs_content = read_qbjs_test_file("206_objects_in_array_document");
+    let document_length = qbjs_content.len();
+    // Copy of the first object after the end of the root
+    let stale_object = qbjs_content[0x14..0x58].to_vec();
+    qbjs_content.extend_from_slice(&stale_object);
+
+    let report = qbjs::analyze_slack(&qbjs_content).unwrap();
+
+    assert_eq!(
+        report.gaps,
+        vec![document_length..(document_length + stale_object.len())]
+    );
+    assert_eq!(
+        report.recovered,
+        vec![RecoveredValue {
+            range: document_length..(document_length + stale_object.len()),
+            recovered: Recovered::Container(json!({"key 1": "value 1", "key 2": "value 2"})),
+        }]
+    );
+}
+
+#[test]
+fn recover_values_stored_in_headers() {
+    let mut qbjs_content = read_qbjs_test_file("012_various_values_object_document");
+    // The object loses its first entry, "bool value key 1": true
+    qbjs_content[0x0c] = (13 << 1) | 1;
+    let offset_table = 0x08 + 0x228;
+    qbjs_content.copy_within((offset_table + 4)..(offset_table + 14 * 4), offset_table);
+
+    let report = qbjs::analyze_slack(&qbjs_content).unwrap();
+
+    assert_eq!(report.gaps, vec![0x14..0x2c, 0x264..0x268]);
+    assert_eq!(
+        report.recovered[0],
+        RecoveredValue {
+            range: 0x14..0x2c,
+            recovered: Recovered::Entry {
+                key: "bool value key 1".to_owned(),
+                qt_value_type: 1,
+                value: Some(json!(true)),
+            },
+        }
+    );
+}
+
+#[test]
+fn slack_of_empty_document() {
+    let report = qbjs::analyze_slack(&[]).unwrap();
+
+    assert_eq!(report.referenced_bytes, 0);
+    assert!(report.gaps.is_empty());
+}
@@ -0,0 +1,66 @@
+mod common;
+
+use common::read_qbjs_test_file;
+use qbjs_deserializer::qbjs::{self, Span};
+
+#[test]
+fn spans_of_objects_in_array() {
+    let qbjs_content = read_qbjs_test_file("206_objects_in_array_document");
+    let (value, spans) = qbjs::deserialize_with_spans(&qbjs_content).unwrap();
+
+    assert_eq!(value, qbjs::deserialize_to_json(&qbjs_content).unwrap());
+    assert_eq!(
+        spans.keys().collect::<Vec<_>>(),
+        vec!["", "/0", "/0/key 1", "/0/key 2", "/1", "/1/key 3", "/1/key 4"]
+    );
+
+    assert_eq!(
+        spans[""],
+        Span {
+            header: None,
+            key: None,
+            payload: 8..164,
+        }
+    );
+    assert_eq!(
+        spans["/0"],
+        Span {
+            header: Some(156..160),
+            key: None,
+            payload: 20..88,
+        }
+    );
+    assert_eq!(
+        spans["/0/key 1"],
+        Span {
+            header: Some(32..36),
+            key: Some(36..43),
+            payload: 44..53,
+        }
+    );
+    assert_eq!(&qbjs_content[38..43], b"key 1");
+    assert_eq!(&qbjs_content[46..53], b"value 1");
+}
+
+#[test]
+fn spans_cover_every_value() {
+    let qbjs_content = read_qbjs_test_file("400_example_from_qbjs_source_document");
+    let (value, spans) = qbjs::deserialize_with_spans(&qbjs_content).unwrap();
+
+    for (pointer, span) in &spans {
+        assert!(value.pointer(pointer).is_some(), "{} not found", pointer);
+        assert!(span.payload.end <= qbjs_content.len());
+    }
+    assert_eq!(spans.len(), 16);
+
+    // Self contained numbers are stored in their header
+    assert_eq!(spans["/age"].header, Some(spans["/age"].payload.clone()));
+}
+
+#[test]
+fn empty_document_has_no_span() {
+    let (value, spans) = qbjs::deserialize_with_spans(&[]).unwrap();
+
+    assert_eq!(value, serde_json::json!({}));
+    assert!(spans.is_empty());
+}
@@ -0,0 +1,121 @@
+mod common;
+
+use common::read_qbjs_test_file;
+use qbjs_deserializer::qbjs::{
+    self, analysis::AnalysisError, data, stats::ByteUsage, stats::ContainerStats,
+    stats::ValueCounts, DeserializeError,
+};
+
+fn byte_usage_sum(bytes: &ByteUsage) -> usize {
+    bytes.header
+        + bytes.container_bases
+        + bytes.tables
+        + bytes.entry_headers
+        + bytes.keys
+        + bytes.strings
+        + bytes.doubles
+        + bytes.padding
+        + bytes.wasted
+}
+
+#[test]
+fn stats_of_example_document() {
+    let qbjs_content = read_qbjs_test_file("400_example_from_qbjs_source_document");
+    let stats = qbjs::stats(&qbjs_content).unwrap();
+
+    assert_eq!(
+        stats.values,
+        ValueCounts {
+            self_contained_numbers: 1,
+            latin1_strings: 10,
+            arrays: 1,
+            objects: 4,
+            ..ValueCounts::default()
+        }
+    );
+    assert_eq!(stats.latin1_keys, 13);
+    assert_eq!(stats.utf16_keys, 0);
+    assert_eq!(stats.max_depth, 3);
+
+    assert_eq!(stats.largest_containers.len(), 5);
+    assert_eq!(
+        stats.largest_containers[0],
+        ContainerStats {
+            pointer: "".to_owned(),
+            is_object: true,
+            length: 5,
+            size: qbjs_content.len() - 8,
+        }
+    );
+    assert_eq!(
+        stats.largest_containers[1],
+        ContainerStats {
+            pointer: "/phoneNumber".to_owned(),
+            is_object: false,
+            length: 2,
+            size: 156,
+        }
+    );
+
+    assert_eq!(stats.bytes.total, qbjs_content.len());
+    assert_eq!(stats.bytes.container_bases, 5 * 12);
+    assert_eq!(stats.bytes.entry_headers, 13 * 4);
+    assert_eq!(stats.bytes.wasted, 0);
+    assert_eq!(byte_usage_sum(&stats.bytes), stats.bytes.total);
+}
+
+#[test]
+fn stats_of_various_values() {
+    let qbjs_content = read_qbjs_test_file("012_various_values_object_document");
+    let stats = qbjs::stats(&qbjs_content).unwrap();
+
+    assert_eq!(
+        stats.values,
+        ValueCounts {
+            nulls: 1,
+            bools: 2,
+            self_contained_numbers: 2,
+            doubles: 4,
+            latin1_strings: 4,
+            utf16_strings: 1,
+            arrays: 0,
+            objects: 1,
+        }
+    );
+    assert_eq!(stats.latin1_keys, 13);
+    assert_eq!(stats.utf16_keys, 1);
+    assert_eq!(stats.bytes.doubles, 4 * 8);
+    assert_eq!(byte_usage_sum(&stats.bytes), stats.bytes.total);
+}
+
+#[test]
+fn stats_count_wasted_bytes() {
+    let mut qbjs_content = read_qbjs_test_file("206_objects_in_array_document");
+    // The first object loses its second entry
+    qbjs_content[0x18] = (1 << 1) | 1;
+    let stats = qbjs::stats(&qbjs_content).unwrap();
+
+    assert_eq!(stats.bytes.wasted, 0x1c);
+    assert_eq!(byte_usage_sum(&stats.bytes), stats.bytes.total);
+}
+
+#[test]
+fn stats_of_empty_document() {
+    let stats = qbjs::stats(&[]).unwrap();
+
+    assert_eq!(stats, qbjs::Stats::default());
+}
+
+#[test]
+fn stats_of_cyclic_document() {
+    // Root array whose only value is an array at offset 0, which is the root array itself
+    let qbjs_content =
+        b"qbjs\x01\x00\x00\x00\x10\x00\x00\x00\x02\x00\x00\x00\x0c\x00\x00\x00\x04\x00\x00\x00";
+
+    assert_eq!(
+        qbjs::stats(qbjs_content),
+        Err(DeserializeError::AnalysisError(AnalysisError::data(
+            data::Error::InvalidContainerOffset
+        )))
+    );
+}
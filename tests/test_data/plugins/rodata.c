@@ -0,0 +1,12 @@
+// Built with: gcc -shared -fPIC -Os -s -o rodata.so rodata.c
+// The array has the layout moc writes for Q_PLUGIN_METADATA
+static const unsigned char qt_pluginMetaData[] = {
+    'Q', 'T', 'M', 'E', 'T', 'A', 'D', 'A', 'T', 'A', ' ', ' ',
+    0x71, 0x62, 0x6a, 0x73, 0x01, 0x00, 0x00, 0x00, 0xf4, 0x00, 0x00, 0x00, 0x0b, 0x00, 0x00, 0x00, 0xe0, 0x00, 0x00, 0x00, 0x1b, 0x03, 0x00, 0x00, 0x03, 0x00, 0x49, 0x49, 0x44, 0x00, 0x00, 0x00, 0x3e, 0x00, 0x6f, 0x72, 0x67, 0x2e, 0x71, 0x74, 0x2d, 0x70, 0x72, 0x6f, 0x6a, 0x65, 0x63, 0x74, 0x2e, 0x51, 0x74, 0x2e, 0x51, 0x50, 0x41, 0x2e, 0x51, 0x50, 0x6c, 0x61, 0x74, 0x66, 0x6f, 0x72, 0x6d, 0x49, 0x6e, 0x74, 0x65, 0x67, 0x72, 0x61, 0x74, 0x69, 0x6f, 0x6e, 0x46, 0x61, 0x63, 0x74, 0x6f, 0x72, 0x79, 0x49, 0x6e, 0x74, 0x65, 0x72, 0x66, 0x61, 0x63, 0x65, 0x2e, 0x35, 0x2e, 0x33, 0x15, 0x0d, 0x00, 0x00, 0x08, 0x00, 0x4d, 0x65, 0x74, 0x61, 0x44, 0x61, 0x74, 0x61, 0x00, 0x00, 0x34, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x30, 0x00, 0x00, 0x00, 0x14, 0x03, 0x00, 0x00, 0x04, 0x00, 0x4b, 0x65, 0x79, 0x73, 0x00, 0x00, 0x18, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x14, 0x00, 0x00, 0x00, 0x03, 0x00, 0x78, 0x63, 0x62, 0x00, 0x00, 0x00, 0x8b, 0x01, 0x00, 0x00, 0x0c, 0x00, 0x00, 0x00, 0x9b, 0x15, 0x00, 0x00, 0x09, 0x00, 0x63, 0x6c, 0x61, 0x73, 0x73, 0x4e, 0x61, 0x6d, 0x65, 0x00, 0x15, 0x00, 0x51, 0x58, 0x63, 0x62, 0x49, 0x6e, 0x74, 0x65, 0x67, 0x72, 0x61, 0x74, 0x69, 0x6f, 0x6e, 0x50, 0x6c, 0x75, 0x67, 0x69, 0x6e, 0x00, 0x11, 0x00, 0x00, 0x00, 0x05, 0x00, 0x64, 0x65, 0x62, 0x75, 0x67, 0x00, 0x1a, 0xe0, 0xa1, 0x00, 0x07, 0x00, 0x76, 0x65, 0x72, 0x73, 0x69, 0x6f, 0x6e, 0x00, 0x00, 0x00, 0x0c, 0x00, 0x00, 0x00, 0x58, 0x00, 0x00, 0x00, 0x9c, 0x00, 0x00, 0x00, 0xc4, 0x00, 0x00, 0x00, 0xd0, 0x00, 0x00, 0x00
+};
+
+const unsigned char *qt_plugin_query_metadata(void) { return qt_pluginMetaData; }
+
+// Same as QtCore, which looks for the marker in plugins
+const char qt_metadata_marker[] = "QTMETADATA  ";
+const char *marker(void) { return qt_metadata_marker; }
@@ -0,0 +1,94 @@
+mod common;
+
+use common::read_qbjs_test_file;
+use qbjs_deserializer::qbjs::{self, text, DeserializeError, TextDecodeError};
+
+use serde_json::json;
+use std::fs;
+
+const EXAMPLE: &str = "400_example_from_qbjs_source_document";
+
+fn read_text_test_file(file_name: &str) -> String {
+    let file_path = format!("tests/test_data/text/{}", file_name);
+    fs::read_to_string(&file_path).unwrap_or_else(|_| panic!("Couldn't read file: {}", file_path))
+}
+
+#[test]
+fn deserialize_text_files() {
+    let expected = qbjs::deserialize_to_json(&read_qbjs_test_file(EXAMPLE));
+
+    // Lines wrapped at 76 characters, as written by base64 and xxd -p
+    let base64 = read_text_test_file(&format!("{}.b64", EXAMPLE));
+    assert_eq!(
+        qbjs::deserialize_base64(&base64).ok(),
+        expected.clone().ok()
+    );
+
+    let hex = read_text_test_file(&format!("{}.hex", EXAMPLE));
+    assert_eq!(qbjs::deserialize_hex(&hex).ok(), expected.clone().ok());
+    assert_eq!(
+        qbjs::deserialize_hex(&hex.to_uppercase()).ok(),
+        expected.ok()
+    );
+}
+
+#[test]
+fn base64_variants() {
+    let padded = "cWJqcwEAAAAkAAAAAwAAACAAAAAQAAAADgBudWxsIHZhbHVlIGtleQwAAAA=";
+    let expected = json!({"null value key": null});
+
+    assert_eq!(qbjs::deserialize_base64(padded), Ok(expected.clone()));
+    assert_eq!(
+        qbjs::deserialize_base64(padded.trim_end_matches('=')),
+        Ok(expected)
+    );
+
+    assert_eq!(text::decode_base64(b"+/8="), Ok(vec![0xfb, 0xff]));
+    assert_eq!(text::decode_base64(b"-_8"), Ok(vec![0xfb, 0xff]));
+    assert_eq!(text::decode_base64(b"QQ=="), Ok(b"A".to_vec()));
+    assert_eq!(text::decode_base64(b"QQ"), Ok(b"A".to_vec()));
+    assert_eq!(text::decode_base64(b"QUJD\r\nQUI="), Ok(b"ABCAB".to_vec()));
+    assert_eq!(text::decode_base64(b""), Ok(Vec::new()));
+}
+
+#[test]
+fn base64_errors() {
+    assert_eq!(
+        text::decode_base64(b"QUJD QU*="),
+        Err(TextDecodeError::InvalidCharacter(7))
+    );
+    assert_eq!(
+        text::decode_base64(b"QUJDQ==="),
+        Err(TextDecodeError::InvalidPadding(5))
+    );
+    assert_eq!(
+        text::decode_base64(b"QQ==QQ=="),
+        Err(TextDecodeError::InvalidPadding(4))
+    );
+    assert_eq!(
+        text::decode_base64(b"QUJDQ"),
+        Err(TextDecodeError::Truncated(4))
+    );
+    assert_eq!(
+        text::decode_base64(b"QUJDQQ="),
+        Err(TextDecodeError::Truncated(4))
+    );
+    assert_eq!(
+        qbjs::deserialize_base64("cWJqcw"),
+        Err(TextDecodeError::DeserializeError(
+            DeserializeError::InsufficientData
+        ))
+    );
+}
+
+#[test]
+fn hex_errors() {
+    assert_eq!(
+        text::decode_hex(b"71 62 6g"),
+        Err(TextDecodeError::InvalidCharacter(7))
+    );
+    assert_eq!(
+        text::decode_hex(b"71 62\n6"),
+        Err(TextDecodeError::Truncated(6))
+    );
+}
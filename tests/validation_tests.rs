@@ -1,9 +1,5 @@
-use qbjs_deserializer;
-
 use std::fs;
 
-use serde_json;
-
 macro_rules! create_test {
     // This macro takes an argument of designator `ident` and creates a test `$test_name`.
     // It uses the test_name (minus the first charcter) to look for file to test and file to read to know what json value to expect
@@ -0,0 +1,141 @@
+mod common;
+
+use common::read_qbjs_test_file;
+use qbjs_deserializer::analysis::{analyze_document, data};
+use qbjs_deserializer::qbjs::{visit, Visitor};
+
+#[derive(Default)]
+struct CountingVisitor {
+    nulls: usize,
+    bools: usize,
+    self_contained_numbers: usize,
+    numbers: usize,
+    latin1_strings: usize,
+    utf16_strings: usize,
+    arrays: usize,
+    objects: usize,
+    keys: usize,
+    depth: usize,
+    max_depth: usize,
+    header_positions: Vec<usize>,
+}
+
+impl Visitor for CountingVisitor {
+    type Error = ();
+
+    fn visit_null(&mut self, _position: usize) -> Result<(), ()> {
+        self.nulls += 1;
+        Ok(())
+    }
+
+    fn visit_bool(&mut self, _position: usize) -> Result<(), ()> {
+        self.bools += 1;
+        Ok(())
+    }
+
+    fn visit_self_contained_number(&mut self, _position: usize) -> Result<(), ()> {
+        self.self_contained_numbers += 1;
+        Ok(())
+    }
+
+    fn visit_number(&mut self, _bytefield: &data::ByteField) -> Result<(), ()> {
+        self.numbers += 1;
+        Ok(())
+    }
+
+    fn visit_latin1_string(&mut self, _bytefield: &data::ByteField) -> Result<(), ()> {
+        self.latin1_strings += 1;
+        Ok(())
+    }
+
+    fn visit_utf16_string(&mut self, _bytefield: &data::ByteField) -> Result<(), ()> {
+        self.utf16_strings += 1;
+        Ok(())
+    }
+
+    fn enter_array(&mut self, _array: &data::Array) -> Result<(), ()> {
+        self.arrays += 1;
+        self.depth += 1;
+        self.max_depth = self.max_depth.max(self.depth);
+        Ok(())
+    }
+
+    fn enter_array_value(&mut self, _index: usize, header_position: usize) -> Result<(), ()> {
+        self.header_positions.push(header_position);
+        Ok(())
+    }
+
+    fn leave_array(&mut self, _array: &data::Array) -> Result<(), ()> {
+        self.depth -= 1;
+        Ok(())
+    }
+
+    fn enter_object(&mut self, _object: &data::Object) -> Result<(), ()> {
+        self.objects += 1;
+        self.depth += 1;
+        self.max_depth = self.max_depth.max(self.depth);
+        Ok(())
+    }
+
+    fn enter_entry(&mut self, _index: usize, entry: &data::Entry) -> Result<(), ()> {
+        self.keys += 1;
+        self.header_positions.push(entry.position);
+        Ok(())
+    }
+
+    fn leave_object(&mut self, _object: &data::Object) -> Result<(), ()> {
+        self.depth -= 1;
+        Ok(())
+    }
+}
+
+#[test]
+fn visitor_counts_every_node() {
+    let qbjs_content = read_qbjs_test_file("400_example_from_qbjs_source_document");
+    let document = analyze_document(&qbjs_content).unwrap();
+
+    let mut visitor = CountingVisitor::default();
+    visit::walk(&document, &mut visitor).unwrap();
+
+    assert_eq!(visitor.nulls, 0);
+    assert_eq!(visitor.bools, 0);
+    assert_eq!(visitor.self_contained_numbers, 1);
+    assert_eq!(visitor.numbers, 0);
+    assert_eq!(visitor.latin1_strings, 10);
+    assert_eq!(visitor.utf16_strings, 0);
+    assert_eq!(visitor.arrays, 1);
+    assert_eq!(visitor.objects, 4);
+    assert_eq!(visitor.keys, 13);
+    assert_eq!(visitor.max_depth, 3);
+
+    // Every value header is a 4 bytes aligned position of the document
+    assert_eq!(visitor.header_positions.len(), 15);
+    assert!(visitor
+        .header_positions
+        .iter()
+        .all(|position| position % 4 == 0 && *position < qbjs_content.len()));
+}
+
+struct FailingVisitor {
+    visited_strings: usize,
+}
+
+impl Visitor for FailingVisitor {
+    type Error = String;
+
+    fn visit_latin1_string(&mut self, bytefield: &data::ByteField) -> Result<(), String> {
+        self.visited_strings += 1;
+        Err(format!("string at {:?}", bytefield.range))
+    }
+}
+
+#[test]
+fn walk_stops_at_first_error() {
+    let qbjs_content = read_qbjs_test_file("010_strings_object_document");
+    let document = analyze_document(&qbjs_content).unwrap();
+
+    let mut visitor = FailingVisitor { visited_strings: 0 };
+
+    assert!(visit::walk(&document, &mut visitor).is_err());
+    assert_eq!(visitor.visited_strings, 1);
+}
@@ -0,0 +1,91 @@
+mod common;
+
+use common::read_qbjs_test_file;
+use qbjs_deserializer::qbjs::{self, WriteOptions};
+
+fn write_json_to_vec(qbjs_content: &[u8], options: WriteOptions) -> Vec<u8> {
+    let mut output = Vec::new();
+    qbjs::write_json(qbjs_content, &mut output, options).unwrap();
+    output
+}
+
+macro_rules! create_test {
+    // Checks the written JSON text parses back to the same value as the one returned by deserialize_to_json
+    ($test_name:ident) => {
+        #[test]
+        fn $test_name() {
+            let test_name = stringify!($test_name);
+            let file_name = &test_name[1..];
+
+            let qbjs_content = read_qbjs_test_file(file_name);
+            let expected_json = qbjs::deserialize_to_json(&qbjs_content).unwrap();
+
+            for pretty in [false, true] {
+                let written_json = write_json_to_vec(
+                    &qbjs_content,
+                    WriteOptions {
+                        pretty,
+                        ..WriteOptions::default()
+                    },
+                );
+                let parsed_json =
+                    serde_json::from_slice::<serde_json::Value>(&written_json).unwrap();
+                assert_eq!(parsed_json, expected_json);
+            }
+        }
+    };
+}
+
+macro_rules! create_tests {
+    ($test_name:ident) => {
+        create_test!($test_name);
+    };
+    ($test_name:ident, $($test_names:ident),+) => {
+        create_test!($test_name);
+        create_tests!($($test_names),+);
+    };
+}
+
+create_tests!(
+    _011_japanese_string_object_document,
+    _012_various_values_object_document,
+    _105_various_values_array_document,
+    _205_tree_array_in_array_document,
+    _208_tree_empty_objects_in_object_document,
+    _300_empty_document,
+    _400_example_from_qbjs_source_document
+);
+
+#[test]
+fn write_json_matches_serde_json_text() {
+    let qbjs_content = read_qbjs_test_file("206_objects_in_array_document");
+    let expected_json = qbjs::deserialize_to_json(&qbjs_content).unwrap();
+
+    let compact = write_json_to_vec(&qbjs_content, WriteOptions::default());
+    assert_eq!(compact, serde_json::to_vec(&expected_json).unwrap());
+
+    let pretty = write_json_to_vec(
+        &qbjs_content,
+        WriteOptions {
+            pretty: true,
+            ..WriteOptions::default()
+        },
+    );
+    assert_eq!(pretty, serde_json::to_vec_pretty(&expected_json).unwrap());
+}
+
+#[test]
+fn write_json_reports_deserialize_errors() {
+    let qbjs_content = read_qbjs_test_file("302_invalid_qbjs_tag_document");
+    let mut output = Vec::new();
+
+    assert_eq!(
+        qbjs::write_json(&qbjs_content, &mut output, WriteOptions::default()),
+        Err(qbjs::DeserializeError::AnalysisError(
+            qbjs_deserializer::analysis::AnalysisError::HeaderAnalysisError(
+                qbjs_deserializer::analysis::header::Error::InvalidTag
+            )
+        ))
+    );
+    assert!(output.is_empty());
+}